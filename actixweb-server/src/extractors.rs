@@ -0,0 +1,73 @@
+use std::future::{ready, Ready};
+
+use actix_web::{dev::Payload, http::header, web, Error, FromRequest, HttpRequest};
+use shared::{
+    auth::{admin_claims_from_auth_header, claims_from_auth_header, Claims, JwtKeys},
+    error::{AppError, AppResult},
+    revocation::TokenRevocations,
+};
+
+use crate::AppState;
+
+/// The caller behind a valid, unrevoked bearer token, or auth cookie when
+/// there is no `Authorization` header. Handlers that take this extractor
+/// answer 401 before running when the token is missing.
+pub struct AuthUser(pub Claims);
+
+impl FromRequest for AuthUser {
+    type Error = Error;
+    type Future = Ready<Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, _: &mut Payload) -> Self::Future {
+        ready(resolve_claims(req, claims_from_auth_header).map(AuthUser))
+    }
+}
+
+/// The caller when the request carries a valid token, for handlers that
+/// also serve anonymous callers. Never rejects.
+pub struct MaybeAuthUser(pub Option<Claims>);
+
+impl FromRequest for MaybeAuthUser {
+    type Error = Error;
+    type Future = Ready<Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, _: &mut Payload) -> Self::Future {
+        ready(Ok(MaybeAuthUser(resolve_claims(req, claims_from_auth_header).ok())))
+    }
+}
+
+/// Like `AuthUser`, but the token must also carry the admin role (403
+/// otherwise).
+pub struct AdminUser(pub Claims);
+
+impl FromRequest for AdminUser {
+    type Error = Error;
+    type Future = Ready<Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, _: &mut Payload) -> Self::Future {
+        ready(resolve_claims(req, admin_claims_from_auth_header).map(AdminUser))
+    }
+}
+
+fn resolve_claims(
+    req: &HttpRequest,
+    resolve: fn(&JwtKeys, &TokenRevocations, Option<&str>, Option<&str>) -> AppResult<Claims>,
+) -> Result<Claims, Error> {
+    let state = req
+        .app_data::<web::Data<AppState>>()
+        .ok_or_else(|| AppError::Internal(anyhow::anyhow!("application state is not configured")))?;
+
+    let cookie = req.cookie(&state.settings.auth_cookie_name);
+    Ok(resolve(
+        &state.jwt_keys,
+        &state.revocations,
+        authorization(req),
+        cookie.as_ref().map(|cookie| cookie.value()),
+    )?)
+}
+
+pub fn authorization(req: &HttpRequest) -> Option<&str> {
+    req.headers()
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+}
\ No newline at end of file
@@ -0,0 +1,858 @@
+mod extractors;
+mod middleware;
+
+use actix_web::body::MessageBody;
+use actix_web::dev::{ServiceFactory, ServiceRequest, ServiceResponse};
+use actix_web::{
+    guard, http::{header, Method, StatusCode}, web, App, HttpMessage, HttpRequest, HttpResponse, HttpServer, Resource, Responder, Result, Route, middleware::{from_fn, Logger},
+};
+use actix_web_lab::sse;
+use async_graphql_actix_web::{GraphQLBatchRequest, GraphQLResponse, GraphQLSubscription};
+use futures::StreamExt;
+use shared::{
+    models::*,
+    auth::*,
+    build_info::BuildInfo,
+    client_ip::{ClientIp, TrustedProxies},
+    config::{Config, ServerSettings},
+    dedup::CreateDedup,
+    db::{check_database, connect_pool, run_migrations, PoolStats, QueryTimer},
+    error::{AppError, AppResult},
+    id::{init_id_kind, Id},
+    pagination::{Paginated, PAGINATION_CLAMPED_HEADER},
+    graphql::*,
+    repository::{ProductChange, ProductRepository, UserRepository, WebhookRepository},
+    store::ProductStore,
+    telemetry::{in_phase_sync, init_tracing, RequestId, SubscriptionConnection},
+    timestamp,
+    validate::Validate,
+    events::{DomainEvent, EventBus, EventMetrics},
+    export::{product_csv, PRODUCT_CSV_DISPOSITION},
+    low_stock::LowStockNotifier,
+    revocation::TokenRevocations,
+    security_headers::SecurityHeaders,
+    shopify::{ShopifyClient, ShopifyWebhookHeaders, ShopifyWebhookReceiver},
+    shutdown::{begin_shutdown, InFlightRequests, ShuttingDown},
+    storage::ObjectStorage,
+    throttle::LoginThrottle,
+};
+use extractors::{authorization, AdminUser, AuthUser, MaybeAuthUser};
+use sqlx::PgPool;
+use serde_json::json;
+use std::net::{IpAddr, Ipv4Addr};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::Semaphore;
+
+pub struct AppState {
+    pub schema: GraphQLSchema,
+    pub pool: PgPool,
+    pub products: Arc<dyn ProductStore>,
+    pub users: UserRepository,
+    pub events: EventBus,
+    pub event_metrics: EventMetrics,
+    pub login: LoginService,
+    pub settings: ServerSettings,
+    pub jwt_keys: Arc<JwtKeys>,
+    pub revocations: Arc<TokenRevocations>,
+    pub shopify: Option<ShopifyClient>,
+    pub shopify_webhooks: ShopifyWebhookReceiver,
+    pub storage: Option<ObjectStorage>,
+    pub create_dedup: Arc<CreateDedup>,
+    pub shutting_down: ShuttingDown,
+}
+
+impl AppState {
+    /// Everything the handlers share, with `products` as the catalog so
+    /// tests can swap in an in-memory store.
+    pub fn new(config: &Config, pool: PgPool, products: Arc<dyn ProductStore>) -> anyhow::Result<Self> {
+        let settings = config.server.clone();
+        let timer = QueryTimer::from_settings(&config.database);
+        let events = EventBus::new();
+        let event_metrics = EventMetrics::listen(&events);
+        LowStockNotifier::new(settings.low_stock_webhook_url.clone(), settings.low_stock_threshold).listen(&events);
+        let jwt_keys = Arc::new(config.jwt_keys.clone());
+        let revocations = Arc::new(TokenRevocations::new());
+        let login_service = LoginService::new(
+            UserRepository::new(pool.clone(), timer),
+            Arc::new(LoginThrottle::new(settings.login_max_failures, settings.login_lockout)),
+            jwt_keys.clone(),
+            revocations.clone(),
+            settings.bcrypt_cost,
+        );
+        let create_dedup = Arc::new(CreateDedup::new(settings.create_dedup_window));
+        let schema = build_schema(
+            products.clone(),
+            UserRepository::new(pool.clone(), timer),
+            events.clone(),
+            &settings,
+            ServerInfo::new("actix-web"),
+            login_service.clone(),
+            create_dedup.clone(),
+        );
+        Ok(Self {
+            schema,
+            products,
+            users: UserRepository::new(pool.clone(), timer),
+            shopify_webhooks: ShopifyWebhookReceiver::new(
+                WebhookRepository::new(pool.clone(), timer),
+                config.shopify_webhook_secret.clone(),
+            ),
+            pool,
+            events,
+            event_metrics,
+            login: login_service,
+            settings,
+            jwt_keys,
+            revocations,
+            shopify: config.shopify.clone().map(ShopifyClient::new).transpose()?,
+            storage: config.storage.clone().map(ObjectStorage::new),
+            create_dedup,
+            shutting_down: ShuttingDown::new(),
+        })
+    }
+}
+
+/// What `app` needs besides the state. Built once and cloned into every
+/// worker, so all workers share the same permits and in-flight count.
+#[derive(Clone)]
+pub struct AppOptions {
+    body_limit: usize,
+    request_timeout: Duration,
+    concurrency_limit: Option<Arc<Semaphore>>,
+    trusted_proxies: Arc<TrustedProxies>,
+    security_headers: Arc<SecurityHeaders>,
+    cors_origins: Vec<String>,
+    in_flight: InFlightRequests,
+}
+
+impl AppOptions {
+    pub fn new(config: &Config, in_flight: InFlightRequests) -> Self {
+        Self {
+            body_limit: config.body_limit,
+            request_timeout: config.server.request_timeout,
+            concurrency_limit: config.server.max_concurrent_requests.map(|max| Arc::new(Semaphore::new(max))),
+            trusted_proxies: Arc::new(config.server.trusted_proxies.clone()),
+            security_headers: Arc::new(config.security_headers.clone()),
+            cors_origins: config.cors_origins.clone(),
+            in_flight,
+        }
+    }
+}
+
+/// Every route behind the middleware stack, for one `HttpServer` worker.
+pub fn app(
+    state: web::Data<AppState>,
+    options: &AppOptions,
+) -> App<
+    impl ServiceFactory<
+        ServiceRequest,
+        Config = (),
+        Response = ServiceResponse<impl MessageBody>,
+        Error = actix_web::Error,
+        InitError = (),
+    >,
+> {
+    let AppOptions {
+        body_limit,
+        request_timeout,
+        concurrency_limit,
+        trusted_proxies,
+        security_headers,
+        cors_origins,
+        in_flight,
+    } = options.clone();
+    App::new()
+        .app_data(state)
+        .app_data(web::JsonConfig::default().limit(body_limit))
+        .app_data(web::QueryConfig::default().error_handler(|err, _| AppError::from(err).into()))
+        .app_data(web::PayloadConfig::new(body_limit))
+        .wrap(from_fn(move |req, next| middleware::timeout(request_timeout, req, next)))
+        .wrap(from_fn(move |req, next| middleware::limit_concurrency(concurrency_limit.clone(), req, next)))
+        .wrap(from_fn(middleware::log_requests))
+        .wrap(from_fn(move |req, next| middleware::resolve_client_ip(trusted_proxies.clone(), req, next)))
+        .wrap(Logger::default())
+        .wrap(from_fn(move |req, next| middleware::security_headers(security_headers.clone(), req, next)))
+        .wrap(middleware::cors(&cors_origins))
+        .wrap(from_fn(move |req, next| middleware::track_in_flight(in_flight.clone(), req, next)))
+        .service(resource("/", [(Method::GET, web::to(health_check))]))
+        .service(resource("/health", [(Method::GET, web::to(health_check))]))
+        .service(resource("/health/db", [(Method::GET, web::to(health_db))]))
+        .service(resource("/health/ready", [(Method::GET, web::to(health_ready))]))
+        .service(resource("/health/events", [(Method::GET, web::to(health_events))]))
+        .service(resource("/version", [(Method::GET, web::to(version))]))
+        .service(resource("/.well-known/jwks.json", [(Method::GET, web::to(jwks))]))
+        .service(
+            web::scope("/api")
+                .service(
+                    web::scope("/auth")
+                        .service(resource("/login", [(Method::POST, web::to(login))]))
+                        .service(resource("/register", [(Method::POST, web::to(register))]))
+                        .service(resource("/logout", [(Method::POST, web::to(logout))]))
+                )
+                .service(
+                    web::scope("/users")
+                        .app_data(web::PathConfig::default().error_handler(|_, _| {
+                            AppError::Validation("invalid user id".to_string()).into()
+                        }))
+                        .service(resource("", [(Method::GET, web::to(get_users))]))
+                        .service(resource("/{id}", [(Method::GET, web::to(get_user))]))
+                )
+                .service(
+                    web::scope("/admin")
+                        .app_data(web::PathConfig::default().error_handler(|_, _| {
+                            AppError::Validation("invalid user id".to_string()).into()
+                        }))
+                        .service(resource(
+                            "/users/{id}/revoke-sessions",
+                            [(Method::POST, web::to(revoke_user_sessions))],
+                        ))
+                        .service(resource("/stats", [(Method::GET, web::to(get_product_stats))]))
+                )
+                .service(
+                    web::scope("/products")
+                        .app_data(web::PathConfig::default().error_handler(|_, _| {
+                            AppError::Validation("invalid product id".to_string()).into()
+                        }))
+                        .service(resource(
+                            "",
+                            [(Method::GET, web::to(get_products)), (Method::POST, web::to(create_product))],
+                        ))
+                        .service(resource("/bulk", [(Method::POST, web::to(create_products_bulk))]))
+                        .service(resource("/stream", [(Method::GET, web::to(stream_products))]))
+                        .service(resource("/export.csv", [(Method::GET, web::to(export_products))]))
+                        .service(resource(
+                            "/{id}",
+                            [
+                                (Method::GET, web::to(get_product)),
+                                (Method::PUT, web::to(replace_product)),
+                                (Method::PATCH, web::to(update_product)),
+                                (Method::DELETE, web::to(delete_product)),
+                            ],
+                        ))
+                        .service(resource(
+                            "/{id}/categories",
+                            [(Method::GET, web::to(get_product_categories))],
+                        ))
+                        .service(resource(
+                            "/{id}/categories/{category_id}",
+                            [
+                                (Method::PUT, web::to(assign_category)),
+                                (Method::DELETE, web::to(unassign_category)),
+                            ],
+                        ))
+                        .service(resource("/{id}/reserve", [(Method::POST, web::to(reserve_inventory))]))
+                        .service(resource(
+                            "/{id}/sync-to-shopify",
+                            [(Method::POST, web::to(sync_product_to_shopify))],
+                        ))
+                        .service(resource(
+                            "/{id}/image-upload-url",
+                            [(Method::POST, web::to(create_image_upload_url))],
+                        ))
+                )
+                .service(resource(
+                    "/categories",
+                    [(Method::GET, web::to(get_categories)), (Method::POST, web::to(create_category))],
+                ))
+                .service(
+                    web::scope("/webhooks").service(resource(
+                        "/shopify",
+                        [
+                            (Method::GET, web::to(list_shopify_webhooks)),
+                            (Method::POST, web::to(handle_shopify_webhook)),
+                        ],
+                    ))
+                )
+        )
+        .service(resource("/graphql", [(Method::POST, web::to(graphql_handler))]))
+        .service(resource("/graphql/ws", [(Method::GET, web::to(graphql_ws_handler))]))
+        .service(resource("/graphiql", [(Method::GET, web::to(graphiql))]))
+        .default_service(web::to(route_not_found))
+}
+
+/// Runs the server against the database until a shutdown signal, then
+/// drains in-flight requests.
+pub async fn serve(config: Config) -> std::io::Result<()> {
+    init_tracing("actixweb_server=debug,shared=info", config.log_format);
+    init_id_kind(config.id_kind);
+    config.log_summary("actix-web");
+    // Same fallback actix-web uses when no worker count is given
+    let workers = config
+        .server
+        .workers
+        .unwrap_or_else(|| std::thread::available_parallelism().map_or(2, usize::from));
+    tracing::info!(workers, "HttpServer workers");
+
+    let settings = config.server.clone();
+    let pool = connect_pool(&config.database).await.map_err(std::io::Error::other)?;
+    if settings.run_migrations {
+        let applied = run_migrations(&pool).await.map_err(std::io::Error::other)?;
+        tracing::info!("Applied {} database migrations", applied);
+    }
+    let timer = QueryTimer::from_settings(&config.database);
+    let products: Arc<dyn ProductStore> = Arc::new(
+        ProductRepository::new(pool.clone(), timer).with_read_cache(config.database.product_cache_ttl),
+    );
+    let app_state = web::Data::new(AppState::new(&config, pool, products).map_err(std::io::Error::other)?);
+    let shutting_down = app_state.shutting_down.clone();
+
+    let in_flight = InFlightRequests::new();
+    let options = AppOptions::new(&config, in_flight.clone());
+    let shutdown_timeout = settings.shutdown_timeout;
+
+    let tls_config = config
+        .tls
+        .as_ref()
+        .map(|tls| tls.server_config())
+        .transpose()
+        .map_err(std::io::Error::other)?;
+    let (http, ws) = if tls_config.is_some() { ("https", "wss") } else { ("http", "ws") };
+
+    tracing::info!(tls = tls_config.is_some(), "ActixWeb server running on {}://localhost:{}", http, config.port);
+    if let Some(ui) = settings.graphql_ui.name() {
+        tracing::info!("{} available at {}://localhost:{}/graphiql", ui, http, config.port);
+    }
+    tracing::info!("GraphQL subscriptions available at {}://localhost:{}/graphql/ws", ws, config.port);
+
+    let server = HttpServer::new(move || app(app_state.clone(), &options))
+        // Signals are handled below so the drain can be reported. actix gets
+        // an extra second so the drain, not actix, decides what was abandoned
+        .disable_signals()
+        .workers(workers)
+        .shutdown_timeout(shutdown_timeout.as_secs() + 1);
+    // actix-web adds the h2 and http/1.1 ALPN protocols itself
+    let server = match tls_config {
+        Some(tls_config) => server.bind_rustls_0_23(config.bind_address(), tls_config)?,
+        None => server.bind(config.bind_address())?,
+    }
+    .run();
+
+    let handle = server.handle();
+    let mut server = std::pin::pin!(server);
+    tokio::select! {
+        result = &mut server => return result,
+        () = begin_shutdown(&shutting_down, settings.shutdown_delay) => {}
+    }
+    // The server future runs the stop command, so it is polled alongside it
+    let (result, (), ()) = tokio::join!(server, handle.stop(true), in_flight.drain(shutdown_timeout));
+    result
+}
+
+/// 503 with status `shutting_down` once a shutdown signal has arrived.
+async fn health_check(state: web::Data<AppState>) -> Result<HttpResponse> {
+    let (mut response, label) = if state.shutting_down.is_set() {
+        (HttpResponse::ServiceUnavailable(), "shutting_down")
+    } else {
+        (HttpResponse::Ok(), "ok")
+    };
+    Ok(response.json(json!({
+        "status": label,
+        "framework": "actix-web",
+        "timestamp": timestamp::format(&chrono::Utc::now())
+    })))
+}
+
+/// Anything no route matches gets the shared JSON 404.
+async fn route_not_found(req: HttpRequest) -> Result<HttpResponse> {
+    Err(AppError::RouteNotFound(req.path().to_string()).into())
+}
+
+/// `path` served by one route per method. As in axum, GET routes answer
+/// HEAD too, and any other method gets a 405 whose `Allow` lists the rest.
+fn resource<const N: usize>(path: &str, routes: [(Method, Route); N]) -> Resource {
+    let mut allow = Vec::new();
+    let mut resource = web::resource(path);
+    for (method, route) in routes {
+        if method == Method::GET {
+            allow.extend(["GET".to_string(), "HEAD".to_string()]);
+            resource = resource.route(route.guard(guard::Any(guard::Get()).or(guard::Head())));
+        } else {
+            allow.push(method.to_string());
+            resource = resource.route(route.method(method));
+        }
+    }
+    resource.default_service(web::to(move || {
+        let allow = allow.clone();
+        async move { Err::<HttpResponse, _>(AppError::MethodNotAllowed { allow }) }
+    }))
+}
+
+async fn version() -> HttpResponse {
+    HttpResponse::Ok().json(BuildInfo::new("actix-web", env!("CARGO_PKG_VERSION")))
+}
+
+/// Public keys to verify our tokens with; empty while signing with HS256.
+async fn jwks(state: web::Data<AppState>) -> HttpResponse {
+    HttpResponse::Ok().json(state.jwt_keys.jwks())
+}
+
+async fn health_db(state: web::Data<AppState>) -> Result<HttpResponse> {
+    Ok(HttpResponse::Ok().json(PoolStats::from_pool(&state.pool)))
+}
+
+/// 503 until the database answers a query, and again once shutting down.
+async fn health_ready(state: web::Data<AppState>) -> Result<HttpResponse> {
+    if state.shutting_down.is_set() {
+        return Err(AppError::Unavailable("server is shutting down".to_string()).into());
+    }
+    Ok(HttpResponse::Ok().json(check_database(&state.pool).await?))
+}
+
+async fn health_events(state: web::Data<AppState>) -> Result<HttpResponse> {
+    Ok(HttpResponse::Ok().json(state.event_metrics.snapshot()))
+}
+
+async fn login(
+    state: web::Data<AppState>,
+    req: HttpRequest,
+    params: web::Query<LoginParams>,
+    payload: web::Json<LoginRequest>,
+) -> Result<HttpResponse> {
+    payload.validate()?;
+    let (token, user) = state
+        .login
+        .login(&payload.email, &payload.password, client_ip(&req))
+        .await?;
+    let mut response = HttpResponse::Ok();
+    if params.set_cookie {
+        response.insert_header((header::SET_COOKIE, auth_cookie_header(&state.settings.auth_cookie_name, &token)));
+    }
+    Ok(response.json(LoginResponse {
+        token,
+        user: user.into(),
+    }))
+}
+
+/// Address `middleware::resolve_client_ip` attributed the request to, or
+/// the peer address when it didn't run.
+fn client_ip(req: &HttpRequest) -> IpAddr {
+    req.extensions()
+        .get::<ClientIp>()
+        .map(|&ClientIp(ip)| ip)
+        .or_else(|| req.peer_addr().map(|addr| addr.ip()))
+        .unwrap_or(IpAddr::V4(Ipv4Addr::UNSPECIFIED))
+}
+
+/// Revokes the presented token; it stays rejected until it would have expired.
+async fn logout(state: web::Data<AppState>, AuthUser(claims): AuthUser) -> HttpResponse {
+    state.revocations.revoke(&claims);
+    HttpResponse::NoContent()
+        .insert_header((header::SET_COOKIE, expired_auth_cookie_header(&state.settings.auth_cookie_name)))
+        .finish()
+}
+
+async fn register(
+    state: web::Data<AppState>,
+    payload: web::Json<CreateUser>,
+) -> Result<HttpResponse> {
+    let email = validate_registration(&payload, state.settings.password_min_length)?;
+    let password_hash = hash_password_with_cost(&payload.password, state.settings.bcrypt_cost).await?;
+    let user = state
+        .users
+        .create(&payload.username, &email, &password_hash)
+        .await?;
+    Ok(HttpResponse::Ok().json(UserResponse::from(user)))
+}
+
+/// Admin only.
+async fn get_users(
+    state: web::Data<AppState>,
+    AdminUser(admin): AdminUser,
+    params: web::Query<UserListParams>,
+) -> Result<HttpResponse> {
+    let page = params.page(state.settings.page_limits)?;
+    tracing::debug!(admin = %admin.sub, limit = page.limit, offset = page.offset, "listing users");
+    let (users, total) = tokio::try_join!(state.users.list(page.limit, page.offset), state.users.count())?;
+    let users = Paginated::new(users, total, page).map(UserResponse::from);
+    let mut response = HttpResponse::Ok();
+    if page.clamped {
+        response.insert_header((PAGINATION_CLAMPED_HEADER, "true"));
+    }
+    Ok(in_phase_sync("serialize", || response.json(users)))
+}
+
+async fn get_user(state: web::Data<AppState>, AuthUser(_): AuthUser, path: web::Path<Id>) -> Result<HttpResponse> {
+    let user = state
+        .users
+        .find_by_id(path.into_inner())
+        .await?
+        .ok_or_else(|| AppError::NotFound("user".to_string()))?;
+    Ok(in_phase_sync("serialize", || HttpResponse::Ok().json(UserResponse::from(user))))
+}
+
+/// Admin only. Every token issued to the user so far stops working.
+async fn revoke_user_sessions(
+    state: web::Data<AppState>,
+    AdminUser(admin): AdminUser,
+    path: web::Path<Id>,
+) -> Result<HttpResponse> {
+    let user = state
+        .users
+        .find_by_id(path.into_inner())
+        .await?
+        .ok_or_else(|| AppError::NotFound("user".to_string()))?;
+    let revoked_sessions = state.revocations.revoke_user(&user.id.to_string());
+    tracing::info!(admin = %admin.sub, user = %user.id, revoked_sessions, "revoked user sessions");
+    Ok(HttpResponse::Ok().json(RevokeSessionsResponse { user_id: user.id, revoked_sessions }))
+}
+
+/// Admin only.
+async fn get_product_stats(state: web::Data<AppState>, AdminUser(admin): AdminUser) -> Result<HttpResponse> {
+    tracing::debug!(admin = %admin.sub, "reading product stats");
+    Ok(HttpResponse::Ok().json(state.products.stats().await?))
+}
+
+async fn get_products(
+    state: web::Data<AppState>,
+    req: HttpRequest,
+    params: web::Query<ProductListParams>,
+) -> Result<HttpResponse> {
+    let page = params.page(state.settings.page_limits)?;
+    let filter = params.filter()?;
+    let (products, total) = tokio::try_join!(
+        state.products.list_page(&filter, page.limit, page.offset),
+        state.products.count(&filter)
+    )?;
+
+    let mut response = HttpResponse::Ok();
+    response.insert_header((TOTAL_COUNT_HEADER, total));
+    if let Some(links) = page.links(&filter.path_with_query(req.path()), total) {
+        response.insert_header((header::LINK, links));
+    }
+    if page.clamped {
+        response.insert_header((PAGINATION_CLAMPED_HEADER, "true"));
+    }
+    Ok(in_phase_sync("serialize", || response.json(Paginated::new(products, total, page))))
+}
+
+async fn get_product(
+    state: web::Data<AppState>,
+    req: HttpRequest,
+    path: web::Path<Id>,
+) -> Result<HttpResponse> {
+    let product = state
+        .products
+        .find_by_id(path.into_inner())
+        .await?
+        .ok_or_else(|| AppError::NotFound("product".to_string()))?;
+
+    let etag = etag_for(&product);
+    let not_modified = req
+        .headers()
+        .get(header::IF_NONE_MATCH)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|value| etag_matches(value, &etag));
+
+    if not_modified {
+        return Ok(HttpResponse::NotModified()
+            .insert_header((header::ETAG, etag))
+            .finish());
+    }
+    Ok(in_phase_sync("serialize", || {
+        HttpResponse::Ok().insert_header((header::ETAG, etag)).json(product)
+    }))
+}
+
+/// Server-sent events carrying every product update as JSON.
+async fn stream_products(state: web::Data<AppState>) -> impl Responder {
+    let events = product_update_stream(&state.events, None).map(|product| {
+        sse::Data::new_json(product).map(|data| sse::Event::from(data.event("product_update")))
+    });
+    sse::Sse::from_stream(events).with_keep_alive(SSE_HEARTBEAT_INTERVAL)
+}
+
+/// The whole catalog as CSV, streamed row by row from the database.
+async fn export_products(state: web::Data<AppState>) -> HttpResponse {
+    let rows = product_csv(state.products.stream_all()).map(|row| row.map(web::Bytes::from));
+    HttpResponse::Ok()
+        .content_type("text/csv; charset=utf-8")
+        .insert_header((header::CONTENT_DISPOSITION, PRODUCT_CSV_DISPOSITION))
+        .streaming(rows)
+}
+
+/// A signed-in caller repeating a create within `CREATE_DEDUP_WINDOW_MS`
+/// gets the product its first request created.
+async fn create_product(
+    state: web::Data<AppState>,
+    MaybeAuthUser(claims): MaybeAuthUser,
+    payload: web::Json<CreateProduct>,
+) -> Result<HttpResponse> {
+    payload.validate()?;
+    let user_id = claims.as_ref().map(|claims| claims.sub.as_str());
+    let (product, created) = state
+        .create_dedup
+        .create(user_id, payload.into_inner(), |input| state.products.create(input))
+        .await?;
+    if created {
+        state.events.publish(DomainEvent::ProductCreated { product: product.clone() });
+    }
+    Ok(in_phase_sync("serialize", || HttpResponse::Ok().json(product)))
+}
+
+/// All-or-nothing: one failed insert rolls back the whole batch.
+async fn create_products_bulk(
+    state: web::Data<AppState>,
+    payload: web::Json<Vec<CreateProduct>>,
+) -> Result<HttpResponse> {
+    validate_products(&payload)?;
+    let products = state.products.create_many(payload.into_inner()).await?;
+    for product in &products {
+        state.events.publish(DomainEvent::ProductCreated { product: product.clone() });
+    }
+    Ok(HttpResponse::Ok().json(products))
+}
+
+/// `PUT` replaces every field, so the body must be a complete product.
+async fn replace_product(
+    state: web::Data<AppState>,
+    req: HttpRequest,
+    path: web::Path<Id>,
+    payload: web::Json<CreateProduct>,
+) -> Result<HttpResponse> {
+    payload.validate()?;
+    let product =
+        apply_product_update(&state, path.into_inner(), payload.into_inner().into(), if_match(&req)).await?;
+    Ok(in_phase_sync("serialize", || {
+        HttpResponse::Ok().insert_header((header::ETAG, etag_for(&product))).json(&product)
+    }))
+}
+
+/// `PATCH` changes only the fields present in the body.
+async fn update_product(
+    state: web::Data<AppState>,
+    req: HttpRequest,
+    path: web::Path<Id>,
+    payload: web::Json<UpdateProduct>,
+) -> Result<HttpResponse> {
+    payload.validate()?;
+    let product = apply_product_update(&state, path.into_inner(), payload.into_inner(), if_match(&req)).await?;
+    Ok(in_phase_sync("serialize", || {
+        HttpResponse::Ok().insert_header((header::ETAG, etag_for(&product))).json(&product)
+    }))
+}
+
+/// An unreadable value is kept (as empty) so it fails the check instead of
+/// silently turning the request into an unconditional write.
+fn if_match(req: &HttpRequest) -> Option<&str> {
+    req.headers()
+        .get(header::IF_MATCH)
+        .map(|value| value.to_str().unwrap_or_default())
+}
+
+/// With `if_match`, the update only happens while the product still has
+/// that ETag; without it the last write wins.
+async fn apply_product_update(
+    state: &AppState,
+    id: Id,
+    update: UpdateProduct,
+    if_match: Option<&str>,
+) -> AppResult<Product> {
+    let change = match if_match {
+        Some(if_match) => state.products.update_if_match(id, update, if_match).await?,
+        None => state.products.update(id, update).await?,
+    }
+    .ok_or_else(|| AppError::NotFound("product".to_string()))?;
+    let product = change.product.clone();
+    state.events.publish_update(change);
+    Ok(product)
+}
+
+async fn reserve_inventory(
+    state: web::Data<AppState>,
+    path: web::Path<Id>,
+    payload: web::Json<ReserveInventory>,
+) -> Result<HttpResponse> {
+    payload.validate()?;
+    let product = state
+        .products
+        .reserve_inventory(path.into_inner(), payload.quantity)
+        .await?;
+    state.events.publish_update(ProductChange {
+        previous_inventory: product.inventory + payload.quantity,
+        product: product.clone(),
+    });
+    Ok(HttpResponse::Ok().json(product))
+}
+
+/// 404 when the product doesn't exist, unless `?idempotent=true`.
+async fn delete_product(
+    state: web::Data<AppState>,
+    path: web::Path<Id>,
+    params: web::Query<DeleteParams>,
+) -> Result<HttpResponse> {
+    let id = path.into_inner();
+    if state.products.delete(id).await? {
+        state.events.publish(DomainEvent::ProductDeleted { product_id: id });
+    } else if !params.idempotent {
+        return Err(AppError::NotFound("product".to_string()).into());
+    }
+    Ok(HttpResponse::NoContent().finish())
+}
+
+async fn get_categories(state: web::Data<AppState>) -> Result<HttpResponse> {
+    Ok(HttpResponse::Ok().json(state.products.list_categories().await?))
+}
+
+async fn create_category(state: web::Data<AppState>, payload: web::Json<CreateCategory>) -> Result<HttpResponse> {
+    payload.validate()?;
+    Ok(HttpResponse::Ok().json(state.products.create_category(payload.into_inner()).await?))
+}
+
+/// 404 when the product doesn't exist.
+async fn get_product_categories(state: web::Data<AppState>, path: web::Path<Id>) -> Result<HttpResponse> {
+    let id = path.into_inner();
+    if state.products.find_by_id(id).await?.is_none() {
+        return Err(AppError::NotFound("product".to_string()).into());
+    }
+    let mut categories = state.products.categories_of(&[id]).await?;
+    Ok(HttpResponse::Ok().json(categories.remove(&id).unwrap_or_default()))
+}
+
+/// Idempotent: `204` whether or not the category was already assigned. The
+/// category id is parsed here rather than by `web::Path`, whose error names
+/// the product.
+async fn assign_category(state: web::Data<AppState>, path: web::Path<(Id, String)>) -> Result<HttpResponse> {
+    let (id, category_id) = path.into_inner();
+    let category_id = parse_category_id(&category_id)?;
+    state.products.assign_category(id, category_id).await?;
+    Ok(HttpResponse::NoContent().finish())
+}
+
+/// 404 when the category wasn't assigned to the product.
+async fn unassign_category(state: web::Data<AppState>, path: web::Path<(Id, String)>) -> Result<HttpResponse> {
+    let (id, category_id) = path.into_inner();
+    let category_id = parse_category_id(&category_id)?;
+    if !state.products.unassign_category(id, category_id).await? {
+        return Err(AppError::NotFound("category assignment".to_string()).into());
+    }
+    Ok(HttpResponse::NoContent().finish())
+}
+
+async fn sync_product_to_shopify(
+    state: web::Data<AppState>,
+    AdminUser(admin): AdminUser,
+    path: web::Path<Id>,
+) -> Result<HttpResponse> {
+    let shopify = state
+        .shopify
+        .as_ref()
+        .ok_or_else(|| AppError::Validation("Shopify sync is not configured".to_string()))?;
+    let id = path.into_inner();
+    tracing::info!(admin = %admin.sub, product = %id, "syncing product to Shopify");
+    Ok(HttpResponse::Ok().json(shopify.sync(state.products.as_ref(), id).await?))
+}
+
+/// 404 for an unknown product, so no URL is signed for an image that could
+/// never be attached.
+async fn create_image_upload_url(
+    state: web::Data<AppState>,
+    AdminUser(admin): AdminUser,
+    path: web::Path<Id>,
+    payload: web::Json<CreateImageUpload>,
+) -> Result<HttpResponse> {
+    let storage = state
+        .storage
+        .as_ref()
+        .ok_or_else(|| AppError::Validation("image uploads are not configured".to_string()))?;
+    payload.validate()?;
+    let product = state
+        .products
+        .find_by_id(path.into_inner())
+        .await?
+        .ok_or_else(|| AppError::NotFound("product".to_string()))?;
+    tracing::debug!(admin = %admin.sub, product = %product.id, "signing an image upload");
+    Ok(HttpResponse::Ok().json(storage.product_image_upload(product.id, &payload.content_type, payload.content_length)))
+}
+
+/// Takes the raw body because the HMAC is computed over the exact bytes
+/// Shopify sent.
+async fn handle_shopify_webhook(
+    state: web::Data<AppState>,
+    req: HttpRequest,
+    body: web::Bytes,
+) -> Result<HttpResponse> {
+    let header = |name: &str| req.headers().get(name).and_then(|value| value.to_str().ok());
+    let webhook = ShopifyWebhookHeaders {
+        hmac: header("x-shopify-hmac-sha256"),
+        webhook_id: header("x-shopify-webhook-id"),
+        topic: header("x-shopify-topic"),
+    };
+    state.shopify_webhooks.receive(webhook, &body).await?;
+    Ok(HttpResponse::Ok().finish())
+}
+
+async fn list_shopify_webhooks(
+    state: web::Data<AppState>,
+    AdminUser(admin): AdminUser,
+    params: web::Query<WebhookListParams>,
+) -> Result<HttpResponse> {
+    tracing::debug!(admin = %admin.sub, processed = ?params.processed, "listing Shopify webhooks");
+    Ok(HttpResponse::Ok().json(state.shopify_webhooks.list(params.processed).await?))
+}
+
+/// Accepts a single operation or a batch (JSON array). A valid bearer token
+/// is passed to resolvers as `Claims`; without one the request still runs,
+/// and guarded fields answer `UNAUTHENTICATED`.
+async fn graphql_handler(
+    state: web::Data<AppState>,
+    http_req: HttpRequest,
+    req: GraphQLBatchRequest,
+) -> HttpResponse {
+    let cookie = http_req.cookie(&state.settings.auth_cookie_name);
+    let claims = claims_from_auth_header(
+        &state.jwt_keys,
+        &state.revocations,
+        authorization(&http_req),
+        cookie.as_ref().map(|cookie| cookie.value()),
+    )
+    .ok();
+    let response = execute_graphql(
+        &state.schema,
+        req.into_inner(),
+        claims,
+        client_ip(&http_req),
+        state.settings.graphql_max_batch_size,
+        state.settings.graphql_max_request_bytes,
+    )
+    .await;
+    let rejected = rejected_before_execution(&response);
+    let mut http_response = GraphQLResponse::from(response).respond_to(&http_req);
+    if rejected {
+        *http_response.status_mut() = StatusCode::BAD_REQUEST;
+    }
+    http_response
+}
+
+/// Each connection gets an id that, with the upgrade request's id, tags the
+/// subscription's log lines. Operations on the connection run as the user
+/// the upgrade request authenticated.
+async fn graphql_ws_handler(
+    state: web::Data<AppState>,
+    req: HttpRequest,
+    payload: web::Payload,
+) -> Result<HttpResponse> {
+    let connection = SubscriptionConnection::new(req.extensions().get::<RequestId>());
+    tracing::debug!(connection_id = %connection.connection_id, "graphql websocket opened");
+    let cookie = req.cookie(&state.settings.auth_cookie_name);
+    let claims = claims_from_auth_header(
+        &state.jwt_keys,
+        &state.revocations,
+        authorization(&req),
+        cookie.as_ref().map(|cookie| cookie.value()),
+    )
+    .ok();
+    let data = websocket_data(connection, claims, client_ip(&req));
+    GraphQLSubscription::new(state.schema.clone()).with_data(data).start(&req, payload)
+}
+
+async fn graphiql(state: web::Data<AppState>) -> Result<HttpResponse> {
+    let page = state
+        .settings
+        .graphql_ui
+        .page()
+        .ok_or_else(|| AppError::NotFound("GraphQL UI".to_string()))?;
+    Ok(HttpResponse::Ok()
+        .content_type("text/html; charset=utf-8")
+        .body(page))
+}
\ No newline at end of file
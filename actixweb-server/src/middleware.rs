@@ -0,0 +1,182 @@
+use actix_cors::Cors;
+use actix_web::{
+    body::MessageBody,
+    dev::{ServiceRequest, ServiceResponse},
+    error::InternalError,
+    http::header::{HeaderMap, HeaderName, HeaderValue},
+    middleware::Next,
+    Error, HttpMessage,
+};
+use shared::{
+    client_ip::{ClientIp, TrustedProxies},
+    error::AppError,
+    models::TOTAL_COUNT_HEADER,
+    pagination::PAGINATION_CLAMPED_HEADER,
+    security_headers::SecurityHeaders,
+    shutdown::InFlightRequests,
+    telemetry::{redact_headers, request_span, RequestId, REQUEST_ID_HEADER},
+};
+use std::net::{IpAddr, Ipv4Addr};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::Semaphore;
+use tracing::Instrument;
+use uuid::Uuid;
+
+/// Aborts handlers that run longer than `limit`, answering with a 504.
+pub async fn timeout(
+    limit: Duration,
+    req: ServiceRequest,
+    next: Next<impl MessageBody>,
+) -> Result<ServiceResponse<impl MessageBody>, Error> {
+    match tokio::time::timeout(limit, next.call(req)).await {
+        Ok(response) => response,
+        Err(_) => Err(AppError::Timeout.into()),
+    }
+}
+
+/// Answers 503 once every permit of `limit` is taken; without a limit every
+/// request passes. The permit is held until the handler returns.
+pub async fn limit_concurrency(
+    limit: Option<Arc<Semaphore>>,
+    req: ServiceRequest,
+    next: Next<impl MessageBody>,
+) -> Result<ServiceResponse<impl MessageBody>, Error> {
+    let _permit = match limit {
+        Some(semaphore) => Some(semaphore.try_acquire_owned().map_err(|_| AppError::Overloaded)?),
+        None => None,
+    };
+    next.call(req).await
+}
+
+/// Counts the request as in flight until its handler has responded.
+pub async fn track_in_flight(
+    in_flight: InFlightRequests,
+    req: ServiceRequest,
+    next: Next<impl MessageBody>,
+) -> Result<ServiceResponse<impl MessageBody>, Error> {
+    let _guard = in_flight.track();
+    next.call(req).await
+}
+
+/// Stores the request's `ClientIp`, taken from the forwarding headers when
+/// the peer is a trusted proxy, for `log_requests` and the handlers.
+pub async fn resolve_client_ip(
+    proxies: Arc<TrustedProxies>,
+    req: ServiceRequest,
+    next: Next<impl MessageBody>,
+) -> Result<ServiceResponse<impl MessageBody>, Error> {
+    let peer = req
+        .peer_addr()
+        .map_or(IpAddr::V4(Ipv4Addr::UNSPECIFIED), |addr| addr.ip());
+    let header = |name: &str| req.headers().get(name).and_then(|value| value.to_str().ok());
+    let client_ip = proxies.client_ip(peer, header("x-forwarded-for"), header("x-real-ip"));
+    req.extensions_mut().insert(ClientIp(client_ip));
+    next.call(req).await
+}
+
+/// Emits one structured event per request and echoes the request id back,
+/// reusing the client's `x-request-id` when one was sent.
+pub async fn log_requests(
+    req: ServiceRequest,
+    next: Next<impl MessageBody>,
+) -> Result<ServiceResponse<impl MessageBody>, Error> {
+    let request_id = req
+        .headers()
+        .get(REQUEST_ID_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .map(str::to_string)
+        .unwrap_or_else(|| Uuid::new_v4().to_string());
+    let method = req.method().to_string();
+    let path = req.path().to_string();
+    let client_ip = req
+        .extensions()
+        .get::<ClientIp>()
+        .map(|ClientIp(ip)| ip.to_string())
+        .unwrap_or_default();
+    req.extensions_mut().insert(RequestId(request_id.clone()));
+    if tracing::enabled!(tracing::Level::DEBUG) {
+        let headers = redact_headers(
+            req.headers()
+                .iter()
+                .map(|(name, value)| (name.as_str(), value.to_str().unwrap_or("<binary>"))),
+        );
+        tracing::debug!(request_id = %request_id, headers = %headers, "request received");
+    }
+
+    let started = Instant::now();
+    let result = next.call(req).instrument(request_span(&request_id, &method, &path)).await;
+    let status = match &result {
+        Ok(response) => response.status(),
+        Err(err) => err.as_response_error().status_code(),
+    };
+
+    tracing::info!(
+        request_id = %request_id,
+        method = %method,
+        path = %path,
+        client_ip = %client_ip,
+        status = status.as_u16(),
+        latency_ms = started.elapsed().as_secs_f64() * 1000.0,
+        "request completed"
+    );
+
+    with_headers(result, |headers| {
+        if let Ok(value) = HeaderValue::from_str(&request_id) {
+            headers.insert(HeaderName::from_static(REQUEST_ID_HEADER), value);
+        }
+    })
+}
+
+/// Adds the shared security headers to every response, including those for
+/// errors raised by inner middleware (timeouts, shedding).
+pub async fn security_headers(
+    security_headers: Arc<SecurityHeaders>,
+    req: ServiceRequest,
+    next: Next<impl MessageBody>,
+) -> Result<ServiceResponse<impl MessageBody>, Error> {
+    let path = req.path().to_string();
+    with_headers(next.call(req).await, |headers| {
+        for (name, value) in security_headers.for_path(&path) {
+            let name = HeaderName::from_static(name);
+            if let (false, Ok(value)) = (headers.contains_key(&name), HeaderValue::from_str(value)) {
+                headers.insert(name, value);
+            }
+        }
+    })
+}
+
+/// Lets `add` set headers on the response, or on the one an error raised
+/// further in will be rendered as. Such an error is rendered here, with no
+/// request left to build a `ServiceResponse` from, and passed on carrying
+/// the finished response.
+fn with_headers<B>(
+    result: Result<ServiceResponse<B>, Error>,
+    add: impl FnOnce(&mut HeaderMap),
+) -> Result<ServiceResponse<B>, Error> {
+    match result {
+        Ok(mut response) => {
+            add(response.headers_mut());
+            Ok(response)
+        }
+        Err(err) => {
+            let mut response = err.error_response();
+            add(response.headers_mut());
+            Err(InternalError::from_response(err, response).into())
+        }
+    }
+}
+
+/// Allows any origin when `origins` is empty, otherwise exactly those listed.
+pub fn cors(origins: &[String]) -> Cors {
+    let cors = if origins.is_empty() {
+        Cors::default().allow_any_origin()
+    } else {
+        origins
+            .iter()
+            .fold(Cors::default(), |cors, origin| cors.allowed_origin(origin))
+    };
+    cors.allow_any_method()
+        .allow_any_header()
+        .expose_headers([TOTAL_COUNT_HEADER, PAGINATION_CLAMPED_HEADER, "link"])
+}
\ No newline at end of file
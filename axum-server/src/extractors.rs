@@ -0,0 +1,75 @@
+use axum::{
+    extract::FromRequestParts,
+    http::{header, request::Parts, HeaderMap},
+};
+use shared::{
+    auth::{admin_claims_from_auth_header, claims_from_auth_header, cookie_value, Claims},
+    error::AppError,
+};
+
+use crate::AppState;
+
+/// `axum::extract::Query` answering a malformed or mistyped query string
+/// with the shared JSON 400 instead of axum's plain-text one.
+#[derive(FromRequestParts)]
+#[from_request(via(axum::extract::Query), rejection(AppError))]
+pub struct Query<T>(pub T);
+
+/// The caller behind a valid, unrevoked bearer token, or auth cookie when
+/// there is no `Authorization` header. Handlers that take this extractor
+/// answer 401 before running when the token is missing.
+pub struct AuthUser(pub Claims);
+
+impl FromRequestParts<AppState> for AuthUser {
+    type Rejection = AppError;
+
+    async fn from_request_parts(parts: &mut Parts, state: &AppState) -> Result<Self, Self::Rejection> {
+        let header = authorization(&parts.headers);
+        let cookie = auth_cookie(&parts.headers, &state.settings.auth_cookie_name);
+        claims_from_auth_header(&state.jwt_keys, &state.revocations, header, cookie).map(AuthUser)
+    }
+}
+
+/// The caller when the request carries a valid token, for handlers that
+/// also serve anonymous callers. Never rejects.
+pub struct MaybeAuthUser(pub Option<Claims>);
+
+impl FromRequestParts<AppState> for MaybeAuthUser {
+    type Rejection = AppError;
+
+    async fn from_request_parts(parts: &mut Parts, state: &AppState) -> Result<Self, Self::Rejection> {
+        AuthUser::from_request_parts(parts, state)
+            .await
+            .map_or(Ok(MaybeAuthUser(None)), |AuthUser(claims)| Ok(MaybeAuthUser(Some(claims))))
+    }
+}
+
+/// Like `AuthUser`, but the token must also carry the admin role (403
+/// otherwise).
+pub struct AdminUser(pub Claims);
+
+impl FromRequestParts<AppState> for AdminUser {
+    type Rejection = AppError;
+
+    async fn from_request_parts(parts: &mut Parts, state: &AppState) -> Result<Self, Self::Rejection> {
+        let header = authorization(&parts.headers);
+        let cookie = auth_cookie(&parts.headers, &state.settings.auth_cookie_name);
+        admin_claims_from_auth_header(&state.jwt_keys, &state.revocations, header, cookie).map(AdminUser)
+    }
+}
+
+pub fn authorization(headers: &HeaderMap) -> Option<&str> {
+    headers
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+}
+
+/// The token in cookie `name`, looking through every `Cookie` header since
+/// HTTP/2 clients may send one per cookie.
+pub fn auth_cookie<'a>(headers: &'a HeaderMap, name: &str) -> Option<&'a str> {
+    headers
+        .get_all(header::COOKIE)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .find_map(|value| cookie_value(value, name))
+}
\ No newline at end of file
@@ -0,0 +1,766 @@
+mod extractors;
+mod middleware;
+
+use axum::{
+    error_handling::HandleErrorLayer,
+    extract::{ws::WebSocketUpgrade, DefaultBodyLimit, Path, State},
+    http::{header, HeaderMap, HeaderName, StatusCode, Uri},
+    response::{AppendHeaders, IntoResponse, Json, Response},
+    routing::{get, post, put},
+    Extension, Router,
+};
+use async_graphql::http::ALL_WEBSOCKET_PROTOCOLS;
+use async_graphql_axum::{GraphQLBatchRequest, GraphQLProtocol, GraphQLResponse, GraphQLWebSocket};
+use axum_server_tls::tls_rustls::RustlsConfig;
+use axum::body::{Body, Bytes};
+use axum::response::sse::{Event, KeepAlive, Sse};
+use axum::response::Html;
+use futures::{Stream, StreamExt};
+use shared::{
+    models::*,
+    auth::*,
+    build_info::BuildInfo,
+    client_ip::ClientIp,
+    config::{Config, ServerSettings},
+    dedup::CreateDedup,
+    db::{check_database, connect_pool, run_migrations, PoolStats, QueryTimer},
+    error::{AppError, AppResult},
+    id::{init_id_kind, Id},
+    pagination::{Paginated, PAGINATION_CLAMPED_HEADER},
+    graphql::{
+        build_schema, execute_graphql, product_update_stream, websocket_data, rejected_before_execution, GraphQLSchema, ServerInfo, SSE_HEARTBEAT_INTERVAL,
+    },
+    repository::{ProductChange, ProductRepository, UserRepository, WebhookRepository},
+    store::ProductStore,
+    telemetry::{in_phase_sync, init_tracing, RequestId, SubscriptionConnection},
+    timestamp,
+    validate::Validate,
+    events::{DomainEvent, EventBus, EventMetrics},
+    export::{product_csv, PRODUCT_CSV_DISPOSITION},
+    low_stock::LowStockNotifier,
+    revocation::TokenRevocations,
+    shopify::{ShopifyClient, ShopifySync, ShopifyWebhookHeaders, ShopifyWebhookReceiver},
+    shutdown::{begin_shutdown, InFlightRequests, ShuttingDown},
+    storage::{ImageUpload, ObjectStorage},
+    throttle::LoginThrottle,
+};
+use extractors::{auth_cookie, authorization, AdminUser, AuthUser, MaybeAuthUser, Query};
+use sqlx::PgPool;
+use serde_json::{json, Value};
+use std::collections::BTreeMap;
+use std::future::IntoFuture;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use tower::{timeout::TimeoutLayer, ServiceBuilder};
+use tower_http::trace::TraceLayer;
+
+#[derive(Clone)]
+pub struct AppState {
+    pub schema: GraphQLSchema,
+    pub pool: PgPool,
+    pub products: Arc<dyn ProductStore>,
+    pub users: UserRepository,
+    pub events: EventBus,
+    pub event_metrics: EventMetrics,
+    pub login: LoginService,
+    pub settings: ServerSettings,
+    pub jwt_keys: Arc<JwtKeys>,
+    pub revocations: Arc<TokenRevocations>,
+    pub shopify: Option<ShopifyClient>,
+    pub shopify_webhooks: ShopifyWebhookReceiver,
+    pub storage: Option<ObjectStorage>,
+    pub create_dedup: Arc<CreateDedup>,
+    pub shutting_down: ShuttingDown,
+}
+
+impl AppState {
+    /// Everything the handlers share, with `products` as the catalog so
+    /// tests can swap in an in-memory store.
+    pub fn new(config: &Config, pool: PgPool, products: Arc<dyn ProductStore>) -> anyhow::Result<Self> {
+        let settings = config.server.clone();
+        let timer = QueryTimer::from_settings(&config.database);
+        let events = EventBus::new();
+        let event_metrics = EventMetrics::listen(&events);
+        LowStockNotifier::new(settings.low_stock_webhook_url.clone(), settings.low_stock_threshold).listen(&events);
+        let jwt_keys = Arc::new(config.jwt_keys.clone());
+        let revocations = Arc::new(TokenRevocations::new());
+        let login_service = LoginService::new(
+            UserRepository::new(pool.clone(), timer),
+            Arc::new(LoginThrottle::new(settings.login_max_failures, settings.login_lockout)),
+            jwt_keys.clone(),
+            revocations.clone(),
+            settings.bcrypt_cost,
+        );
+        let create_dedup = Arc::new(CreateDedup::new(settings.create_dedup_window));
+        let schema = build_schema(
+            products.clone(),
+            UserRepository::new(pool.clone(), timer),
+            events.clone(),
+            &settings,
+            ServerInfo::new("axum"),
+            login_service.clone(),
+            create_dedup.clone(),
+        );
+        Ok(Self {
+            schema,
+            products,
+            users: UserRepository::new(pool.clone(), timer),
+            shopify_webhooks: ShopifyWebhookReceiver::new(
+                WebhookRepository::new(pool.clone(), timer),
+                config.shopify_webhook_secret.clone(),
+            ),
+            pool,
+            events,
+            event_metrics,
+            login: login_service,
+            settings,
+            jwt_keys,
+            revocations,
+            shopify: config.shopify.clone().map(ShopifyClient::new).transpose()?,
+            storage: config.storage.clone().map(ObjectStorage::new),
+            create_dedup,
+            shutting_down: ShuttingDown::new(),
+        })
+    }
+}
+
+/// Every route behind the middleware stack. `in_flight` counts the requests
+/// being handled so shutdown can wait for them.
+pub fn router(state: AppState, config: &Config, in_flight: InFlightRequests) -> anyhow::Result<Router> {
+    let settings = &config.server;
+    let trusted_proxies = Arc::new(settings.trusted_proxies.clone());
+    let security_headers = Arc::new(config.security_headers.clone());
+    Ok(Router::new()
+        .route("/", get(health_check))
+        .route("/health", get(health_check))
+        .route("/health/db", get(health_db))
+        .route("/health/ready", get(health_ready))
+        .route("/health/events", get(health_events))
+        .route("/version", get(version))
+        .route("/.well-known/jwks.json", get(jwks))
+        .route("/api/auth/login", post(login))
+        .route("/api/auth/register", post(register))
+        .route("/api/auth/logout", post(logout))
+        .route("/api/users", get(get_users))
+        .route("/api/users/{id}", get(get_user))
+        .route("/api/admin/users/{id}/revoke-sessions", post(revoke_user_sessions))
+        .route("/api/admin/stats", get(get_product_stats))
+        .route("/api/products", get(get_products).post(create_product))
+        .route("/api/products/bulk", post(create_products_bulk))
+        .route("/api/products/stream", get(stream_products))
+        .route("/api/products/export.csv", get(export_products))
+        .route(
+            "/api/products/{id}",
+            get(get_product)
+                .put(replace_product)
+                .patch(update_product)
+                .delete(delete_product),
+        )
+        .route("/api/products/{id}/categories", get(get_product_categories))
+        .route(
+            "/api/products/{id}/categories/{category_id}",
+            put(assign_category).delete(unassign_category),
+        )
+        .route("/api/categories", get(get_categories).post(create_category))
+        .route("/api/products/{id}/reserve", post(reserve_inventory))
+        .route("/api/products/{id}/sync-to-shopify", post(sync_product_to_shopify))
+        .route("/api/products/{id}/image-upload-url", post(create_image_upload_url))
+        .route("/api/webhooks/shopify", get(list_shopify_webhooks).post(handle_shopify_webhook))
+        .route("/graphql", post(graphql_handler))
+        .route("/graphql/ws", get(graphql_ws_handler))
+        .route("/graphiql", get(graphiql))
+        .method_not_allowed_fallback(method_not_allowed)
+        .fallback(route_not_found)
+        .layer(
+            ServiceBuilder::new()
+                .layer(axum::middleware::from_fn_with_state(in_flight, middleware::track_in_flight))
+                .layer(axum::middleware::from_fn_with_state(trusted_proxies, middleware::resolve_client_ip))
+                .layer(axum::middleware::from_fn(middleware::log_requests))
+                .layer(axum::middleware::from_fn_with_state(security_headers, middleware::security_headers))
+                .layer(TraceLayer::new_for_http())
+                .layer(middleware::cors_layer(&config.cors_origins)?)
+                .layer(DefaultBodyLimit::max(config.body_limit))
+                .layer(HandleErrorLayer::new(middleware::handle_layer_error))
+                .option_layer(settings.max_concurrent_requests.map(middleware::concurrency_limit))
+                .layer(TimeoutLayer::new(settings.request_timeout)),
+        )
+        .with_state(state))
+}
+
+/// Runs the server against the database until a shutdown signal, then
+/// drains in-flight requests.
+pub async fn serve(config: Config) -> anyhow::Result<()> {
+    init_tracing("axum_server=debug,shared=info", config.log_format);
+    init_id_kind(config.id_kind);
+    config.log_summary("axum");
+    tracing::info!(
+        workers = tokio::runtime::Handle::current().metrics().num_workers(),
+        "Tokio runtime started"
+    );
+
+    let settings = config.server.clone();
+    let pool = connect_pool(&config.database).await?;
+    if settings.run_migrations {
+        let applied = run_migrations(&pool).await?;
+        tracing::info!("Applied {} database migrations", applied);
+    }
+    let timer = QueryTimer::from_settings(&config.database);
+    let products: Arc<dyn ProductStore> = Arc::new(
+        ProductRepository::new(pool.clone(), timer).with_read_cache(config.database.product_cache_ttl),
+    );
+    let state = AppState::new(&config, pool, products)?;
+    let shutting_down = state.shutting_down.clone();
+    let in_flight = InFlightRequests::new();
+    let app = router(state, &config, in_flight.clone())?;
+
+    let tls_config = config
+        .tls
+        .as_ref()
+        .map(|tls| {
+            let mut tls_config = tls.server_config()?;
+            // The protocols actix-web offers, so both are measured alike
+            tls_config.alpn_protocols = vec![b"h2".to_vec(), b"http/1.1".to_vec()];
+            AppResult::Ok(RustlsConfig::from_config(Arc::new(tls_config)))
+        })
+        .transpose()?;
+    let (http, ws) = if tls_config.is_some() { ("https", "wss") } else { ("http", "ws") };
+
+    let listener = tokio::net::TcpListener::bind(config.bind_address()).await?;
+    tracing::info!(tls = tls_config.is_some(), "Axum server running on {}://localhost:{}", http, config.port);
+    if let Some(ui) = settings.graphql_ui.name() {
+        tracing::info!("{} available at {}://localhost:{}/graphiql", ui, http, config.port);
+    }
+    tracing::info!("GraphQL subscriptions available at {}://localhost:{}/graphql/ws", ws, config.port);
+
+    let (shutdown_tx, shutdown_rx) = tokio::sync::oneshot::channel();
+    let shutdown_delay = settings.shutdown_delay;
+    let shutdown = async move {
+        begin_shutdown(&shutting_down, shutdown_delay).await;
+        let _ = shutdown_tx.send(());
+    };
+    let app = app.into_make_service_with_connect_info::<SocketAddr>();
+    let mut server = match tls_config {
+        Some(tls_config) => {
+            let handle = axum_server_tls::Handle::new();
+            let stop = handle.clone();
+            tokio::spawn(async move {
+                shutdown.await;
+                stop.graceful_shutdown(None);
+            });
+            tokio::spawn(
+                axum_server_tls::from_tcp_rustls(listener.into_std()?, tls_config)
+                    .handle(handle)
+                    .serve(app),
+            )
+        }
+        None => tokio::spawn(axum::serve(listener, app).with_graceful_shutdown(shutdown).into_future()),
+    };
+    tokio::select! {
+        result = &mut server => return Ok(result??),
+        Ok(()) = shutdown_rx => {}
+    }
+    // axum keeps serving open connections until they close; the drain bounds
+    // that by the shutdown timeout and reports progress along the way
+    in_flight.drain(settings.shutdown_timeout).await;
+    Ok(())
+}
+
+/// 503 with status `shutting_down` once a shutdown signal has arrived.
+async fn health_check(State(state): State<AppState>) -> (StatusCode, Json<Value>) {
+    let (status, label) = if state.shutting_down.is_set() {
+        (StatusCode::SERVICE_UNAVAILABLE, "shutting_down")
+    } else {
+        (StatusCode::OK, "ok")
+    };
+    let body = json!({
+        "status": label,
+        "framework": "axum",
+        "timestamp": timestamp::format(&chrono::Utc::now())
+    });
+    (status, Json(body))
+}
+
+/// Anything no route matches gets the shared JSON 404.
+async fn route_not_found(uri: Uri) -> AppError {
+    AppError::RouteNotFound(uri.path().to_string())
+}
+
+/// A known path hit with the wrong method; axum adds the `Allow` header.
+async fn method_not_allowed() -> AppError {
+    AppError::MethodNotAllowed { allow: Vec::new() }
+}
+
+async fn version() -> Json<BuildInfo> {
+    Json(BuildInfo::new("axum", env!("CARGO_PKG_VERSION")))
+}
+
+/// Public keys to verify our tokens with; empty while signing with HS256.
+async fn jwks(State(state): State<AppState>) -> Json<Value> {
+    Json(state.jwt_keys.jwks())
+}
+
+async fn health_db(State(state): State<AppState>) -> Json<PoolStats> {
+    Json(PoolStats::from_pool(&state.pool))
+}
+
+/// 503 until the database answers a query, and again once shutting down.
+async fn health_ready(State(state): State<AppState>) -> AppResult<Json<PoolStats>> {
+    if state.shutting_down.is_set() {
+        return Err(AppError::Unavailable("server is shutting down".to_string()));
+    }
+    Ok(Json(check_database(&state.pool).await?))
+}
+
+async fn health_events(State(state): State<AppState>) -> Json<BTreeMap<&'static str, u64>> {
+    Json(state.event_metrics.snapshot())
+}
+
+async fn login(
+    State(state): State<AppState>,
+    Extension(ClientIp(client_ip)): Extension<ClientIp>,
+    Query(params): Query<LoginParams>,
+    Json(payload): Json<LoginRequest>,
+) -> AppResult<Response> {
+    payload.validate()?;
+    let (token, user) = state.login.login(&payload.email, &payload.password, client_ip).await?;
+    let cookie = params
+        .set_cookie
+        .then(|| (header::SET_COOKIE, auth_cookie_header(&state.settings.auth_cookie_name, &token)));
+    Ok((
+        AppendHeaders(cookie),
+        Json(LoginResponse {
+            token,
+            user: user.into(),
+        }),
+    )
+        .into_response())
+}
+
+/// Revokes the presented token; it stays rejected until it would have expired.
+async fn logout(State(state): State<AppState>, AuthUser(claims): AuthUser) -> impl IntoResponse {
+    state.revocations.revoke(&claims);
+    (
+        StatusCode::NO_CONTENT,
+        [(header::SET_COOKIE, expired_auth_cookie_header(&state.settings.auth_cookie_name))],
+    )
+}
+
+async fn register(
+    State(state): State<AppState>,
+    Json(payload): Json<CreateUser>,
+) -> AppResult<Json<UserResponse>> {
+    let email = validate_registration(&payload, state.settings.password_min_length)?;
+    let password_hash = hash_password_with_cost(&payload.password, state.settings.bcrypt_cost).await?;
+    let user = state
+        .users
+        .create(&payload.username, &email, &password_hash)
+        .await?;
+    Ok(Json(user.into()))
+}
+
+/// Admin only.
+async fn get_users(
+    State(state): State<AppState>,
+    AdminUser(admin): AdminUser,
+    Query(params): Query<UserListParams>,
+) -> AppResult<Response> {
+    let page = params.page(state.settings.page_limits)?;
+    tracing::debug!(admin = %admin.sub, limit = page.limit, offset = page.offset, "listing users");
+    let (users, total) = tokio::try_join!(state.users.list(page.limit, page.offset), state.users.count())?;
+    let users = Paginated::new(users, total, page).map(UserResponse::from);
+    let clamped = page.clamped.then_some((HeaderName::from_static(PAGINATION_CLAMPED_HEADER), "true"));
+    Ok((AppendHeaders(clamped), in_phase_sync("serialize", || Json(users).into_response())).into_response())
+}
+
+async fn get_user(
+    State(state): State<AppState>,
+    AuthUser(_): AuthUser,
+    Path(id): Path<String>,
+) -> AppResult<Response> {
+    let user = state
+        .users
+        .find_by_id(parse_user_id(&id)?)
+        .await?
+        .ok_or_else(|| AppError::NotFound("user".to_string()))?;
+    Ok(in_phase_sync("serialize", || Json(UserResponse::from(user)).into_response()))
+}
+
+/// Admin only. Every token issued to the user so far stops working.
+async fn revoke_user_sessions(
+    State(state): State<AppState>,
+    AdminUser(admin): AdminUser,
+    Path(id): Path<String>,
+) -> AppResult<Json<RevokeSessionsResponse>> {
+    let user = state
+        .users
+        .find_by_id(parse_user_id(&id)?)
+        .await?
+        .ok_or_else(|| AppError::NotFound("user".to_string()))?;
+    let revoked_sessions = state.revocations.revoke_user(&user.id.to_string());
+    tracing::info!(admin = %admin.sub, user = %user.id, revoked_sessions, "revoked user sessions");
+    Ok(Json(RevokeSessionsResponse { user_id: user.id, revoked_sessions }))
+}
+
+/// Admin only.
+async fn get_product_stats(
+    State(state): State<AppState>,
+    AdminUser(admin): AdminUser,
+) -> AppResult<Json<ProductStats>> {
+    tracing::debug!(admin = %admin.sub, "reading product stats");
+    Ok(Json(state.products.stats().await?))
+}
+
+async fn get_products(
+    State(state): State<AppState>,
+    uri: Uri,
+    Query(params): Query<ProductListParams>,
+) -> AppResult<Response> {
+    let page = params.page(state.settings.page_limits)?;
+    let filter = params.filter()?;
+    let (products, total) = tokio::try_join!(
+        state.products.list_page(&filter, page.limit, page.offset),
+        state.products.count(&filter)
+    )?;
+
+    let mut headers = vec![(HeaderName::from_static(TOTAL_COUNT_HEADER), total.to_string())];
+    if let Some(links) = page.links(&filter.path_with_query(uri.path()), total) {
+        headers.push((header::LINK, links));
+    }
+    if page.clamped {
+        headers.push((HeaderName::from_static(PAGINATION_CLAMPED_HEADER), "true".to_string()));
+    }
+    let body = in_phase_sync("serialize", || Json(Paginated::new(products, total, page)).into_response());
+    Ok((AppendHeaders(headers), body).into_response())
+}
+
+async fn get_product(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    headers: HeaderMap,
+) -> AppResult<Response> {
+    let product = state
+        .products
+        .find_by_id(parse_product_id(&id)?)
+        .await?
+        .ok_or_else(|| AppError::NotFound("product".to_string()))?;
+
+    let etag = etag_for(&product);
+    let not_modified = headers
+        .get(header::IF_NONE_MATCH)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|value| etag_matches(value, &etag));
+
+    if not_modified {
+        return Ok((StatusCode::NOT_MODIFIED, [(header::ETAG, etag)]).into_response());
+    }
+    let body = in_phase_sync("serialize", || Json(product).into_response());
+    Ok(([(header::ETAG, etag)], body).into_response())
+}
+
+/// Server-sent events carrying every product update as JSON.
+async fn stream_products(
+    State(state): State<AppState>,
+) -> Sse<impl Stream<Item = Result<Event, axum::Error>>> {
+    let events = product_update_stream(&state.events, None)
+        .map(|product| Event::default().event("product_update").json_data(product));
+    Sse::new(events).keep_alive(KeepAlive::new().interval(SSE_HEARTBEAT_INTERVAL))
+}
+
+/// The whole catalog as CSV, streamed row by row from the database.
+async fn export_products(State(state): State<AppState>) -> impl IntoResponse {
+    (
+        [
+            (header::CONTENT_TYPE, "text/csv; charset=utf-8"),
+            (header::CONTENT_DISPOSITION, PRODUCT_CSV_DISPOSITION),
+        ],
+        Body::from_stream(product_csv(state.products.stream_all())),
+    )
+}
+
+/// A signed-in caller repeating a create within `CREATE_DEDUP_WINDOW_MS`
+/// gets the product its first request created.
+async fn create_product(
+    State(state): State<AppState>,
+    MaybeAuthUser(claims): MaybeAuthUser,
+    Json(payload): Json<CreateProduct>,
+) -> AppResult<Response> {
+    payload.validate()?;
+    let user_id = claims.as_ref().map(|claims| claims.sub.as_str());
+    let (product, created) = state
+        .create_dedup
+        .create(user_id, payload, |input| state.products.create(input))
+        .await?;
+    if created {
+        state.events.publish(DomainEvent::ProductCreated { product: product.clone() });
+    }
+    Ok(in_phase_sync("serialize", || Json(product).into_response()))
+}
+
+/// All-or-nothing: one failed insert rolls back the whole batch.
+async fn create_products_bulk(
+    State(state): State<AppState>,
+    Json(payload): Json<Vec<CreateProduct>>,
+) -> AppResult<Json<Vec<Product>>> {
+    validate_products(&payload)?;
+    let products = state.products.create_many(payload).await?;
+    for product in &products {
+        state.events.publish(DomainEvent::ProductCreated { product: product.clone() });
+    }
+    Ok(Json(products))
+}
+
+/// `PUT` replaces every field, so the body must be a complete product.
+async fn replace_product(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    headers: HeaderMap,
+    Json(payload): Json<CreateProduct>,
+) -> AppResult<Response> {
+    let id = parse_product_id(&id)?;
+    payload.validate()?;
+    let product = apply_product_update(&state, id, payload.into(), if_match(&headers)).await?;
+    let etag = etag_for(&product);
+    let body = in_phase_sync("serialize", || Json(product).into_response());
+    Ok(([(header::ETAG, etag)], body).into_response())
+}
+
+/// `PATCH` changes only the fields present in the body.
+async fn update_product(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    headers: HeaderMap,
+    Json(payload): Json<UpdateProduct>,
+) -> AppResult<Response> {
+    let id = parse_product_id(&id)?;
+    payload.validate()?;
+    let product = apply_product_update(&state, id, payload, if_match(&headers)).await?;
+    let etag = etag_for(&product);
+    let body = in_phase_sync("serialize", || Json(product).into_response());
+    Ok(([(header::ETAG, etag)], body).into_response())
+}
+
+/// An unreadable value is kept (as empty) so it fails the check instead of
+/// silently turning the request into an unconditional write.
+fn if_match(headers: &HeaderMap) -> Option<&str> {
+    headers
+        .get(header::IF_MATCH)
+        .map(|value| value.to_str().unwrap_or_default())
+}
+
+/// With `if_match`, the update only happens while the product still has
+/// that ETag; without it the last write wins.
+async fn apply_product_update(
+    state: &AppState,
+    id: Id,
+    update: UpdateProduct,
+    if_match: Option<&str>,
+) -> AppResult<Product> {
+    let change = match if_match {
+        Some(if_match) => state.products.update_if_match(id, update, if_match).await?,
+        None => state.products.update(id, update).await?,
+    }
+    .ok_or_else(|| AppError::NotFound("product".to_string()))?;
+    let product = change.product.clone();
+    state.events.publish_update(change);
+    Ok(product)
+}
+
+async fn reserve_inventory(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    Json(payload): Json<ReserveInventory>,
+) -> AppResult<Json<Product>> {
+    let id = parse_product_id(&id)?;
+    payload.validate()?;
+    let product = state.products.reserve_inventory(id, payload.quantity).await?;
+    state.events.publish_update(ProductChange {
+        previous_inventory: product.inventory + payload.quantity,
+        product: product.clone(),
+    });
+    Ok(Json(product))
+}
+
+/// 404 when the product doesn't exist, unless `?idempotent=true`.
+async fn delete_product(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    Query(params): Query<DeleteParams>,
+) -> AppResult<StatusCode> {
+    let id = parse_product_id(&id)?;
+    if state.products.delete(id).await? {
+        state.events.publish(DomainEvent::ProductDeleted { product_id: id });
+    } else if !params.idempotent {
+        return Err(AppError::NotFound("product".to_string()));
+    }
+    Ok(StatusCode::NO_CONTENT)
+}
+
+async fn get_categories(State(state): State<AppState>) -> AppResult<Json<Vec<Category>>> {
+    Ok(Json(state.products.list_categories().await?))
+}
+
+async fn create_category(
+    State(state): State<AppState>,
+    Json(payload): Json<CreateCategory>,
+) -> AppResult<Json<Category>> {
+    payload.validate()?;
+    Ok(Json(state.products.create_category(payload).await?))
+}
+
+/// 404 when the product doesn't exist.
+async fn get_product_categories(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+) -> AppResult<Json<Vec<Category>>> {
+    let id = parse_product_id(&id)?;
+    if state.products.find_by_id(id).await?.is_none() {
+        return Err(AppError::NotFound("product".to_string()));
+    }
+    let mut categories = state.products.categories_of(&[id]).await?;
+    Ok(Json(categories.remove(&id).unwrap_or_default()))
+}
+
+/// Idempotent: `204` whether or not the category was already assigned.
+async fn assign_category(
+    State(state): State<AppState>,
+    Path((id, category_id)): Path<(String, String)>,
+) -> AppResult<StatusCode> {
+    let id = parse_product_id(&id)?;
+    let category_id = parse_category_id(&category_id)?;
+    state.products.assign_category(id, category_id).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// 404 when the category wasn't assigned to the product.
+async fn unassign_category(
+    State(state): State<AppState>,
+    Path((id, category_id)): Path<(String, String)>,
+) -> AppResult<StatusCode> {
+    let id = parse_product_id(&id)?;
+    let category_id = parse_category_id(&category_id)?;
+    if !state.products.unassign_category(id, category_id).await? {
+        return Err(AppError::NotFound("category assignment".to_string()));
+    }
+    Ok(StatusCode::NO_CONTENT)
+}
+
+async fn sync_product_to_shopify(
+    State(state): State<AppState>,
+    AdminUser(admin): AdminUser,
+    Path(id): Path<String>,
+) -> AppResult<Json<ShopifySync>> {
+    let shopify = state
+        .shopify
+        .as_ref()
+        .ok_or_else(|| AppError::Validation("Shopify sync is not configured".to_string()))?;
+    let id = parse_product_id(&id)?;
+    tracing::info!(admin = %admin.sub, product = %id, "syncing product to Shopify");
+    Ok(Json(shopify.sync(state.products.as_ref(), id).await?))
+}
+
+/// 404 for an unknown product, so no URL is signed for an image that could
+/// never be attached.
+async fn create_image_upload_url(
+    State(state): State<AppState>,
+    AdminUser(admin): AdminUser,
+    Path(id): Path<String>,
+    Json(payload): Json<CreateImageUpload>,
+) -> AppResult<Json<ImageUpload>> {
+    let storage = state
+        .storage
+        .as_ref()
+        .ok_or_else(|| AppError::Validation("image uploads are not configured".to_string()))?;
+    let id = parse_product_id(&id)?;
+    payload.validate()?;
+    let product = state
+        .products
+        .find_by_id(id)
+        .await?
+        .ok_or_else(|| AppError::NotFound("product".to_string()))?;
+    tracing::debug!(admin = %admin.sub, product = %product.id, "signing an image upload");
+    Ok(Json(storage.product_image_upload(product.id, &payload.content_type, payload.content_length)))
+}
+
+/// Takes the raw body because the HMAC is computed over the exact bytes
+/// Shopify sent.
+async fn handle_shopify_webhook(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> AppResult<StatusCode> {
+    let header = |name: &str| headers.get(name).and_then(|value| value.to_str().ok());
+    let webhook = ShopifyWebhookHeaders {
+        hmac: header("x-shopify-hmac-sha256"),
+        webhook_id: header("x-shopify-webhook-id"),
+        topic: header("x-shopify-topic"),
+    };
+    state.shopify_webhooks.receive(webhook, &body).await?;
+    Ok(StatusCode::OK)
+}
+
+async fn list_shopify_webhooks(
+    State(state): State<AppState>,
+    AdminUser(admin): AdminUser,
+    Query(params): Query<WebhookListParams>,
+) -> AppResult<Json<Vec<ShopifyWebhook>>> {
+    tracing::debug!(admin = %admin.sub, processed = ?params.processed, "listing Shopify webhooks");
+    Ok(Json(state.shopify_webhooks.list(params.processed).await?))
+}
+
+/// Accepts a single operation or a batch (JSON array). A valid bearer token
+/// is passed to resolvers as `Claims`; without one the request still runs,
+/// and guarded fields answer `UNAUTHENTICATED`.
+async fn graphql_handler(
+    State(state): State<AppState>,
+    Extension(ClientIp(client_ip)): Extension<ClientIp>,
+    headers: HeaderMap,
+    req: GraphQLBatchRequest,
+) -> Response {
+    let cookie = auth_cookie(&headers, &state.settings.auth_cookie_name);
+    let claims = claims_from_auth_header(&state.jwt_keys, &state.revocations, authorization(&headers), cookie).ok();
+    let response = execute_graphql(
+        &state.schema,
+        req.into_inner(),
+        claims,
+        client_ip,
+        state.settings.graphql_max_batch_size,
+        state.settings.graphql_max_request_bytes,
+    )
+    .await;
+    let status = if rejected_before_execution(&response) {
+        StatusCode::BAD_REQUEST
+    } else {
+        StatusCode::OK
+    };
+    (status, GraphQLResponse::from(response)).into_response()
+}
+
+/// Subscriptions over WebSocket. Each connection gets an id that, with the
+/// upgrade request's id, tags the subscription's log lines. Operations on
+/// the connection run as the user the upgrade request authenticated.
+async fn graphql_ws_handler(
+    State(state): State<AppState>,
+    request_id: Option<Extension<RequestId>>,
+    Extension(ClientIp(client_ip)): Extension<ClientIp>,
+    headers: HeaderMap,
+    protocol: GraphQLProtocol,
+    upgrade: WebSocketUpgrade,
+) -> Response {
+    let connection = SubscriptionConnection::new(request_id.as_ref().map(|Extension(id)| id));
+    tracing::debug!(connection_id = %connection.connection_id, "graphql websocket opened");
+    let cookie = auth_cookie(&headers, &state.settings.auth_cookie_name);
+    let claims = claims_from_auth_header(&state.jwt_keys, &state.revocations, authorization(&headers), cookie).ok();
+    let data = websocket_data(connection, claims, client_ip);
+    upgrade
+        .protocols(ALL_WEBSOCKET_PROTOCOLS)
+        .on_upgrade(move |stream| GraphQLWebSocket::new(stream, state.schema, protocol).with_data(data).serve())
+}
+
+async fn graphiql(State(state): State<AppState>) -> AppResult<Html<String>> {
+    state
+        .settings
+        .graphql_ui
+        .page()
+        .map(Html)
+        .ok_or_else(|| AppError::NotFound("GraphQL UI".to_string()))
+}
\ No newline at end of file
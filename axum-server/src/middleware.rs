@@ -0,0 +1,145 @@
+use axum::{
+    extract::{ConnectInfo, Request, State},
+    http::{header, HeaderName, HeaderValue},
+    middleware::Next,
+    response::Response,
+    BoxError,
+};
+use shared::{
+    client_ip::{ClientIp, TrustedProxies},
+    error::AppError,
+    models::TOTAL_COUNT_HEADER,
+    pagination::PAGINATION_CLAMPED_HEADER,
+    security_headers::SecurityHeaders,
+    shutdown::InFlightRequests,
+    telemetry::{redact_headers, request_span, RequestId, REQUEST_ID_HEADER},
+};
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+use std::sync::Arc;
+use std::time::Instant;
+use tower::layer::util::{Identity, Stack};
+use tower::limit::GlobalConcurrencyLimitLayer;
+use tower::load_shed::LoadShedLayer;
+use tower::ServiceBuilder;
+use tower_http::cors::{Any, CorsLayer};
+use tracing::Instrument;
+use uuid::Uuid;
+
+/// Maps errors raised by the tower layers (timeout and load shedding) onto
+/// the shared `AppError` JSON responses.
+pub async fn handle_layer_error(err: BoxError) -> AppError {
+    if err.is::<tower::timeout::error::Elapsed>() {
+        AppError::Timeout
+    } else if err.is::<tower::load_shed::error::Overloaded>() {
+        AppError::Overloaded
+    } else {
+        AppError::Internal(anyhow::anyhow!(err.to_string()))
+    }
+}
+
+/// Sheds requests beyond `max` in flight instead of queueing them. The
+/// global variant shares one semaphore across all routes; the plain
+/// `ConcurrencyLimitLayer` would count every route separately.
+pub fn concurrency_limit(max: usize) -> ServiceBuilder<Stack<GlobalConcurrencyLimitLayer, Stack<LoadShedLayer, Identity>>> {
+    ServiceBuilder::new()
+        .layer(LoadShedLayer::new())
+        .layer(GlobalConcurrencyLimitLayer::new(max))
+}
+
+/// Counts the request as in flight until its handler has responded.
+pub async fn track_in_flight(State(in_flight): State<InFlightRequests>, req: Request, next: Next) -> Response {
+    let _guard = in_flight.track();
+    next.run(req).await
+}
+
+/// Stores the request's `ClientIp`, taken from the forwarding headers when
+/// the peer is a trusted proxy, for `log_requests` and the handlers.
+pub async fn resolve_client_ip(State(proxies): State<Arc<TrustedProxies>>, mut req: Request, next: Next) -> Response {
+    let peer = req
+        .extensions()
+        .get::<ConnectInfo<SocketAddr>>()
+        .map_or(IpAddr::V4(Ipv4Addr::UNSPECIFIED), |ConnectInfo(addr)| addr.ip());
+    let headers = req.headers();
+    let header = |name: &str| headers.get(name).and_then(|value| value.to_str().ok());
+    let client_ip = proxies.client_ip(peer, header("x-forwarded-for"), header("x-real-ip"));
+    req.extensions_mut().insert(ClientIp(client_ip));
+    next.run(req).await
+}
+
+/// Emits one structured event per request and echoes the request id back,
+/// reusing the client's `x-request-id` when one was sent.
+pub async fn log_requests(mut req: Request, next: Next) -> Response {
+    let request_id = req
+        .headers()
+        .get(REQUEST_ID_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .map(str::to_string)
+        .unwrap_or_else(|| Uuid::new_v4().to_string());
+    let method = req.method().to_string();
+    let path = req.uri().path().to_string();
+    let client_ip = req
+        .extensions()
+        .get::<ClientIp>()
+        .map(|ClientIp(ip)| ip.to_string())
+        .unwrap_or_default();
+    req.extensions_mut().insert(RequestId(request_id.clone()));
+    if tracing::enabled!(tracing::Level::DEBUG) {
+        let headers = redact_headers(
+            req.headers()
+                .iter()
+                .map(|(name, value)| (name.as_str(), value.to_str().unwrap_or("<binary>"))),
+        );
+        tracing::debug!(request_id = %request_id, headers = %headers, "request received");
+    }
+
+    let started = Instant::now();
+    let mut response = next.run(req).instrument(request_span(&request_id, &method, &path)).await;
+
+    tracing::info!(
+        request_id = %request_id,
+        method = %method,
+        path = %path,
+        client_ip = %client_ip,
+        status = response.status().as_u16(),
+        latency_ms = started.elapsed().as_secs_f64() * 1000.0,
+        "request completed"
+    );
+
+    if let Ok(value) = HeaderValue::from_str(&request_id) {
+        response.headers_mut().insert(REQUEST_ID_HEADER, value);
+    }
+    response
+}
+
+/// Adds the shared security headers to every response, error or not.
+pub async fn security_headers(State(headers): State<Arc<SecurityHeaders>>, req: Request, next: Next) -> Response {
+    let path = req.uri().path().to_string();
+    let mut response = next.run(req).await;
+    for (name, value) in headers.for_path(&path) {
+        if let Ok(value) = HeaderValue::from_str(value) {
+            response.headers_mut().entry(name).or_insert(value);
+        }
+    }
+    response
+}
+
+/// Allows any origin when `origins` is empty, otherwise exactly those listed.
+pub fn cors_layer(origins: &[String]) -> anyhow::Result<CorsLayer> {
+    if origins.is_empty() {
+        return Ok(CorsLayer::permissive());
+    }
+
+    let origins = origins
+        .iter()
+        .map(|origin| HeaderValue::from_str(origin))
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(CorsLayer::new()
+        .allow_origin(origins)
+        .allow_methods(Any)
+        .allow_headers(Any)
+        .expose_headers([
+            HeaderName::from_static(TOTAL_COUNT_HEADER),
+            HeaderName::from_static(PAGINATION_CLAMPED_HEADER),
+            header::LINK,
+        ]))
+}
\ No newline at end of file
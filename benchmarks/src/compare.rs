@@ -0,0 +1,203 @@
+use anyhow::{bail, Context, Result};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use tabled::{Table, Tabled};
+
+use crate::{BenchmarkResult, OperationResult};
+
+/// One row of a `--json-out` file. Measurements a run doesn't produce, such
+/// as the per-operation throughput of `mixed`, are `null`.
+#[derive(Serialize, Deserialize)]
+pub struct SavedResult {
+    pub framework: String,
+    pub endpoint: String,
+    pub requests: usize,
+    pub requests_per_second: Option<f64>,
+    pub p99_ms: Option<f64>,
+    /// Percent of requests that succeeded.
+    pub success_rate: Option<f64>,
+}
+
+impl From<&BenchmarkResult> for SavedResult {
+    fn from(result: &BenchmarkResult) -> Self {
+        Self {
+            framework: result.framework.clone(),
+            endpoint: result.endpoint.clone(),
+            requests: result.total_requests,
+            requests_per_second: Some(result.requests_per_second),
+            p99_ms: Some(result.p99_ms),
+            success_rate: Some(result.success_rate),
+        }
+    }
+}
+
+impl From<&OperationResult> for SavedResult {
+    fn from(result: &OperationResult) -> Self {
+        Self {
+            framework: result.framework.clone(),
+            endpoint: result.operation.clone(),
+            requests: result.requests,
+            requests_per_second: None,
+            p99_ms: Some(result.p99_ms),
+            success_rate: Some(result.success_rate),
+        }
+    }
+}
+
+impl SavedResult {
+    /// A whole run's throughput, e.g. of the mixed workload, without the
+    /// latencies its operations report on their own.
+    pub fn throughput(framework: &str, endpoint: &str, requests: usize, requests_per_second: f64) -> Self {
+        Self {
+            framework: framework.to_string(),
+            endpoint: endpoint.to_string(),
+            requests,
+            requests_per_second: Some(requests_per_second),
+            p99_ms: None,
+            success_rate: None,
+        }
+    }
+}
+
+/// Results gathered during a run for `--json-out`.
+#[derive(Clone, Default)]
+pub struct ResultRecorder {
+    results: Arc<Mutex<Vec<SavedResult>>>,
+}
+
+impl ResultRecorder {
+    pub fn record(&self, result: SavedResult) {
+        self.results.lock().unwrap().push(result);
+    }
+
+    /// Writes every recorded result as a JSON array, in the order the runs
+    /// finished.
+    pub fn write_json(&self, path: &Path) -> Result<()> {
+        let json = serde_json::to_string_pretty(&*self.results.lock().unwrap())?;
+        std::fs::write(path, json).with_context(|| format!("failed to write results to {}", path.display()))
+    }
+}
+
+fn read_results(path: &Path) -> Result<Vec<SavedResult>> {
+    let json = std::fs::read_to_string(path).with_context(|| format!("failed to read {}", path.display()))?;
+    serde_json::from_str(&json).with_context(|| format!("{} is not a --json-out results file", path.display()))
+}
+
+#[derive(Tabled)]
+struct ComparisonRow {
+    framework: String,
+    endpoint: String,
+    baseline_rps: String,
+    current_rps: String,
+    rps_change: String,
+    baseline_p99_ms: String,
+    current_p99_ms: String,
+    p99_change: String,
+    success_rate_change: String,
+    result: String,
+}
+
+/// `current` relative to `baseline`, in percent.
+fn percent_change(baseline: Option<f64>, current: Option<f64>) -> Option<f64> {
+    match (baseline, current) {
+        (Some(baseline), Some(current)) if baseline > 0.0 => Some((current - baseline) / baseline * 100.0),
+        _ => None,
+    }
+}
+
+fn measurement(value: Option<f64>) -> String {
+    value.map_or_else(|| "-".to_string(), |value| format!("{:.2}", value))
+}
+
+fn change(value: Option<f64>, unit: &str) -> String {
+    value.map_or_else(|| "-".to_string(), |value| format!("{:+.1}{}", value, unit))
+}
+
+/// Prints the deltas between two `--json-out` files for every framework
+/// and endpoint in either, and returns the regressions: throughput down or
+/// p99 up by more than `threshold` percent, or the success rate down by more
+/// than `max_success_drop` points. Endpoints found in only one file are
+/// listed but never count as regressions.
+pub fn run_compare(baseline: &Path, current: &Path, threshold: f64, max_success_drop: f64) -> Result<Vec<String>> {
+    let valid = |limit: f64| limit.is_finite() && limit >= 0.0;
+    if !valid(threshold) || !valid(max_success_drop) {
+        bail!("--threshold and --max-success-drop must not be negative");
+    }
+    let baseline_results = read_results(baseline)?;
+    let mut current_results = read_results(current)?;
+
+    let mut rows = Vec::new();
+    let mut regressions = Vec::new();
+    for before in &baseline_results {
+        let position = current_results
+            .iter()
+            .position(|after| after.framework == before.framework && after.endpoint == before.endpoint);
+        let Some(after) = position.map(|position| current_results.remove(position)) else {
+            rows.push(ComparisonRow {
+                framework: before.framework.clone(),
+                endpoint: before.endpoint.clone(),
+                baseline_rps: measurement(before.requests_per_second),
+                current_rps: "-".to_string(),
+                rps_change: "-".to_string(),
+                baseline_p99_ms: measurement(before.p99_ms),
+                current_p99_ms: "-".to_string(),
+                p99_change: "-".to_string(),
+                success_rate_change: "-".to_string(),
+                result: "only in baseline".to_string(),
+            });
+            continue;
+        };
+
+        let rps_change = percent_change(before.requests_per_second, after.requests_per_second);
+        let p99_change = percent_change(before.p99_ms, after.p99_ms);
+        let success_rate_change = match (before.success_rate, after.success_rate) {
+            (Some(before), Some(after)) => Some(after - before),
+            _ => None,
+        };
+
+        let label = format!("{} {}", before.framework, before.endpoint);
+        let mut problems = Vec::new();
+        if let Some(rps_change) = rps_change.filter(|change| *change < -threshold) {
+            problems.push(format!("{}: throughput {:+.1}%", label, rps_change));
+        }
+        if let Some(p99_change) = p99_change.filter(|change| *change > threshold) {
+            problems.push(format!("{}: p99 {:+.1}%", label, p99_change));
+        }
+        if let Some(drop) = success_rate_change.filter(|change| *change < -max_success_drop) {
+            problems.push(format!("{}: success rate {:+.1} points", label, drop));
+        }
+
+        rows.push(ComparisonRow {
+            framework: before.framework.clone(),
+            endpoint: before.endpoint.clone(),
+            baseline_rps: measurement(before.requests_per_second),
+            current_rps: measurement(after.requests_per_second),
+            rps_change: change(rps_change, "%"),
+            baseline_p99_ms: measurement(before.p99_ms),
+            current_p99_ms: measurement(after.p99_ms),
+            p99_change: change(p99_change, "%"),
+            success_rate_change: change(success_rate_change, " pts"),
+            result: if problems.is_empty() { "ok" } else { "REGRESSION" }.to_string(),
+        });
+        regressions.extend(problems);
+    }
+    for after in current_results {
+        rows.push(ComparisonRow {
+            framework: after.framework,
+            endpoint: after.endpoint,
+            baseline_rps: "-".to_string(),
+            current_rps: measurement(after.requests_per_second),
+            rps_change: "-".to_string(),
+            baseline_p99_ms: "-".to_string(),
+            current_p99_ms: measurement(after.p99_ms),
+            p99_change: "-".to_string(),
+            success_rate_change: "-".to_string(),
+            result: "only in current".to_string(),
+        });
+    }
+
+    println!("\n📊 {} vs {}:", current.display(), baseline.display());
+    println!("{}", Table::new(&rows));
+    Ok(regressions)
+}
\ No newline at end of file
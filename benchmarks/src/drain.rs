@@ -0,0 +1,198 @@
+use anyhow::{anyhow, bail, Context, Result};
+use futures::future::join_all;
+use nix::sys::signal::{kill, Signal};
+use nix::unistd::Pid;
+use reqwest::Client;
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tabled::{Table, Tabled};
+use tokio::process::{Child, Command};
+use tokio::time::sleep;
+use tracing::info;
+
+use crate::{http_client, server_url};
+
+/// How long a launched server gets to start answering `/health`.
+const STARTUP_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// How long a signalled server gets to exit before it is killed.
+const EXIT_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// Pause after a failed request. Once the server stops listening, failures
+/// come back instantly, so workers back off rather than spin.
+const FAILURE_BACKOFF: Duration = Duration::from_millis(5);
+
+#[derive(Tabled)]
+struct DrainResult {
+    framework: String,
+    /// Requests started and answered before the signal.
+    before_signal: usize,
+    /// Requests started before the signal and answered after it.
+    in_flight: usize,
+    /// In-flight requests that still succeeded.
+    drained: usize,
+    /// In-flight requests that failed or lost their connection.
+    dropped: usize,
+    /// Requests started after the signal that succeeded anyway.
+    served_after_signal: usize,
+    /// Requests started after the signal that failed, e.g. refused.
+    refused_after_signal: usize,
+    /// From the signal to the process exiting.
+    shutdown_ms: u128,
+}
+
+/// What one request ran into, relative to the signal.
+struct Sample {
+    started: Instant,
+    finished: Instant,
+    success: bool,
+}
+
+/// A server binary to launch, and where.
+pub struct DrainTarget {
+    pub framework: &'static str,
+    pub binary: PathBuf,
+    pub port: u16,
+}
+
+/// The server binaries built next to this one, e.g. `target/release`.
+pub fn sibling_binary(name: &str) -> PathBuf {
+    std::env::current_exe()
+        .map(|exe| exe.with_file_name(name))
+        .unwrap_or_else(|_| PathBuf::from(name))
+}
+
+/// Launches each server in turn, loads `path` from `concurrency` workers,
+/// sends SIGTERM after `signal_after` and counts which requests the
+/// graceful shutdown let finish. The servers inherit this environment, so
+/// they use the same database and `SHUTDOWN_TIMEOUT_SECS`.
+pub async fn run_drain_benchmark(
+    targets: &[DrainTarget],
+    concurrency: usize,
+    path: &str,
+    signal_after: Duration,
+) -> Result<()> {
+    if concurrency == 0 {
+        bail!("concurrency must be at least 1");
+    }
+    info!(
+        "Running drain benchmark ({} workers, SIGTERM after {} ms)...",
+        concurrency,
+        signal_after.as_millis()
+    );
+
+    let mut rows = Vec::new();
+    for target in targets {
+        rows.push(measure_drain(target, concurrency, path, signal_after).await?);
+    }
+    println!("{}", Table::new(&rows));
+    Ok(())
+}
+
+async fn measure_drain(target: &DrainTarget, concurrency: usize, path: &str, signal_after: Duration) -> Result<DrainResult> {
+    let base_url = server_url(target.port);
+    let mut child = launch(&target.binary, target.port)?;
+    let client = http_client();
+    wait_until_healthy(&client, &base_url, &mut child).await?;
+
+    let url = format!("{}{}", base_url, path);
+    let stop = Arc::new(AtomicBool::new(false));
+    let workers: Vec<_> = (0..concurrency)
+        .map(|_| tokio::spawn(load(client.clone(), url.clone(), stop.clone())))
+        .collect();
+
+    sleep(signal_after).await;
+    let pid = child.id().ok_or_else(|| anyhow!("{} exited before the signal", target.framework))?;
+    let signalled_at = Instant::now();
+    kill(Pid::from_raw(pid as i32), Signal::SIGTERM).context("failed to send SIGTERM")?;
+    info!(framework = target.framework, pid, "sent SIGTERM");
+
+    let exited = tokio::time::timeout(EXIT_TIMEOUT, child.wait()).await;
+    let shutdown = signalled_at.elapsed();
+    stop.store(true, Ordering::Relaxed);
+    if exited.is_err() {
+        child.kill().await?;
+        bail!("{} did not exit within {} s of SIGTERM", target.framework, EXIT_TIMEOUT.as_secs());
+    }
+
+    let mut result = DrainResult {
+        framework: target.framework.to_string(),
+        before_signal: 0,
+        in_flight: 0,
+        drained: 0,
+        dropped: 0,
+        served_after_signal: 0,
+        refused_after_signal: 0,
+        shutdown_ms: shutdown.as_millis(),
+    };
+    for worker in join_all(workers).await {
+        for sample in worker? {
+            if sample.finished < signalled_at {
+                result.before_signal += 1;
+            } else if sample.started < signalled_at {
+                result.in_flight += 1;
+                if sample.success {
+                    result.drained += 1;
+                } else {
+                    result.dropped += 1;
+                }
+            } else if sample.success {
+                result.served_after_signal += 1;
+            } else {
+                result.refused_after_signal += 1;
+            }
+        }
+    }
+    Ok(result)
+}
+
+fn launch(binary: &Path, port: u16) -> Result<Child> {
+    Command::new(binary)
+        .env("PORT", port.to_string())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .kill_on_drop(true)
+        .spawn()
+        .with_context(|| format!("failed to launch {}", binary.display()))
+}
+
+async fn wait_until_healthy(client: &Client, base_url: &str, child: &mut Child) -> Result<()> {
+    let deadline = Instant::now() + STARTUP_TIMEOUT;
+    loop {
+        if let Some(status) = child.try_wait()? {
+            bail!("server on {} exited during startup with {}", base_url, status);
+        }
+        let healthy = client.get(format!("{}/health", base_url)).send().await;
+        if healthy.is_ok_and(|response| response.status().is_success()) {
+            return Ok(());
+        }
+        if Instant::now() > deadline {
+            bail!("server on {} did not become healthy", base_url);
+        }
+        sleep(Duration::from_millis(100)).await;
+    }
+}
+
+/// Sends requests back to back until `stop`, then reports each one.
+async fn load(client: Client, url: String, stop: Arc<AtomicBool>) -> Vec<Sample> {
+    let mut samples = Vec::new();
+    while !stop.load(Ordering::Relaxed) {
+        let started = Instant::now();
+        let success = match client.get(&url).send().await {
+            Ok(response) => response.status().is_success() && response.bytes().await.is_ok(),
+            Err(_) => false,
+        };
+        samples.push(Sample {
+            started,
+            finished: Instant::now(),
+            success,
+        });
+        if !success {
+            sleep(FAILURE_BACKOFF).await;
+        }
+    }
+    samples
+}
\ No newline at end of file
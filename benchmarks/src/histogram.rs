@@ -0,0 +1,88 @@
+use anyhow::Result;
+use std::collections::BTreeMap;
+use std::fmt::Write as _;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// Buckets per factor of ten in latency, so each bucket's upper bound is
+/// about 26% above its lower bound at any scale.
+const BUCKETS_PER_DECADE: f64 = 10.0;
+
+/// Upper bound of the first bucket; faster requests are counted there.
+const SMALLEST_BUCKET_MS: f64 = 0.01;
+
+/// Latency distribution of one framework and endpoint, counted in
+/// logarithmic buckets so sub-millisecond and multi-second latencies are both
+/// resolved.
+pub struct Histogram {
+    framework: String,
+    endpoint: String,
+    /// Bucket index to request count; see `bucket_bounds_ms`.
+    counts: BTreeMap<u32, u64>,
+}
+
+impl Histogram {
+    pub fn new(framework: &str, endpoint: &str, durations: &[Duration]) -> Self {
+        let mut counts = BTreeMap::new();
+        for duration in durations {
+            *counts.entry(bucket_index(duration.as_secs_f64() * 1000.0)).or_default() += 1;
+        }
+        Self {
+            framework: framework.to_string(),
+            endpoint: endpoint.to_string(),
+            counts,
+        }
+    }
+}
+
+fn bucket_index(ms: f64) -> u32 {
+    if ms < SMALLEST_BUCKET_MS {
+        return 0;
+    }
+    ((ms / SMALLEST_BUCKET_MS).log10() * BUCKETS_PER_DECADE).floor() as u32 + 1
+}
+
+/// `[lower, upper)` in milliseconds; bucket 0 starts at zero.
+fn bucket_bounds_ms(index: u32) -> (f64, f64) {
+    let bound = |index: u32| SMALLEST_BUCKET_MS * 10f64.powf(index as f64 / BUCKETS_PER_DECADE);
+    match index {
+        0 => (0.0, SMALLEST_BUCKET_MS),
+        _ => (bound(index - 1), bound(index)),
+    }
+}
+
+/// Histograms gathered during a run for `--histogram-out`.
+#[derive(Clone, Default)]
+pub struct HistogramRecorder {
+    histograms: Arc<Mutex<Vec<Histogram>>>,
+}
+
+impl HistogramRecorder {
+    pub fn record(&self, framework: &str, endpoint: &str, durations: &[Duration]) {
+        self.histograms
+            .lock()
+            .unwrap()
+            .push(Histogram::new(framework, endpoint, durations));
+    }
+
+    /// Writes one CSV row per bucket:
+    /// `framework,endpoint,lower_ms,upper_ms,count`. Every bucket between a
+    /// histogram's fastest and slowest request is listed, empty ones with a
+    /// count of 0, so the rows can be plotted directly.
+    pub fn write_csv(&self, path: &Path) -> Result<()> {
+        let mut csv = String::from("framework,endpoint,lower_ms,upper_ms,count\n");
+        for histogram in self.histograms.lock().unwrap().iter() {
+            let (Some(&first), Some(&last)) = (histogram.counts.keys().next(), histogram.counts.keys().last()) else {
+                continue;
+            };
+            for index in first..=last {
+                let (lower, upper) = bucket_bounds_ms(index);
+                let count = histogram.counts.get(&index).copied().unwrap_or(0);
+                writeln!(csv, "{},{},{:.4},{:.4},{}", histogram.framework, histogram.endpoint, lower, upper, count)?;
+            }
+        }
+        std::fs::write(path, csv)
+            .map_err(|e| anyhow::anyhow!("failed to write histogram to {}: {}", path.display(), e))
+    }
+}
\ No newline at end of file
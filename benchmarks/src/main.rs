@@ -1,8 +1,24 @@
+mod compare;
+mod drain;
+mod histogram;
+mod parity;
+mod plan;
+mod replay;
+mod subscription;
+
 use anyhow::Result;
-use clap::{Parser, Subcommand};
+use clap::{Args, Parser, Subcommand};
 use futures::future::join_all;
-use reqwest::Client;
+use compare::{ResultRecorder, SavedResult};
+use histogram::HistogramRecorder;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use reqwest::{Client, Method, RequestBuilder};
 use serde_json::json;
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::sync::{Arc, OnceLock};
 use std::time::{Duration, Instant};
 use tabled::{Table, Tabled};
 use tokio::time::sleep;
@@ -15,6 +31,212 @@ use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
 struct Cli {
     #[command(subcommand)]
     command: Commands,
+    /// Retry failed requests up to N times with exponential backoff
+    #[arg(long, global = true, default_value_t = 0)]
+    retries: u32,
+    /// Pause each worker for this long between its requests (between whole
+    /// chains for `flow`), simulating users rather than a tight loop
+    #[arg(long, global = true, default_value_t = 0)]
+    think_time_ms: u64,
+    /// Write the latency distribution of every run to this CSV file
+    #[arg(long, global = true)]
+    histogram_out: Option<PathBuf>,
+    /// Save the results of every run to this JSON file, for `compare`
+    #[arg(long, global = true)]
+    json_out: Option<PathBuf>,
+    /// Connect over HTTPS (and WSS), to servers started with `TLS_CERT` and
+    /// `TLS_KEY`
+    #[arg(long, global = true)]
+    tls: bool,
+    /// Accept any server certificate, e.g. a self-signed one
+    #[arg(long, global = true)]
+    insecure: bool,
+    /// Before the timed run, wait until `/health/ready` reports the database
+    /// reachable and open pool connections with a burst of readiness checks
+    #[arg(long, global = true)]
+    wait_db: bool,
+    /// Print the targets, requests and concurrency the command would use,
+    /// then exit without sending anything
+    #[arg(long, global = true)]
+    dry_run: bool,
+    /// Request rate per server the `--dry-run` duration estimate assumes
+    #[arg(long, global = true)]
+    target_rps: Option<f64>,
+}
+
+/// How the servers are reached and when they count as ready, fixed by
+/// `--tls`, `--insecure` and `--wait-db` before anything is sent.
+#[derive(Default)]
+struct Connection {
+    tls: bool,
+    insecure: bool,
+    wait_db: bool,
+}
+
+static CONNECTION: OnceLock<Connection> = OnceLock::new();
+
+fn connection() -> &'static Connection {
+    CONNECTION.get_or_init(Connection::default)
+}
+
+fn server_url(port: u16) -> String {
+    let scheme = if connection().tls { "https" } else { "http" };
+    format!("{}://localhost:{}", scheme, port)
+}
+
+fn axum_url() -> String {
+    server_url(3000)
+}
+
+fn actix_url() -> String {
+    server_url(3001)
+}
+
+/// A client that skips certificate verification under `--insecure`.
+fn http_client() -> Client {
+    Client::builder()
+        .danger_accept_invalid_certs(connection().insecure)
+        .build()
+        .expect("failed to build the HTTP client")
+}
+
+/// Options shared by every benchmark run regardless of the subcommand.
+#[derive(Clone)]
+struct RunOptions {
+    retries: u32,
+    /// Pause between a worker's requests. It counts towards the total time,
+    /// so it lowers the reported throughput, but not towards any latency.
+    think_time: Duration,
+    /// Collects latencies when `--histogram-out` is given.
+    histograms: Option<HistogramRecorder>,
+    /// Collects result rows when `--json-out` is given.
+    results: Option<ResultRecorder>,
+}
+
+/// Regression gate: after a run, any framework missing a threshold makes the
+/// command fail. Each value is either one number for both frameworks
+/// (`--min-rps 500`) or per framework (`--min-rps axum=800,actixweb=600`).
+#[derive(Args, Clone, Default)]
+struct Thresholds {
+    /// Lowest acceptable requests per second
+    #[arg(long)]
+    min_rps: Option<FrameworkLimit>,
+    /// Highest acceptable p99 latency in milliseconds
+    #[arg(long)]
+    max_p99_ms: Option<FrameworkLimit>,
+}
+
+impl Thresholds {
+    /// Describes every threshold `framework` missed on `label`. Measurements
+    /// passed as `None` are not checked.
+    fn violations(&self, framework: &str, label: &str, rps: Option<f64>, p99_ms: Option<f64>) -> Vec<String> {
+        let mut violations = Vec::new();
+        if let (Some(rps), Some(min)) = (rps, self.min_rps.as_ref().and_then(|limit| limit.get(framework))) {
+            if rps < min {
+                violations.push(format!("{} {}: {:.2} req/s is below the minimum of {}", framework, label, rps, min));
+            }
+        }
+        if let (Some(p99_ms), Some(max)) = (p99_ms, self.max_p99_ms.as_ref().and_then(|limit| limit.get(framework))) {
+            if p99_ms > max {
+                violations.push(format!("{} {}: p99 of {:.2} ms exceeds the maximum of {}", framework, label, p99_ms, max));
+            }
+        }
+        violations
+    }
+
+    fn check_endpoints(&self, results: &[BenchmarkResult]) -> Vec<String> {
+        results
+            .iter()
+            .flat_map(|result| {
+                self.violations(&result.framework, &result.endpoint, Some(result.requests_per_second), Some(result.p99_ms))
+            })
+            .collect()
+    }
+}
+
+/// The operation the GraphQL benchmark sends; without `--query-file` a
+/// plain product listing.
+#[derive(Args, Clone, Default)]
+struct GraphqlQuery {
+    /// File holding the GraphQL document to send
+    #[arg(long)]
+    query_file: Option<PathBuf>,
+    /// Variables of the operation, as a JSON object
+    #[arg(long)]
+    variables: Option<String>,
+    /// Operation to run when the document defines several
+    #[arg(long)]
+    operation_name: Option<String>,
+}
+
+impl GraphqlQuery {
+    const DEFAULT_QUERY: &'static str = "{ products { id name price inventory } }";
+
+    /// Request body per GraphQL over HTTP; unset variables and operation
+    /// name are sent as `null`.
+    fn body(&self) -> Result<serde_json::Value> {
+        let query = match &self.query_file {
+            Some(path) => std::fs::read_to_string(path)
+                .map_err(|e| anyhow::anyhow!("failed to read {}: {}", path.display(), e))?,
+            None => Self::DEFAULT_QUERY.to_string(),
+        };
+        let variables = match &self.variables {
+            Some(variables) => match serde_json::from_str(variables) {
+                Ok(variables @ serde_json::Value::Object(_)) => variables,
+                _ => anyhow::bail!("--variables must be a JSON object"),
+            },
+            None => serde_json::Value::Null,
+        };
+        Ok(json!({
+            "query": query,
+            "variables": variables,
+            "operationName": self.operation_name,
+        }))
+    }
+}
+
+/// A threshold value for both frameworks, optionally overridden per
+/// framework, e.g. `500` or `400,axum=500`.
+#[derive(Clone, Debug)]
+struct FrameworkLimit {
+    default: Option<f64>,
+    per_framework: HashMap<String, f64>,
+}
+
+impl FrameworkLimit {
+    fn get(&self, framework: &str) -> Option<f64> {
+        self.per_framework
+            .get(&framework.to_lowercase())
+            .copied()
+            .or(self.default)
+    }
+}
+
+impl FromStr for FrameworkLimit {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let mut limit = FrameworkLimit { default: None, per_framework: HashMap::new() };
+        for entry in value.split(',').map(str::trim) {
+            let parse = |number: &str| {
+                number
+                    .trim()
+                    .parse::<f64>()
+                    .map_err(|_| format!("invalid threshold: {}", number))
+            };
+            match entry.split_once('=') {
+                Some((framework, number)) => {
+                    let framework = framework.trim().to_lowercase();
+                    if framework != "axum" && framework != "actixweb" {
+                        return Err(format!("unknown framework {} (expected axum or actixweb)", framework));
+                    }
+                    limit.per_framework.insert(framework, parse(number)?);
+                }
+                None => limit.default = Some(parse(entry)?),
+            }
+        }
+        Ok(limit)
+    }
 }
 
 #[derive(Subcommand)]
@@ -27,6 +249,8 @@ enum Commands {
         /// Total number of requests
         #[arg(short, long, default_value_t = 1000)]
         requests: usize,
+        #[command(flatten)]
+        thresholds: Thresholds,
     },
     /// Run REST API benchmark
     Rest {
@@ -36,6 +260,8 @@ enum Commands {
         /// Total number of requests
         #[arg(short, long, default_value_t = 500)]
         requests: usize,
+        #[command(flatten)]
+        thresholds: Thresholds,
     },
     /// Run GraphQL benchmark
     Graphql {
@@ -45,9 +271,123 @@ enum Commands {
         /// Total number of requests
         #[arg(short, long, default_value_t = 300)]
         requests: usize,
+        #[command(flatten)]
+        query: GraphqlQuery,
+        #[command(flatten)]
+        thresholds: Thresholds,
+    },
+    /// Run a weighted mix of operations against both servers
+    Mixed {
+        /// Number of concurrent requests
+        #[arg(short, long, default_value_t = 50)]
+        concurrency: usize,
+        /// Total number of requests
+        #[arg(short, long, default_value_t = 500)]
+        requests: usize,
+        /// Comma-separated operation=weight pairs
+        /// (operations: health, list-products, get-product, create-product, graphql-products)
+        #[arg(short, long, default_value = "list-products=70,health=20,create-product=10")]
+        mix: String,
+        /// Seed for the operation picker so runs are comparable
+        #[arg(long, default_value_t = 42)]
+        seed: u64,
+        #[command(flatten)]
+        thresholds: Thresholds,
+    },
+    /// Run signed-in user sessions: log in, create a product with the
+    /// token, read it back
+    Flow {
+        /// Number of concurrent sessions
+        #[arg(short, long, default_value_t = 20)]
+        concurrency: usize,
+        /// Total number of login → create → read chains
+        #[arg(short, long, default_value_t = 200)]
+        flows: usize,
+        /// Account the sessions log in as; registered if it doesn't exist yet
+        #[arg(long, default_value = "benchmark-flow@example.com")]
+        email: String,
+        #[arg(long, default_value = "BenchFlow1")]
+        password: String,
+        #[command(flatten)]
+        thresholds: Thresholds,
+    },
+    /// Hold GraphQL subscriptions to `productUpdates` open while a product
+    /// is updated at a fixed rate; measures delivery latency and drops
+    Subscription {
+        /// Number of concurrent WebSocket subscriptions
+        #[arg(short, long, default_value_t = 50)]
+        subscribers: usize,
+        /// Total number of product updates
+        #[arg(short, long, default_value_t = 500)]
+        updates: usize,
+        /// Product updates sent per second
+        #[arg(long, default_value_t = 100.0)]
+        rate: f64,
+        #[command(flatten)]
+        thresholds: Thresholds,
+    },
+    /// Replay a traffic log: a JSONL file of `{ method, path, body,
+    /// delay_ms }` entries, sent in order
+    Replay {
+        /// The traffic log
+        file: PathBuf,
+        /// Number of replays running at once, interleaved
+        #[arg(short, long, default_value_t = 1)]
+        concurrency: usize,
+        /// Times each replay runs through the log
+        #[arg(short, long, default_value_t = 1)]
+        loops: usize,
+        #[command(flatten)]
+        thresholds: Thresholds,
+    },
+    /// Launch each server, load it, send SIGTERM mid-run and count the
+    /// in-flight requests its graceful shutdown drains or drops. Stop any
+    /// servers already running on these ports first
+    Drain {
+        /// Number of concurrent workers
+        #[arg(short, long, default_value_t = 50)]
+        concurrency: usize,
+        /// Endpoint the workers request
+        #[arg(long, default_value = "/api/products")]
+        path: String,
+        /// Load time before the signal, in milliseconds
+        #[arg(long, default_value_t = 2000)]
+        signal_after_ms: u64,
+        /// The Axum server binary, by default the one built next to this one
+        #[arg(long)]
+        axum_bin: Option<PathBuf>,
+        /// The ActixWeb server binary, by default the one built next to this one
+        #[arg(long)]
+        actix_bin: Option<PathBuf>,
+        /// Port the launched Axum server listens on
+        #[arg(long, default_value_t = 3100)]
+        axum_port: u16,
+        /// Port the launched ActixWeb server listens on
+        #[arg(long, default_value_t = 3101)]
+        actix_port: u16,
+    },
+    /// Check both servers answer the same requests with the same responses
+    Parity,
+    /// Compare two `--json-out` files and flag regressions of the current
+    /// run against the baseline; sends no requests
+    Compare {
+        /// Results of the earlier run
+        baseline: PathBuf,
+        /// Results of the run to check
+        current: PathBuf,
+        /// Largest tolerated drop in requests per second and rise in p99, in
+        /// percent
+        #[arg(long, default_value_t = 10.0)]
+        threshold: f64,
+        /// Largest tolerated drop in success rate, in percentage points
+        #[arg(long, default_value_t = 1.0)]
+        max_success_drop: f64,
     },
     /// Run all benchmarks
-    All,
+    All {
+        #[command(flatten)]
+        thresholds: Thresholds,
+    },
 }
 
 #[derive(Tabled)]
@@ -59,75 +399,220 @@ struct BenchmarkResult {
     total_time_ms: u128,
     avg_response_time_ms: f64,
     requests_per_second: f64,
+    p99_ms: f64,
+    success_rate: f64,
+    retried_successes: usize,
+}
+
+#[derive(Tabled)]
+struct OperationResult {
+    framework: String,
+    operation: String,
+    requests: usize,
     success_rate: f64,
+    p50_ms: f64,
+    p90_ms: f64,
+    p99_ms: f64,
+}
+
+impl OperationResult {
+    /// `sorted` holds the latencies the percentiles are taken from.
+    fn new(framework: &str, operation: &str, requests: usize, successes: usize, sorted: &[Duration]) -> Self {
+        Self {
+            framework: framework.to_string(),
+            operation: operation.to_string(),
+            requests,
+            success_rate: if requests == 0 { 0.0 } else { (successes as f64 / requests as f64) * 100.0 },
+            p50_ms: percentile_ms(sorted, 50.0),
+            p90_ms: percentile_ms(sorted, 90.0),
+            p99_ms: percentile_ms(sorted, 99.0),
+        }
+    }
+}
+
+const RETRY_BASE_DELAY: Duration = Duration::from_millis(10);
+
+/// A single HTTP request that workers send repeatedly.
+#[derive(Clone)]
+struct RequestSpec {
+    method: Method,
+    url: String,
+    body: Option<serde_json::Value>,
+    /// GraphQL answers 200 even when the query fails, so the response body
+    /// is inspected and any `errors` count the request as failed.
+    graphql: bool,
+}
+
+impl RequestSpec {
+    fn new(method: Method, url: &str, body: Option<serde_json::Value>) -> Self {
+        Self {
+            method,
+            url: url.to_string(),
+            body,
+            graphql: false,
+        }
+    }
+
+    fn graphql(url: &str, query: serde_json::Value) -> Self {
+        Self {
+            graphql: true,
+            ..Self::new(Method::POST, url, Some(query))
+        }
+    }
+
+    fn build(&self, client: &Client) -> RequestBuilder {
+        let request = client.request(self.method.clone(), &self.url);
+        match &self.body {
+            Some(json_body) => request.json(json_body),
+            None => request,
+        }
+    }
 }
 
 #[tokio::main]
 async fn main() -> Result<()> {
-    tracing_subscriber::registry()
-        .with(
-            tracing_subscriber::EnvFilter::try_from_default_env()
-                .unwrap_or_else(|_| "benchmarks=info".into()),
-        )
-        .with(tracing_subscriber::fmt::layer())
-        .init();
+    let registry = tracing_subscriber::registry().with(
+        tracing_subscriber::EnvFilter::try_from_default_env()
+            .unwrap_or_else(|_| "benchmarks=info".into()),
+    );
+    // Same LOG_FORMAT switch as the servers
+    match std::env::var("LOG_FORMAT").as_deref() {
+        Ok("json") => registry
+            .with(tracing_subscriber::fmt::layer().json().flatten_event(true))
+            .init(),
+        _ => registry.with(tracing_subscriber::fmt::layer()).init(),
+    }
 
     let cli = Cli::parse();
+    let _ = CONNECTION.set(Connection {
+        tls: cli.tls,
+        insecure: cli.insecure,
+        wait_db: cli.wait_db,
+    });
+    if cli.dry_run {
+        return plan::print_plan(&cli);
+    }
+    let options = RunOptions {
+        retries: cli.retries,
+        think_time: Duration::from_millis(cli.think_time_ms),
+        histograms: cli.histogram_out.as_ref().map(|_| HistogramRecorder::default()),
+        results: cli.json_out.as_ref().map(|_| ResultRecorder::default()),
+    };
 
-    match &cli.command {
-        Commands::Health { concurrency, requests } => {
-            run_health_benchmark(*concurrency, *requests).await?;
+    let violations = match &cli.command {
+        Commands::Health { concurrency, requests, thresholds } => {
+            run_health_benchmark(*concurrency, *requests, &options, thresholds).await?
+        }
+        Commands::Rest { concurrency, requests, thresholds } => {
+            run_rest_benchmark(*concurrency, *requests, &options, thresholds).await?
+        }
+        Commands::Graphql { concurrency, requests, query, thresholds } => {
+            run_graphql_benchmark(*concurrency, *requests, query, &options, thresholds).await?
+        }
+        Commands::Mixed { concurrency, requests, mix, seed, thresholds } => {
+            let mix = parse_mix(mix)?;
+            run_mixed_benchmark(*concurrency, *requests, &mix, *seed, &options, thresholds).await?
         }
-        Commands::Rest { concurrency, requests } => {
-            run_rest_benchmark(*concurrency, *requests).await?;
+        Commands::Flow { concurrency, flows, email, password, thresholds } => {
+            run_flow_benchmark(*concurrency, *flows, email, password, &options, thresholds).await?
         }
-        Commands::Graphql { concurrency, requests } => {
-            run_graphql_benchmark(*concurrency, *requests).await?;
+        Commands::Subscription { subscribers, updates, rate, thresholds } => {
+            subscription::run_subscription_benchmark(*subscribers, *updates, *rate, &options, thresholds).await?
         }
-        Commands::All => {
+        Commands::Replay { file, concurrency, loops, thresholds } => {
+            replay::run_replay_benchmark(file, *concurrency, *loops, &options, thresholds).await?
+        }
+        Commands::Drain { concurrency, path, signal_after_ms, axum_bin, actix_bin, axum_port, actix_port } => {
+            let targets = [
+                drain::DrainTarget {
+                    framework: "Axum",
+                    binary: axum_bin.clone().unwrap_or_else(|| drain::sibling_binary("axum-server")),
+                    port: *axum_port,
+                },
+                drain::DrainTarget {
+                    framework: "ActixWeb",
+                    binary: actix_bin.clone().unwrap_or_else(|| drain::sibling_binary("actixweb-server")),
+                    port: *actix_port,
+                },
+            ];
+            drain::run_drain_benchmark(&targets, *concurrency, path, Duration::from_millis(*signal_after_ms)).await?;
+            Vec::new()
+        }
+        Commands::Parity => {
+            parity::run_parity_check().await?;
+            Vec::new()
+        }
+        Commands::Compare { baseline, current, threshold, max_success_drop } => {
+            compare::run_compare(baseline, current, *threshold, *max_success_drop)?
+        }
+        Commands::All { thresholds } => {
             info!("Running all benchmarks...");
-            run_health_benchmark(100, 1000).await?;
-            run_rest_benchmark(50, 500).await?;
-            run_graphql_benchmark(30, 300).await?;
+            let mut violations = run_health_benchmark(100, 1000, &options, thresholds).await?;
+            violations.extend(run_rest_benchmark(50, 500, &options, thresholds).await?);
+            violations.extend(run_graphql_benchmark(30, 300, &GraphqlQuery::default(), &options, thresholds).await?);
+            violations
         }
+    };
+
+    if let (Some(path), Some(histograms)) = (&cli.histogram_out, &options.histograms) {
+        histograms.write_csv(path)?;
+        info!("Latency histogram written to {}", path.display());
+    }
+    if let (Some(path), Some(results)) = (&cli.json_out, &options.results) {
+        results.write_json(path)?;
+        info!("Results written to {}", path.display());
+    }
+
+    if !violations.is_empty() {
+        anyhow::bail!("performance thresholds violated:\n  {}", violations.join("\n  "));
     }
 
     Ok(())
 }
 
-async fn run_health_benchmark(concurrency: usize, total_requests: usize) -> Result<()> {
+/// Returns the thresholds either framework missed.
+async fn run_health_benchmark(
+    concurrency: usize,
+    total_requests: usize,
+    options: &RunOptions,
+    thresholds: &Thresholds,
+) -> Result<Vec<String>> {
     info!("Running health check benchmark...");
     
     // Wait for servers to be ready
     wait_for_servers().await?;
     
     let axum_result = benchmark_endpoint(
-        "http://localhost:3000/health",
-        "GET",
-        None,
+        RequestSpec::new(Method::GET, &format!("{}/health", axum_url()), None),
         concurrency,
         total_requests,
         "Axum",
         "Health Check",
+        options,
     ).await?;
 
     let actix_result = benchmark_endpoint(
-        "http://localhost:3001/health", 
-        "GET",
-        None,
+        RequestSpec::new(Method::GET, &format!("{}/health", actix_url()), None),
         concurrency,
         total_requests,
         "ActixWeb",
         "Health Check",
+        options,
     ).await?;
 
-    let table = Table::new(vec![axum_result, actix_result]);
-    println!("{}", table);
+    let results = vec![axum_result, actix_result];
+    println!("{}", Table::new(&results));
 
-    Ok(())
+    Ok(thresholds.check_endpoints(&results))
 }
 
-async fn run_rest_benchmark(concurrency: usize, total_requests: usize) -> Result<()> {
+/// Returns the thresholds either framework missed.
+async fn run_rest_benchmark(
+    concurrency: usize,
+    total_requests: usize,
+    options: &RunOptions,
+    thresholds: &Thresholds,
+) -> Result<Vec<String>> {
     info!("Running REST API benchmark...");
     
     wait_for_servers().await?;
@@ -141,123 +626,671 @@ async fn run_rest_benchmark(concurrency: usize, total_requests: usize) -> Result
     });
 
     let axum_result = benchmark_endpoint(
-        "http://localhost:3000/api/products",
-        "POST",
-        Some(product_data.clone()),
+        RequestSpec::new(Method::POST, &format!("{}/api/products", axum_url()), Some(product_data.clone())),
         concurrency,
         total_requests,
         "Axum",
         "Create Product",
+        options,
     ).await?;
 
     let actix_result = benchmark_endpoint(
-        "http://localhost:3001/api/products",
-        "POST", 
-        Some(product_data),
+        RequestSpec::new(Method::POST, &format!("{}/api/products", actix_url()), Some(product_data)),
         concurrency,
         total_requests,
         "ActixWeb",
         "Create Product",
+        options,
     ).await?;
 
-    let table = Table::new(vec![axum_result, actix_result]);
-    println!("{}", table);
+    let results = vec![axum_result, actix_result];
+    println!("{}", Table::new(&results));
 
-    Ok(())
+    Ok(thresholds.check_endpoints(&results))
 }
 
-async fn run_graphql_benchmark(concurrency: usize, total_requests: usize) -> Result<()> {
+/// Returns the thresholds either framework missed.
+async fn run_graphql_benchmark(
+    concurrency: usize,
+    total_requests: usize,
+    query: &GraphqlQuery,
+    options: &RunOptions,
+    thresholds: &Thresholds,
+) -> Result<Vec<String>> {
     info!("Running GraphQL benchmark...");
-    
+    let query_data = query.body()?;
+
     wait_for_servers().await?;
 
-    let query_data = json!({
-        "query": "{ products { id name price inventory } }"
-    });
+    check_graphql_endpoint(&axum_url(), &query_data).await?;
+    check_graphql_endpoint(&actix_url(), &query_data).await?;
 
     let axum_result = benchmark_endpoint(
-        "http://localhost:3000/graphql",
-        "POST",
-        Some(query_data.clone()),
+        RequestSpec::graphql(&format!("{}/graphql", axum_url()), query_data.clone()),
         concurrency,
         total_requests,
         "Axum",
         "GraphQL Query",
+        options,
     ).await?;
 
     let actix_result = benchmark_endpoint(
-        "http://localhost:3001/graphql",
-        "POST",
-        Some(query_data),
+        RequestSpec::graphql(&format!("{}/graphql", actix_url()), query_data),
         concurrency,
         total_requests,
         "ActixWeb", 
         "GraphQL Query",
+        options,
     ).await?;
 
-    let table = Table::new(vec![axum_result, actix_result]);
-    println!("{}", table);
+    let results = vec![axum_result, actix_result];
+    println!("{}", Table::new(&results));
+
+    Ok(thresholds.check_endpoints(&results))
+}
+
+/// Sends the benchmark query once and fails if it errors. GraphQL errors come
+/// back with HTTP 200, so the body's `errors` field is what gets checked.
+async fn check_graphql_endpoint(base_url: &str, query: &serde_json::Value) -> Result<()> {
+    let url = format!("{}/graphql", base_url);
+    let response = http_client().post(&url).json(query).send().await?;
+    let status = response.status();
+    let body: serde_json::Value = response
+        .json()
+        .await
+        .map_err(|e| anyhow::anyhow!("{} did not return a JSON GraphQL response ({}): {}", url, status, e))?;
+
+    let messages = graphql_error_messages(&body);
+    if !messages.is_empty() {
+        anyhow::bail!("benchmark query failed on {}: {}", url, messages.join("; "));
+    }
+    if !status.is_success() {
+        anyhow::bail!("benchmark query failed on {} with HTTP {}", url, status);
+    }
+
+    let products = body.pointer("/data/products").and_then(|products| products.as_array());
+    if products.is_none_or(|products| products.is_empty()) {
+        warn!("{} returned no products; run the seed binary so the benchmark resolves real data", url);
+    }
+
+    Ok(())
+}
+
+/// Messages of a GraphQL response's `errors` array, empty when it has none.
+fn graphql_error_messages(body: &serde_json::Value) -> Vec<&str> {
+    body.get("errors")
+        .and_then(|errors| errors.as_array())
+        .map(|errors| {
+            errors
+                .iter()
+                .map(|error| error.get("message").and_then(|message| message.as_str()).unwrap_or("unknown error"))
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+enum Operation {
+    Health,
+    ListProducts,
+    /// Reads the same product over and over, the hot path `PRODUCT_CACHE_TTL_MS` targets.
+    GetProduct,
+    CreateProduct,
+    GraphqlProducts,
+}
+
+impl Operation {
+    fn parse(name: &str) -> Result<Self> {
+        match name {
+            "health" => Ok(Operation::Health),
+            "list-products" => Ok(Operation::ListProducts),
+            "get-product" => Ok(Operation::GetProduct),
+            "create-product" => Ok(Operation::CreateProduct),
+            "graphql-products" => Ok(Operation::GraphqlProducts),
+            other => Err(anyhow::anyhow!("Unknown operation in mix: {}", other)),
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        match self {
+            Operation::Health => "GET health",
+            Operation::ListProducts => "GET products",
+            Operation::GetProduct => "GET product",
+            Operation::CreateProduct => "POST products",
+            Operation::GraphqlProducts => "GraphQL products",
+        }
+    }
+
+    /// `hot_product` is the id `GetProduct` reads; see `hot_product_id`.
+    fn spec(&self, base_url: &str, hot_product: &str) -> RequestSpec {
+        match self {
+            Operation::Health => RequestSpec::new(Method::GET, &format!("{}/health", base_url), None),
+            Operation::ListProducts => {
+                RequestSpec::new(Method::GET, &format!("{}/api/products", base_url), None)
+            }
+            Operation::GetProduct => {
+                RequestSpec::new(Method::GET, &format!("{}/api/products/{}", base_url, hot_product), None)
+            }
+            Operation::CreateProduct => RequestSpec::new(
+                Method::POST,
+                &format!("{}/api/products", base_url),
+                Some(json!({
+                    "name": "Test Product",
+                    "description": "A test product for benchmarking",
+                    "price": 1999,
+                    "inventory": 100
+                })),
+            ),
+            Operation::GraphqlProducts => RequestSpec::graphql(
+                &format!("{}/graphql", base_url),
+                json!({ "query": "{ products { id name price inventory } }" }),
+            ),
+        }
+    }
+}
+
+/// Parses `"list-products=70,health=20,create-product=10"` into weighted operations.
+fn parse_mix(mix: &str) -> Result<Vec<(Operation, u32)>> {
+    let weighted = mix
+        .split(',')
+        .map(|entry| {
+            let (name, weight) = entry
+                .trim()
+                .split_once('=')
+                .ok_or_else(|| anyhow::anyhow!("Mix entries must look like operation=weight, got: {}", entry))?;
+            let weight: u32 = weight
+                .trim()
+                .trim_end_matches('%')
+                .parse()
+                .map_err(|_| anyhow::anyhow!("Invalid weight for {}: {}", name, weight))?;
+            Ok((Operation::parse(name.trim())?, weight))
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    if weighted.iter().map(|(_, weight)| weight).sum::<u32>() == 0 {
+        return Err(anyhow::anyhow!("Mix weights must not all be zero"));
+    }
+
+    Ok(weighted)
+}
+
+fn pick_operation(mix: &[(Operation, u32)], rng: &mut StdRng) -> Operation {
+    let total: u32 = mix.iter().map(|(_, weight)| weight).sum();
+    let mut roll = rng.gen_range(0..total);
+
+    for (operation, weight) in mix {
+        if roll < *weight {
+            return *operation;
+        }
+        roll -= weight;
+    }
+
+    mix[mix.len() - 1].0
+}
+
+/// Nearest-rank percentile of already sorted samples, in milliseconds.
+fn percentile_ms(sorted: &[Duration], percentile: f64) -> f64 {
+    if sorted.is_empty() {
+        return 0.0;
+    }
+    let rank = ((percentile / 100.0) * sorted.len() as f64).ceil() as usize;
+    let ms = sorted[rank.clamp(1, sorted.len()) - 1].as_secs_f64() * 1000.0;
+    (ms * 100.0).round() / 100.0
+}
+
+/// Label of the whole mix, for its throughput.
+const MIXED_WORKLOAD: &str = "mixed workload";
+
+async fn run_mixed_benchmark(
+    concurrency: usize,
+    total_requests: usize,
+    mix: &[(Operation, u32)],
+    seed: u64,
+    options: &RunOptions,
+    thresholds: &Thresholds,
+) -> Result<Vec<String>> {
+    info!("Running mixed workload benchmark (seed {})...", seed);
+
+    wait_for_servers().await?;
+
+    let (mut rows, axum_rps) = benchmark_mix(&axum_url(), mix, seed, concurrency, total_requests, "Axum", options).await?;
+    let (actix_rows, actix_rps) =
+        benchmark_mix(&actix_url(), mix, seed, concurrency, total_requests, "ActixWeb", options).await?;
+    rows.extend(actix_rows);
+
+    println!("{}", Table::new(&rows));
+
+    // Throughput is only meaningful for the whole mix, latency per operation
+    let mut violations = thresholds.violations("Axum", MIXED_WORKLOAD, Some(axum_rps), None);
+    violations.extend(thresholds.violations("ActixWeb", MIXED_WORKLOAD, Some(actix_rps), None));
+    for row in &rows {
+        violations.extend(thresholds.violations(&row.framework, &row.operation, None, Some(row.p99_ms)));
+    }
+    Ok(violations)
+}
+
+async fn benchmark_mix(
+    base_url: &str,
+    mix: &[(Operation, u32)],
+    seed: u64,
+    concurrency: usize,
+    total_requests: usize,
+    framework: &str,
+    options: &RunOptions,
+) -> Result<(Vec<OperationResult>, f64)> {
+    check_load(concurrency, total_requests)?;
+    let client = http_client();
+    let mut tasks = Vec::new();
+    let hot_product = if mix.iter().any(|(operation, _)| *operation == Operation::GetProduct) {
+        hot_product_id(&client, base_url).await?
+    } else {
+        String::new()
+    };
+
+    let start_time = Instant::now();
+
+    for worker in 0..concurrency {
+        let requests_per_worker = worker_share(total_requests, concurrency, worker);
+        let client_clone = client.clone();
+        let mix_clone = mix.to_vec();
+        let base_url = base_url.to_string();
+        let hot_product = hot_product.clone();
+        let retries = options.retries;
+        let think_time = options.think_time;
+        // Each worker gets its own deterministic sequence of operations
+        let mut rng = StdRng::seed_from_u64(seed.wrapping_add(worker as u64));
+
+        let task = tokio::spawn(async move {
+            let mut samples: HashMap<Operation, (usize, Vec<Duration>)> = HashMap::new();
+
+            for index in 0..requests_per_worker {
+                if index > 0 {
+                    think(think_time).await;
+                }
+                let operation = pick_operation(&mix_clone, &mut rng);
+                let outcome = send_with_retries(&client_clone, &operation.spec(&base_url, &hot_product), retries).await;
+
+                let entry = samples.entry(operation).or_default();
+                if outcome.success {
+                    entry.0 += 1;
+                }
+                entry.1.push(outcome.elapsed);
+            }
+
+            samples
+        });
+
+        tasks.push(task);
+    }
+
+    let results = join_all(tasks).await;
+    let requests_per_second = total_requests as f64 / start_time.elapsed().as_secs_f64();
+
+    let mut merged: HashMap<Operation, (usize, Vec<Duration>)> = HashMap::new();
+    for result in results {
+        for (operation, (successes, durations)) in result? {
+            let entry = merged.entry(operation).or_default();
+            entry.0 += successes;
+            entry.1.extend(durations);
+        }
+    }
+
+    // Report operations in the order they were listed in the mix
+    let rows = mix
+        .iter()
+        .filter_map(|(operation, _)| {
+            let (successes, mut durations) = merged.remove(operation)?;
+            durations.sort();
+            if let Some(histograms) = &options.histograms {
+                histograms.record(framework, operation.name(), &durations);
+            }
+            Some(OperationResult::new(framework, operation.name(), durations.len(), successes, &durations))
+        })
+        .collect();
+    if let Some(results) = &options.results {
+        for row in &rows {
+            results.record(SavedResult::from(row));
+        }
+        results.record(SavedResult::throughput(framework, MIXED_WORKLOAD, total_requests, requests_per_second));
+    }
+
+    Ok((rows, requests_per_second))
+}
+
+/// Id of the oldest product, which every `get-product` request reads.
+async fn hot_product_id(client: &Client, base_url: &str) -> Result<String> {
+    let url = format!("{}/api/products?limit=1", base_url);
+    let products: serde_json::Value = client.get(&url).send().await?.error_for_status()?.json().await?;
+    products
+        .pointer("/items/0/id")
+        .and_then(|id| id.as_str())
+        .map(str::to_string)
+        .ok_or_else(|| anyhow::anyhow!("{} returned no products; run the seed binary before benchmarking get-product", url))
+}
+
+#[derive(Clone, Copy)]
+enum FlowStep {
+    Login,
+    CreateProduct,
+    GetProduct,
+}
+
+impl FlowStep {
+    const ALL: [FlowStep; 3] = [FlowStep::Login, FlowStep::CreateProduct, FlowStep::GetProduct];
+
+    fn name(&self) -> &'static str {
+        match self {
+            FlowStep::Login => "POST auth/login",
+            FlowStep::CreateProduct => "POST products",
+            FlowStep::GetProduct => "GET products/{id}",
+        }
+    }
+}
 
+const FULL_FLOW: &str = "full flow";
+
+/// Latencies collected by flow workers.
+#[derive(Default)]
+struct FlowSamples {
+    /// Successes and the latency of every attempt, indexed by `FlowStep`.
+    /// A step is only attempted after the previous one succeeded.
+    steps: [(usize, Vec<Duration>); 3],
+    attempted: usize,
+    /// Whole-chain latency of the flows where every step succeeded.
+    completed: Vec<Duration>,
+}
+
+impl FlowSamples {
+    /// Counts the step and passes `value` through, `None` meaning it failed.
+    fn record<T>(&mut self, step: FlowStep, elapsed: Duration, value: Option<T>) -> Option<T> {
+        let (successes, durations) = &mut self.steps[step as usize];
+        durations.push(elapsed);
+        if value.is_some() {
+            *successes += 1;
+        }
+        value
+    }
+
+    fn merge(&mut self, other: FlowSamples) {
+        for (merged, (successes, durations)) in self.steps.iter_mut().zip(other.steps) {
+            merged.0 += successes;
+            merged.1.extend(durations);
+        }
+        self.attempted += other.attempted;
+        self.completed.extend(other.completed);
+    }
+}
+
+/// Returns the thresholds either framework missed.
+async fn run_flow_benchmark(
+    concurrency: usize,
+    total_flows: usize,
+    email: &str,
+    password: &str,
+    options: &RunOptions,
+    thresholds: &Thresholds,
+) -> Result<Vec<String>> {
+    info!("Running login → create → read flow benchmark...");
+
+    wait_for_servers().await?;
+
+    // Both servers share the database, so one registration covers them
+    let credentials = json!({ "email": email, "password": password });
+    ensure_flow_user(&axum_url(), &credentials).await?;
+
+    let (mut rows, axum_fps) = benchmark_flow(&axum_url(), &credentials, concurrency, total_flows, "Axum", options).await?;
+    let (actix_rows, actix_fps) =
+        benchmark_flow(&actix_url(), &credentials, concurrency, total_flows, "ActixWeb", options).await?;
+    rows.extend(actix_rows);
+
+    println!("{}", Table::new(&rows));
+    info!("Axum: {:.2} flows/s, ActixWeb: {:.2} flows/s", axum_fps, actix_fps);
+
+    // --min-rps applies to completed chains per second, --max-p99-ms to
+    // every step and the whole chain
+    let mut violations = thresholds.violations("Axum", FULL_FLOW, Some(axum_fps), None);
+    violations.extend(thresholds.violations("ActixWeb", FULL_FLOW, Some(actix_fps), None));
+    for row in &rows {
+        violations.extend(thresholds.violations(&row.framework, &row.operation, None, Some(row.p99_ms)));
+    }
+    Ok(violations)
+}
+
+/// Registers the flow account unless it can already log in.
+async fn ensure_flow_user(base_url: &str, credentials: &serde_json::Value) -> Result<()> {
+    let client = http_client();
+    let login_url = format!("{}/api/auth/login", base_url);
+    if client.post(&login_url).json(credentials).send().await?.status().is_success() {
+        return Ok(());
+    }
+
+    let email = credentials["email"].as_str().unwrap_or_default();
+    let registration = json!({
+        "username": email.split('@').next().unwrap_or(email),
+        "email": email,
+        "password": credentials["password"],
+    });
+    let response = client
+        .post(format!("{}/api/auth/register", base_url))
+        .json(&registration)
+        .send()
+        .await?;
+    if !response.status().is_success() {
+        let status = response.status();
+        anyhow::bail!("could not register flow user {}: {} {}", email, status, response.text().await?);
+    }
+    info!("Registered flow user {}", email);
     Ok(())
 }
 
+async fn benchmark_flow(
+    base_url: &str,
+    credentials: &serde_json::Value,
+    concurrency: usize,
+    total_flows: usize,
+    framework: &str,
+    options: &RunOptions,
+) -> Result<(Vec<OperationResult>, f64)> {
+    check_load(concurrency, total_flows)?;
+    let client = http_client();
+    let mut tasks = Vec::new();
+
+    let start_time = Instant::now();
+
+    for worker in 0..concurrency {
+        let flows_per_worker = worker_share(total_flows, concurrency, worker);
+        let client_clone = client.clone();
+        let base_url = base_url.to_string();
+        let credentials = credentials.clone();
+        let think_time = options.think_time;
+
+        let task = tokio::spawn(async move {
+            let mut samples = FlowSamples::default();
+            for index in 0..flows_per_worker {
+                if index > 0 {
+                    think(think_time).await;
+                }
+                run_flow(&client_clone, &base_url, &credentials, &mut samples).await;
+            }
+            samples
+        });
+
+        tasks.push(task);
+    }
+
+    let results = join_all(tasks).await;
+    let elapsed = start_time.elapsed();
+
+    let mut merged = FlowSamples::default();
+    for result in results {
+        merged.merge(result?);
+    }
+    let flows_per_second = merged.completed.len() as f64 / elapsed.as_secs_f64();
+
+    let mut rows = Vec::new();
+    for (step, (successes, mut durations)) in FlowStep::ALL.into_iter().zip(merged.steps) {
+        durations.sort();
+        if let Some(histograms) = &options.histograms {
+            histograms.record(framework, step.name(), &durations);
+        }
+        rows.push(OperationResult::new(framework, step.name(), durations.len(), successes, &durations));
+    }
+    let mut completed = merged.completed;
+    completed.sort();
+    if let Some(histograms) = &options.histograms {
+        histograms.record(framework, FULL_FLOW, &completed);
+    }
+    rows.push(OperationResult::new(framework, FULL_FLOW, merged.attempted, completed.len(), &completed));
+    if let Some(results) = &options.results {
+        for row in &rows {
+            let mut saved = SavedResult::from(row);
+            if row.operation == FULL_FLOW {
+                saved.requests_per_second = Some(flows_per_second);
+            }
+            results.record(saved);
+        }
+    }
+
+    Ok((rows, flows_per_second))
+}
+
+/// One session: log in, create a product with the bearer token, then read
+/// it back. Stops at the first failed step. Requests are not retried, since
+/// retrying a create could leave duplicate products.
+async fn run_flow(client: &Client, base_url: &str, credentials: &serde_json::Value, samples: &mut FlowSamples) {
+    samples.attempted += 1;
+    let started = Instant::now();
+
+    let login = client.post(format!("{}/api/auth/login", base_url)).json(credentials);
+    let (elapsed, body) = send_flow_step(login).await;
+    let token = body.and_then(|body| body.get("token")?.as_str().map(str::to_string));
+    let Some(token) = samples.record(FlowStep::Login, elapsed, token) else {
+        return;
+    };
+
+    let create = client
+        .post(format!("{}/api/products", base_url))
+        .bearer_auth(&token)
+        .json(&json!({
+            "name": "Flow Product",
+            "description": "Created by the flow benchmark",
+            "price": 1999,
+            "inventory": 100
+        }));
+    let (elapsed, body) = send_flow_step(create).await;
+    let id = body.and_then(|body| body.get("id")?.as_str().map(str::to_string));
+    let Some(id) = samples.record(FlowStep::CreateProduct, elapsed, id) else {
+        return;
+    };
+
+    let read = client.get(format!("{}/api/products/{}", base_url, id)).bearer_auth(&token);
+    let (elapsed, body) = send_flow_step(read).await;
+    if samples.record(FlowStep::GetProduct, elapsed, body).is_some() {
+        samples.completed.push(started.elapsed());
+    }
+}
+
+/// Latency up to the response headers, and the JSON body of a successful
+/// response.
+async fn send_flow_step(request: RequestBuilder) -> (Duration, Option<serde_json::Value>) {
+    let started = Instant::now();
+    let response = request.send().await;
+    let elapsed = started.elapsed();
+    let body = match response {
+        Ok(response) if response.status().is_success() => response.json().await.ok(),
+        _ => None,
+    };
+    (elapsed, body)
+}
+
+/// Rejects loads that would send nothing, which would otherwise report `NaN`
+/// rates.
+fn check_load(concurrency: usize, total_requests: usize) -> Result<()> {
+    if concurrency == 0 {
+        anyhow::bail!("concurrency must be at least 1");
+    }
+    if total_requests == 0 {
+        anyhow::bail!("the number of requests must be at least 1");
+    }
+    Ok(())
+}
+
+/// Requests `worker` sends out of `total` split across `workers`. The first
+/// `total % workers` workers send one extra, so the shares add up to `total`
+/// and workers beyond `total` send none.
+fn worker_share(total: usize, workers: usize, worker: usize) -> usize {
+    total / workers + usize::from(worker < total % workers)
+}
+
 async fn benchmark_endpoint(
-    url: &str,
-    method: &str,
-    body: Option<serde_json::Value>,
+    spec: RequestSpec,
+    concurrency: usize,
+    total_requests: usize,
+    framework: &str,
+    endpoint_name: &str,
+    options: &RunOptions,
+) -> Result<BenchmarkResult> {
+    let script = Arc::new(vec![ScriptStep { spec, delay: Duration::ZERO }]);
+    benchmark_script(script, concurrency, total_requests, framework, endpoint_name, options).await
+}
+
+/// One request of a script, sent after pausing for `delay`.
+#[derive(Clone)]
+struct ScriptStep {
+    spec: RequestSpec,
+    delay: Duration,
+}
+
+/// Like `benchmark_endpoint`, but each worker walks `script` from the start,
+/// wrapping around, until it has sent its share of `total_requests`. Pauses
+/// count towards the total time but not towards any request's latency.
+async fn benchmark_script(
+    script: Arc<Vec<ScriptStep>>,
     concurrency: usize,
     total_requests: usize,
     framework: &str,
     endpoint_name: &str,
+    options: &RunOptions,
 ) -> Result<BenchmarkResult> {
-    let client = Client::new();
-    let requests_per_worker = total_requests / concurrency;
+    check_load(concurrency, total_requests)?;
+    if script.is_empty() {
+        anyhow::bail!("there are no requests to send");
+    }
+    let client = http_client();
     let mut tasks = Vec::new();
 
     let start_time = Instant::now();
 
-    for _ in 0..concurrency {
+    for worker in 0..concurrency {
+        let requests_per_worker = worker_share(total_requests, concurrency, worker);
         let client_clone = client.clone();
-        let url_clone = url.to_string();
-        let method_clone = method.to_string();
-        let body_clone = body.clone();
+        let script = script.clone();
+        let retries = options.retries;
+        let think_time = options.think_time;
 
         let task = tokio::spawn(async move {
             let mut successes = 0;
-            let mut total_response_time = Duration::default();
-
-            for _ in 0..requests_per_worker {
-                let req_start = Instant::now();
-                
-                let request = match method_clone.as_str() {
-                    "GET" => client_clone.get(&url_clone),
-                    "POST" => {
-                        let mut req = client_clone.post(&url_clone);
-                        if let Some(json_body) = &body_clone {
-                            req = req.json(json_body);
-                        }
-                        req
-                    }
-                    _ => client_clone.get(&url_clone),
-                };
+            let mut retried_successes = 0;
+            let mut durations = Vec::with_capacity(requests_per_worker);
 
-                match request.send().await {
-                    Ok(response) if response.status().is_success() => {
-                        successes += 1;
-                        total_response_time += req_start.elapsed();
-                    }
-                    Ok(_) => {
-                        // Non-success status code
-                        total_response_time += req_start.elapsed();
-                    }
-                    Err(_) => {
-                        // Request failed
-                        total_response_time += req_start.elapsed();
+            for (index, step) in script.iter().cycle().take(requests_per_worker).enumerate() {
+                if index > 0 {
+                    think(think_time).await;
+                }
+                if !step.delay.is_zero() {
+                    sleep(step.delay).await;
+                }
+                let outcome = send_with_retries(&client_clone, &step.spec, retries).await;
+                if outcome.success {
+                    successes += 1;
+                    if outcome.retried {
+                        retried_successes += 1;
                     }
                 }
+                durations.push(outcome.elapsed);
             }
 
-            (successes, total_response_time)
+            (successes, retried_successes, durations)
         });
 
         tasks.push(task);
@@ -267,19 +1300,26 @@ async fn benchmark_endpoint(
     let total_time = start_time.elapsed();
 
     let mut total_successes = 0;
-    let mut total_response_time = Duration::default();
+    let mut total_retried_successes = 0;
+    let mut durations = Vec::with_capacity(total_requests);
 
     for result in results {
-        let (successes, response_time) = result?;
+        let (successes, retried_successes, worker_durations) = result?;
         total_successes += successes;
-        total_response_time += response_time;
+        total_retried_successes += retried_successes;
+        durations.extend(worker_durations);
+    }
+    durations.sort();
+    let total_response_time: Duration = durations.iter().sum();
+    if let Some(histograms) = &options.histograms {
+        histograms.record(framework, endpoint_name, &durations);
     }
 
     let success_rate = (total_successes as f64 / total_requests as f64) * 100.0;
     let avg_response_time_ms = total_response_time.as_millis() as f64 / total_requests as f64;
     let requests_per_second = total_requests as f64 / total_time.as_secs_f64();
 
-    Ok(BenchmarkResult {
+    let result = BenchmarkResult {
         framework: framework.to_string(),
         endpoint: endpoint_name.to_string(),
         total_requests,
@@ -287,30 +1327,92 @@ async fn benchmark_endpoint(
         total_time_ms: total_time.as_millis(),
         avg_response_time_ms,
         requests_per_second,
+        p99_ms: percentile_ms(&durations, 99.0),
         success_rate,
-    })
+        retried_successes: total_retried_successes,
+    };
+    if let Some(results) = &options.results {
+        results.record(SavedResult::from(&result));
+    }
+    Ok(result)
+}
+
+/// `--think-time-ms` between two requests of a worker. Only between them,
+/// so a worker neither starts nor ends on a pause.
+async fn think(think_time: Duration) {
+    if !think_time.is_zero() {
+        sleep(think_time).await;
+    }
+}
+
+/// Result of one logical request after any retries.
+struct RequestOutcome {
+    success: bool,
+    retried: bool,
+    /// Latency of the final attempt only, so retries never inflate response times
+    elapsed: Duration,
+}
+
+async fn send_with_retries(client: &Client, spec: &RequestSpec, retries: u32) -> RequestOutcome {
+    let mut attempt = 0;
+
+    loop {
+        let req_start = Instant::now();
+        let result = spec.build(client).send().await;
+        let elapsed = req_start.elapsed();
+
+        let retryable = match result {
+            Ok(response) if response.status().is_success() => {
+                // A query that errored would fail again, so it is never retried
+                let success = !spec.graphql || graphql_succeeded(response).await;
+                return RequestOutcome { success, retried: attempt > 0, elapsed };
+            }
+            // Server errors and connection failures may be transient
+            Ok(response) => response.status().is_server_error(),
+            Err(_) => true,
+        };
+
+        if !retryable || attempt >= retries {
+            return RequestOutcome { success: false, retried: attempt > 0, elapsed };
+        }
+
+        sleep(RETRY_BASE_DELAY * 2u32.pow(attempt)).await;
+        attempt += 1;
+    }
+}
+
+async fn graphql_succeeded(response: reqwest::Response) -> bool {
+    match response.json::<serde_json::Value>().await {
+        Ok(body) => graphql_error_messages(&body).is_empty(),
+        Err(_) => false,
+    }
 }
 
 async fn wait_for_servers() -> Result<()> {
-    let client = Client::new();
+    let client = http_client();
     let max_retries = 30;
     let retry_delay = Duration::from_secs(1);
 
     for i in 0..max_retries {
-        let axum_ready = client.get("http://localhost:3000/health").send().await.is_ok();
-        let actix_ready = client.get("http://localhost:3001/health").send().await.is_ok();
+        let axum_ready = client.get(format!("{}/health", axum_url())).send().await.is_ok();
+        let actix_ready = client.get(format!("{}/health", actix_url())).send().await.is_ok();
 
         if axum_ready && actix_ready {
             info!("Both servers are ready!");
+            if connection().wait_db {
+                for base_url in [axum_url(), actix_url()] {
+                    warm_database(&client, &base_url).await?;
+                }
+            }
             return Ok(());
         }
 
         if i == max_retries - 1 {
             if !axum_ready {
-                warn!("Axum server not responding at http://localhost:3000");
+                warn!("Axum server not responding at {}", axum_url());
             }
             if !actix_ready {
-                warn!("ActixWeb server not responding at http://localhost:3001");
+                warn!("ActixWeb server not responding at {}", actix_url());
             }
             return Err(anyhow::anyhow!("Servers not ready after {} retries", max_retries));
         }
@@ -320,4 +1422,51 @@ async fn wait_for_servers() -> Result<()> {
     }
 
     Ok(())
+}
+
+/// Concurrent readiness checks sent once the database is up, so the pool
+/// holds several live connections before the timed run starts.
+const DB_WARMUP_REQUESTS: usize = 10;
+
+/// Polls `/health/ready` until the server reaches its database, then sends
+/// a burst of checks to open pool connections. Under `--wait-db` only.
+async fn warm_database(client: &Client, base_url: &str) -> Result<()> {
+    let url = format!("{}/health/ready", base_url);
+    let max_retries = 30;
+
+    for i in 0..max_retries {
+        let ready = client.get(&url).send().await;
+        if ready.is_ok_and(|response| response.status().is_success()) {
+            join_all((0..DB_WARMUP_REQUESTS).map(|_| client.get(&url).send())).await;
+            info!("Database ready behind {}", base_url);
+            return Ok(());
+        }
+        info!("Waiting for the database behind {}... (attempt {}/{})", base_url, i + 1, max_retries);
+        sleep(Duration::from_secs(1)).await;
+    }
+
+    Err(anyhow::anyhow!("Database behind {} not ready after {} retries", base_url, max_retries))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn worker_shares_add_up_to_the_total() {
+        for (total, workers) in [(1000, 3), (10, 4), (7, 7), (3, 8), (1, 2), (999, 1), (1001, 10)] {
+            let shares: Vec<usize> = (0..workers).map(|worker| worker_share(total, workers, worker)).collect();
+            assert_eq!(shares.iter().sum::<usize>(), total, "{} across {}", total, workers);
+            let (min, max) = (shares.iter().min().unwrap(), shares.iter().max().unwrap());
+            assert!(max - min <= 1, "{} across {}: {:?}", total, workers, shares);
+        }
+    }
+
+    #[test]
+    fn the_remainder_goes_to_the_first_workers() {
+        let shares: Vec<usize> = (0..3).map(|worker| worker_share(1000, 3, worker)).collect();
+        assert_eq!(shares, [334, 333, 333]);
+        let shares: Vec<usize> = (0..5).map(|worker| worker_share(3, 5, worker)).collect();
+        assert_eq!(shares, [1, 1, 1, 0, 0]);
+    }
 }
\ No newline at end of file
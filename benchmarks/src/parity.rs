@@ -0,0 +1,239 @@
+use anyhow::{anyhow, Result};
+use reqwest::{Client, Method};
+use serde_json::{json, Value};
+use tabled::{Table, Tabled};
+use tracing::{info, warn};
+use ulid::Ulid;
+use uuid::Uuid;
+
+use crate::{actix_url, axum_url, http_client, wait_for_servers};
+
+/// Placeholder in case paths and bodies, replaced with a value unique to each
+/// server so stateful cases (register, then login) don't collide.
+const UNIQUE: &str = "{unique}";
+
+/// Fields whose values differ by design: issued JWTs and the health check's
+/// framework name.
+const MASKED_KEYS: &[&str] = &["token", "framework"];
+
+/// One request sent unchanged to both servers.
+struct ParityCase {
+    name: &'static str,
+    method: Method,
+    path: &'static str,
+    body: Option<Value>,
+}
+
+impl ParityCase {
+    fn new(name: &'static str, method: Method, path: &'static str, body: Option<Value>) -> Self {
+        Self { name, method, path, body }
+    }
+}
+
+#[derive(Tabled)]
+struct ParityResult {
+    case: String,
+    axum_status: u16,
+    actix_status: u16,
+    result: String,
+}
+
+/// Cases run in order against each server; later ones may rely on state
+/// created by earlier ones (the login uses the registered account).
+fn parity_cases() -> Vec<ParityCase> {
+    let password = "Parity0Password";
+    vec![
+        ParityCase::new("health", Method::GET, "/health", None),
+        ParityCase::new(
+            "register: invalid email",
+            Method::POST,
+            "/api/auth/register",
+            Some(json!({"username": "parity", "email": "notanemail", "password": password})),
+        ),
+        ParityCase::new(
+            "register: weak password",
+            Method::POST,
+            "/api/auth/register",
+            Some(json!({"username": "parity", "email": "parity-{unique}@example.com", "password": "short"})),
+        ),
+        ParityCase::new(
+            "register",
+            Method::POST,
+            "/api/auth/register",
+            Some(json!({"username": "parity-{unique}", "email": "parity-{unique}@example.com", "password": password})),
+        ),
+        ParityCase::new(
+            "login: wrong password",
+            Method::POST,
+            "/api/auth/login",
+            Some(json!({"email": "parity-{unique}@example.com", "password": "Wrong0Password"})),
+        ),
+        ParityCase::new(
+            "login",
+            Method::POST,
+            "/api/auth/login",
+            Some(json!({"email": "parity-{unique}@example.com", "password": password})),
+        ),
+        ParityCase::new("logout: no token", Method::POST, "/api/auth/logout", None),
+        ParityCase::new(
+            "create product",
+            Method::POST,
+            "/api/products",
+            Some(json!({"name": "Parity {unique}", "description": "parity check", "price": 1999, "inventory": 3})),
+        ),
+        ParityCase::new("get product: invalid id", Method::GET, "/api/products/not-a-uuid", None),
+        ParityCase::new(
+            "get product: missing",
+            Method::GET,
+            "/api/products/00000000-0000-0000-0000-000000000000",
+            None,
+        ),
+        ParityCase::new("unknown route", Method::GET, "/api/nope", None),
+        ParityCase::new(
+            "wrong method",
+            Method::POST,
+            "/api/products/00000000-0000-0000-0000-000000000000",
+            None,
+        ),
+        ParityCase::new(
+            "graphql: strict missing product",
+            Method::POST,
+            "/graphql",
+            Some(json!({"query": "{ product(id: \"00000000-0000-0000-0000-000000000000\", strict: true) { id } }"})),
+        ),
+        ParityCase::new(
+            "graphql: users without token",
+            Method::POST,
+            "/graphql",
+            Some(json!({"query": "{ users(first: 1) { edges { node { id } } } }"})),
+        ),
+        ParityCase::new(
+            "user without token",
+            Method::GET,
+            "/api/users/00000000-0000-0000-0000-000000000000",
+            None,
+        ),
+    ]
+}
+
+/// Each distinct request the check sends to a server, e.g. `POST /graphql`,
+/// and how many cases send it.
+pub fn planned_requests() -> Vec<(String, usize)> {
+    let mut requests: Vec<(String, usize)> = Vec::new();
+    for case in parity_cases() {
+        let request = format!("{} {}", case.method, case.path);
+        match requests.iter_mut().find(|(seen, _)| *seen == request) {
+            Some((_, count)) => *count += 1,
+            None => requests.push((request, 1)),
+        }
+    }
+    requests
+}
+
+/// Sends every case to both servers and compares status codes and JSON
+/// bodies, ignoring generated ids, timestamps and tokens. Fails if any case
+/// diverges, printing a line diff of the normalized bodies.
+pub async fn run_parity_check() -> Result<()> {
+    info!("Checking REST and GraphQL parity between the servers...");
+    wait_for_servers().await?;
+
+    let client = http_client();
+    let run_id = Uuid::new_v4().simple().to_string();
+    let axum_unique = format!("axum-{}", &run_id[..8]);
+    let actix_unique = format!("actix-{}", &run_id[..8]);
+
+    let mut results = Vec::new();
+    let mut diverged = 0;
+
+    for case in parity_cases() {
+        let (axum_status, axum_body) = send_case(&client, &axum_url(), &case, &axum_unique).await?;
+        let (actix_status, actix_body) = send_case(&client, &actix_url(), &case, &actix_unique).await?;
+
+        let matches = axum_status == actix_status && axum_body == actix_body;
+        if !matches {
+            diverged += 1;
+            warn!("{} diverged:\n{}", case.name, body_diff(&axum_body, &actix_body));
+        }
+
+        results.push(ParityResult {
+            case: case.name.to_string(),
+            axum_status,
+            actix_status,
+            result: if matches { "match" } else { "DIFF" }.to_string(),
+        });
+    }
+
+    let total = results.len();
+    println!("\n🔍 Parity Results:");
+    println!("{}", Table::new(results));
+
+    if diverged > 0 {
+        return Err(anyhow!("{} of {} parity cases diverged", diverged, total));
+    }
+    Ok(())
+}
+
+async fn send_case(client: &Client, base: &str, case: &ParityCase, unique: &str) -> Result<(u16, Value)> {
+    let url = format!("{}{}", base, case.path.replace(UNIQUE, unique));
+    let mut request = client.request(case.method.clone(), url);
+    if let Some(body) = &case.body {
+        let body = serde_json::to_string(body)?.replace(UNIQUE, unique);
+        request = request.header("content-type", "application/json").body(body);
+    }
+
+    let response = request.send().await?;
+    let status = response.status().as_u16();
+    let text = response.text().await?;
+    let body = serde_json::from_str(&text).unwrap_or(Value::String(text));
+    Ok((status, normalize(body, unique)))
+}
+
+/// Replaces values that legitimately differ between runs and servers.
+fn normalize(value: Value, unique: &str) -> Value {
+    match value {
+        Value::Object(map) => Value::Object(
+            map.into_iter()
+                .map(|(key, value)| {
+                    let value = if MASKED_KEYS.contains(&key.as_str()) {
+                        Value::String(format!("<{}>", key))
+                    } else {
+                        normalize(value, unique)
+                    };
+                    (key, value)
+                })
+                .collect(),
+        ),
+        Value::Array(items) => Value::Array(items.into_iter().map(|item| normalize(item, unique)).collect()),
+        Value::String(s) if Uuid::parse_str(&s).is_ok() || Ulid::from_string(&s).is_ok() => {
+            Value::String("<id>".to_string())
+        }
+        Value::String(s) if chrono::DateTime::parse_from_rfc3339(&s).is_ok() => {
+            Value::String("<timestamp>".to_string())
+        }
+        Value::String(s) => Value::String(s.replace(unique, UNIQUE)),
+        other => other,
+    }
+}
+
+fn body_diff(axum: &Value, actix: &Value) -> String {
+    let axum = serde_json::to_string_pretty(axum).unwrap_or_default();
+    let actix = serde_json::to_string_pretty(actix).unwrap_or_default();
+    let axum_lines: Vec<&str> = axum.lines().collect();
+    let actix_lines: Vec<&str> = actix.lines().collect();
+
+    let mut diff = Vec::new();
+    for i in 0..axum_lines.len().max(actix_lines.len()) {
+        match (axum_lines.get(i), actix_lines.get(i)) {
+            (Some(a), Some(b)) if a == b => diff.push(format!("  {}", a)),
+            (a, b) => {
+                if let Some(a) = a {
+                    diff.push(format!("- axum  {}", a));
+                }
+                if let Some(b) = b {
+                    diff.push(format!("+ actix {}", b));
+                }
+            }
+        }
+    }
+    diff.join("\n")
+}
\ No newline at end of file
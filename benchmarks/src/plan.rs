@@ -0,0 +1,257 @@
+use anyhow::{bail, Result};
+use reqwest::Method;
+use std::time::Duration;
+use tabled::{Table, Tabled};
+
+use crate::{
+    actix_url, axum_url, check_load, connection, drain, parity, parse_mix, replay, server_url, worker_share, Cli,
+    Commands, GraphqlQuery,
+};
+
+#[derive(Tabled)]
+struct PlanRow {
+    framework: String,
+    target: String,
+    request: String,
+    requests: String,
+    concurrency: usize,
+}
+
+/// One run against one server, as `--dry-run` describes it.
+struct PlannedRun {
+    framework: &'static str,
+    target: String,
+    /// What is sent and how often, e.g. `GET /health` and `1000`.
+    requests: Vec<(String, String)>,
+    /// `None` when the run ends on something other than a count.
+    total_requests: Option<usize>,
+    concurrency: usize,
+    /// Time the run takes however fast the server answers, e.g. at a fixed rate.
+    min_duration: Duration,
+}
+
+impl PlannedRun {
+    fn new(framework: &'static str, target: String, total_requests: usize, concurrency: usize) -> Self {
+        Self {
+            framework,
+            target,
+            requests: Vec::new(),
+            total_requests: Some(total_requests),
+            concurrency,
+            min_duration: Duration::ZERO,
+        }
+    }
+
+    fn request(mut self, method: Method, path: &str, count: impl ToString) -> Self {
+        self.requests.push((format!("{} {}", method, path), count.to_string()));
+        self
+    }
+
+    /// The busiest worker's pauses between requests, which it sits out
+    /// however fast the server is.
+    fn think_time(&self, think_time: Duration) -> Duration {
+        match self.total_requests {
+            Some(total) if self.concurrency > 0 => {
+                think_time * worker_share(total, self.concurrency, 0).saturating_sub(1) as u32
+            }
+            _ => Duration::ZERO,
+        }
+    }
+
+    /// How long the run should take at `target_rps`; without one, only runs
+    /// whose length is fixed can be estimated.
+    fn estimate(&self, think_time: Duration, target_rps: Option<f64>) -> Option<Duration> {
+        let lower_bound = self.min_duration.max(self.think_time(think_time));
+        match (self.total_requests, target_rps) {
+            (Some(total), Some(rps)) => Some(Duration::from_secs_f64(total as f64 / rps).max(lower_bound)),
+            (None, _) => Some(lower_bound),
+            (Some(_), None) => None,
+        }
+    }
+}
+
+/// Both servers getting the same run, one after the other.
+fn both_servers(run: impl Fn(&'static str, String) -> PlannedRun) -> Vec<PlannedRun> {
+    vec![run("Axum", axum_url()), run("ActixWeb", actix_url())]
+}
+
+fn endpoint_runs(method: Method, path: &str, concurrency: usize, requests: usize) -> Result<Vec<PlannedRun>> {
+    check_load(concurrency, requests)?;
+    Ok(both_servers(|framework, target| {
+        PlannedRun::new(framework, target, requests, concurrency).request(method.clone(), path, requests)
+    }))
+}
+
+fn graphql_runs(query: &GraphqlQuery, concurrency: usize, requests: usize) -> Result<Vec<PlannedRun>> {
+    // Reads --query-file and parses --variables, so mistakes show up here
+    query.body()?;
+    endpoint_runs(Method::POST, "/graphql", concurrency, requests)
+}
+
+fn planned_runs(command: &Commands) -> Result<Vec<PlannedRun>> {
+    let runs = match command {
+        Commands::Health { concurrency, requests, .. } => endpoint_runs(Method::GET, "/health", *concurrency, *requests)?,
+        Commands::Rest { concurrency, requests, .. } => {
+            endpoint_runs(Method::POST, "/api/products", *concurrency, *requests)?
+        }
+        Commands::Graphql { concurrency, requests, query, .. } => graphql_runs(query, *concurrency, *requests)?,
+        Commands::Mixed { concurrency, requests, mix, .. } => {
+            check_load(*concurrency, *requests)?;
+            let mix = parse_mix(mix)?;
+            let total_weight: u32 = mix.iter().map(|(_, weight)| weight).sum();
+            both_servers(|framework, target| {
+                let mut run = PlannedRun::new(framework, target.clone(), *requests, *concurrency);
+                for (operation, weight) in &mix {
+                    let spec = operation.spec(&target, "{id}");
+                    let path = spec.url.trim_start_matches(&target).to_string();
+                    let share = (*requests as f64 * f64::from(*weight) / f64::from(total_weight)).round();
+                    run = run.request(spec.method, &path, format!("~{}", share));
+                }
+                run
+            })
+        }
+        Commands::Flow { concurrency, flows, .. } => {
+            check_load(*concurrency, *flows)?;
+            both_servers(|framework, target| {
+                PlannedRun::new(framework, target, *flows, *concurrency)
+                    .request(Method::POST, "/api/auth/login", flows)
+                    .request(Method::POST, "/api/products", flows)
+                    .request(Method::GET, "/api/products/{id}", flows)
+            })
+        }
+        Commands::Subscription { subscribers, updates, rate, .. } => {
+            if !(rate.is_finite() && *rate > 0.0) {
+                bail!("rate must be a positive number of updates per second");
+            }
+            both_servers(|framework, target| {
+                let mut run = PlannedRun::new(framework, target, *updates, *subscribers)
+                    .request(Method::PATCH, "/api/products/{id}", updates);
+                run.requests.insert(0, ("WS /graphql/ws productUpdates".to_string(), subscribers.to_string()));
+                // Updates go out at a fixed rate, not as fast as possible
+                run.total_requests = None;
+                run.min_duration = Duration::from_secs_f64(*updates as f64 / rate);
+                run
+            })
+        }
+        Commands::Replay { file, concurrency, loops, .. } => {
+            let plan = replay::plan(file, *concurrency, *loops)?;
+            check_load(*concurrency, plan.total_requests)?;
+            both_servers(|framework, target| {
+                let mut run = PlannedRun::new(framework, target, plan.total_requests, *concurrency);
+                run.requests.extend(plan.requests.iter().map(|(request, count)| (request.clone(), count.to_string())));
+                run.min_duration = plan.delays;
+                run
+            })
+        }
+        Commands::Drain { concurrency, path, signal_after_ms, axum_bin, actix_bin, axum_port, actix_port } => {
+            if *concurrency == 0 {
+                bail!("concurrency must be at least 1");
+            }
+            let launched = [
+                ("Axum", axum_bin, "axum-server", *axum_port),
+                ("ActixWeb", actix_bin, "actixweb-server", *actix_port),
+            ];
+            launched
+                .into_iter()
+                .map(|(framework, binary, name, port)| {
+                    let binary = binary.clone().unwrap_or_else(|| drain::sibling_binary(name));
+                    let missing = if binary.exists() { "" } else { ", missing" };
+                    let target = format!("{} (launches {}{})", server_url(port), binary.display(), missing);
+                    let mut run = PlannedRun::new(framework, target, 0, *concurrency).request(
+                        Method::GET,
+                        path,
+                        format!("until exit, SIGTERM after {} ms", signal_after_ms),
+                    );
+                    run.total_requests = None;
+                    run.min_duration = Duration::from_millis(*signal_after_ms);
+                    run
+                })
+                .collect()
+        }
+        Commands::Parity => {
+            let requests = parity::planned_requests();
+            let total = requests.iter().map(|(_, count)| count).sum();
+            both_servers(|framework, target| {
+                let mut run = PlannedRun::new(framework, target, total, 1);
+                run.requests.extend(requests.iter().map(|(request, count)| (request.clone(), count.to_string())));
+                run
+            })
+        }
+        // Only reads the saved results
+        Commands::Compare { .. } => Vec::new(),
+        Commands::All { .. } => {
+            let mut runs = endpoint_runs(Method::GET, "/health", 100, 1000)?;
+            runs.extend(endpoint_runs(Method::POST, "/api/products", 50, 500)?);
+            runs.extend(graphql_runs(&GraphqlQuery::default(), 30, 300)?);
+            runs
+        }
+    };
+    Ok(runs)
+}
+
+/// Prints what `cli` would do, without sending anything: the connection
+/// settings, every request per server, and how long the whole thing should
+/// take at `--target-rps`. Invalid arguments fail here as they would in a
+/// real run.
+pub fn print_plan(cli: &Cli) -> Result<()> {
+    if cli.target_rps.is_some_and(|rps| !(rps.is_finite() && rps > 0.0)) {
+        bail!("--target-rps must be a positive number of requests per second");
+    }
+    let runs = planned_runs(&cli.command)?;
+    let think_time = Duration::from_millis(cli.think_time_ms);
+    let connection = connection();
+
+    println!("Dry run: no requests are sent.");
+    println!(
+        "tls: {}, insecure: {}, wait for database: {}, retries: {}, think time: {} ms",
+        connection.tls, connection.insecure, connection.wait_db, cli.retries, cli.think_time_ms
+    );
+    if let Some(path) = &cli.histogram_out {
+        println!("histogram written to: {}", path.display());
+    }
+    if let Some(path) = &cli.json_out {
+        println!("results written to: {}", path.display());
+    }
+    if runs.is_empty() {
+        println!("Nothing to send.");
+        return Ok(());
+    }
+
+    let rows: Vec<PlanRow> = runs
+        .iter()
+        .flat_map(|run| {
+            run.requests.iter().map(|(request, count)| PlanRow {
+                framework: run.framework.to_string(),
+                target: run.target.clone(),
+                request: request.clone(),
+                requests: count.clone(),
+                concurrency: run.concurrency,
+            })
+        })
+        .collect();
+    println!("{}", Table::new(&rows));
+
+    let estimates: Option<Vec<Duration>> = runs.iter().map(|run| run.estimate(think_time, cli.target_rps)).collect();
+    match (estimates, cli.target_rps) {
+        (Some(estimates), Some(rps)) => {
+            let total: Duration = estimates.iter().sum();
+            println!("Estimated duration: {:.1} s at {} req/s per server", total.as_secs_f64(), rps);
+        }
+        (Some(estimates), None) => {
+            let total: Duration = estimates.iter().sum();
+            println!("Estimated duration: {:.1} s", total.as_secs_f64());
+        }
+        (None, _) => {
+            let lower_bound: Duration = runs.iter().map(|run| run.min_duration.max(run.think_time(think_time))).sum();
+            if lower_bound.is_zero() {
+                println!("Estimated duration: unknown; pass --target-rps for an estimate");
+            } else {
+                println!(
+                    "Estimated duration: at least {:.1} s; pass --target-rps for an estimate",
+                    lower_bound.as_secs_f64()
+                );
+            }
+        }
+    }
+    Ok(())
+}
\ No newline at end of file
@@ -0,0 +1,132 @@
+use anyhow::{bail, Context, Result};
+use reqwest::Method;
+use serde::Deserialize;
+use serde_json::Value;
+use std::path::Path;
+use std::sync::Arc;
+use std::time::Duration;
+use tabled::Table;
+use tracing::info;
+
+use crate::{actix_url, axum_url, benchmark_script, wait_for_servers, RequestSpec, RunOptions, ScriptStep, Thresholds};
+
+const REPLAY: &str = "Replay";
+
+/// One line of a traffic log.
+#[derive(Deserialize)]
+struct LogEntry {
+    method: String,
+    /// Path and query, e.g. `/api/products?limit=5`.
+    path: String,
+    #[serde(default)]
+    body: Option<Value>,
+    /// Pause before sending this request.
+    #[serde(default)]
+    delay_ms: u64,
+}
+
+/// Reads a JSONL traffic log; blank lines are skipped and a bad line fails
+/// the whole file, naming the line.
+fn read_log(file: &Path) -> Result<Vec<LogEntry>> {
+    let contents = std::fs::read_to_string(file).with_context(|| format!("failed to read {}", file.display()))?;
+    let mut entries = Vec::new();
+    for (index, line) in contents.lines().enumerate() {
+        if line.trim().is_empty() {
+            continue;
+        }
+        let entry: LogEntry =
+            serde_json::from_str(line).with_context(|| format!("{} line {}", file.display(), index + 1))?;
+        if !entry.path.starts_with('/') {
+            bail!("{} line {}: path must start with /", file.display(), index + 1);
+        }
+        entries.push(entry);
+    }
+    if entries.is_empty() {
+        bail!("{} contains no requests", file.display());
+    }
+    Ok(entries)
+}
+
+/// The log as a script against `base_url`. Requests to `/graphql` count as
+/// failed when the response carries errors, as in the GraphQL benchmark.
+fn script(entries: &[LogEntry], base_url: &str) -> Result<Vec<ScriptStep>> {
+    entries
+        .iter()
+        .map(|entry| {
+            let method = Method::from_bytes(entry.method.to_ascii_uppercase().as_bytes())
+                .with_context(|| format!("invalid method {}", entry.method))?;
+            let url = format!("{}{}", base_url, entry.path);
+            let spec = match &entry.body {
+                Some(body) if entry.path == "/graphql" && method == Method::POST => {
+                    RequestSpec::graphql(&url, body.clone())
+                }
+                body => RequestSpec::new(method, &url, body.clone()),
+            };
+            Ok(ScriptStep {
+                spec,
+                delay: Duration::from_millis(entry.delay_ms),
+            })
+        })
+        .collect()
+}
+
+/// What a replay sends to each server, for `--dry-run`.
+pub struct ReplayPlan {
+    /// Each distinct method and path in the log, and how often it is sent
+    /// across all replays and loops.
+    pub requests: Vec<(String, usize)>,
+    pub total_requests: usize,
+    /// The delays one replay sits out; replays run side by side, so this is
+    /// also the least time the whole run takes.
+    pub delays: Duration,
+}
+
+/// Reads the log as `run_replay_benchmark` would, without sending anything.
+pub fn plan(file: &Path, concurrency: usize, loops: usize) -> Result<ReplayPlan> {
+    let entries = read_log(file)?;
+    let steps = script(&entries, "")?;
+    let mut requests: Vec<(String, usize)> = Vec::new();
+    for step in &steps {
+        let request = format!("{} {}", step.spec.method, step.spec.url);
+        match requests.iter_mut().find(|(seen, _)| *seen == request) {
+            Some((_, count)) => *count += loops * concurrency,
+            None => requests.push((request, loops * concurrency)),
+        }
+    }
+    Ok(ReplayPlan {
+        requests,
+        total_requests: entries.len() * loops * concurrency,
+        delays: steps.iter().map(|step| step.delay).sum::<Duration>() * loops as u32,
+    })
+}
+
+/// Each of `concurrency` workers replays the whole log `loops` times, in
+/// order and honouring the delays, interleaved with the other workers.
+pub async fn run_replay_benchmark(
+    file: &Path,
+    concurrency: usize,
+    loops: usize,
+    options: &RunOptions,
+    thresholds: &Thresholds,
+) -> Result<Vec<String>> {
+    let entries = read_log(file)?;
+    info!(
+        "Replaying {} requests from {} ({} concurrent replays, {} loops)...",
+        entries.len(),
+        file.display(),
+        concurrency,
+        loops
+    );
+
+    wait_for_servers().await?;
+
+    let total_requests = entries.len() * loops * concurrency;
+    let mut results = Vec::new();
+    for (base_url, framework) in [(axum_url(), "Axum"), (actix_url(), "ActixWeb")] {
+        let script = Arc::new(script(&entries, &base_url)?);
+        results.push(benchmark_script(script, concurrency, total_requests, framework, REPLAY, options).await?);
+    }
+    println!("{}", Table::new(&results));
+
+    Ok(thresholds.check_endpoints(&results))
+}
\ No newline at end of file
@@ -0,0 +1,328 @@
+use anyhow::{anyhow, bail, Result};
+use futures::future::join_all;
+use futures::{SinkExt, StreamExt};
+use reqwest::Client;
+use serde_json::{json, Value};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, OnceLock};
+use std::time::{Duration, Instant};
+use tabled::{Table, Tabled};
+use tokio::net::TcpStream;
+use tokio::sync::watch;
+use tokio::time::{interval, sleep, timeout, MissedTickBehavior};
+use tokio_tungstenite::tungstenite::client::IntoClientRequest;
+use tokio_tungstenite::tungstenite::http::HeaderValue;
+use tokio_tungstenite::tungstenite::Message;
+use tokio_tungstenite::{Connector, MaybeTlsStream, WebSocketStream};
+use tracing::{debug, info, warn};
+
+use crate::{
+    actix_url, axum_url, connection, hot_product_id, http_client, percentile_ms, wait_for_servers, RunOptions,
+    Thresholds,
+};
+
+type Socket = WebSocketStream<MaybeTlsStream<TcpStream>>;
+
+/// Subprotocol both servers speak on `/graphql/ws`.
+const PROTOCOL: &str = "graphql-transport-ws";
+
+const SUBSCRIPTION: &str = "subscription ($id: Id) { productUpdates(productId: $id) { inventory } }";
+
+/// Update `n` sets the inventory to `SEQUENCE_BASE + n`, so subscribers can
+/// tell which update a message carries and ignore any other traffic.
+const SEQUENCE_BASE: i64 = 1_000_000;
+
+/// How long to wait for every subscription to prove it is live.
+const WARM_UP_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// How long after the last update late deliveries are still waited for.
+const DRAIN_TIMEOUT: Duration = Duration::from_secs(5);
+
+const DELIVERY: &str = "subscription delivery";
+
+#[derive(Tabled)]
+struct SubscriptionResult {
+    framework: String,
+    subscribers: usize,
+    updates: usize,
+    delivered: usize,
+    dropped: usize,
+    /// Most updates any single subscriber missed.
+    worst_subscriber_dropped: usize,
+    deliveries_per_second: f64,
+    p50_ms: f64,
+    p90_ms: f64,
+    p99_ms: f64,
+}
+
+/// What one subscriber saw.
+struct SubscriberSamples {
+    /// Send-to-receive time of each update delivered.
+    latencies: Vec<Duration>,
+    /// Updates delivered at least once, by sequence number.
+    seen: Vec<bool>,
+}
+
+/// When each update was sent; unset for updates not sent (yet).
+type SendTimes = Arc<Vec<OnceLock<Instant>>>;
+
+/// `--min-rps` applies to deliveries per second summed over subscribers,
+/// `--max-p99-ms` to delivery latency.
+pub async fn run_subscription_benchmark(
+    subscribers: usize,
+    updates: usize,
+    rate: f64,
+    options: &RunOptions,
+    thresholds: &Thresholds,
+) -> Result<Vec<String>> {
+    if subscribers == 0 || updates == 0 {
+        bail!("subscribers and updates must both be at least 1");
+    }
+    if !(rate.is_finite() && rate > 0.0) {
+        bail!("rate must be a positive number of updates per second");
+    }
+    info!(
+        "Running subscription benchmark ({} subscribers, {} updates at {}/s)...",
+        subscribers, updates, rate
+    );
+
+    wait_for_servers().await?;
+
+    let mut rows = Vec::new();
+    for (base_url, framework) in [(axum_url(), "Axum"), (actix_url(), "ActixWeb")] {
+        rows.push(benchmark_subscriptions(&base_url, framework, subscribers, updates, rate, options).await?);
+    }
+    println!("{}", Table::new(&rows));
+
+    Ok(rows
+        .iter()
+        .flat_map(|row| {
+            thresholds.violations(&row.framework, DELIVERY, Some(row.deliveries_per_second), Some(row.p99_ms))
+        })
+        .collect())
+}
+
+async fn benchmark_subscriptions(
+    base_url: &str,
+    framework: &str,
+    subscribers: usize,
+    updates: usize,
+    rate: f64,
+    options: &RunOptions,
+) -> Result<SubscriptionResult> {
+    let client = http_client();
+    let product_id = hot_product_id(&client, base_url).await?;
+    let product_url = format!("{}/api/products/{}", base_url, product_id);
+    let original: Value = client.get(&product_url).send().await?.error_for_status()?.json().await?;
+    let original_inventory = original["inventory"].as_i64().unwrap_or_default();
+
+    let ws_url = format!("{}/graphql/ws", base_url.replacen("http", "ws", 1));
+    let sockets = join_all((0..subscribers).map(|_| open_subscription(&ws_url, &product_id))).await;
+    let sockets = sockets.into_iter().collect::<Result<Vec<_>>>()?;
+
+    let sent: SendTimes = Arc::new((0..updates).map(|_| OnceLock::new()).collect());
+    let ready = Arc::new(AtomicUsize::new(0));
+    let delivered = Arc::new(AtomicUsize::new(0));
+    let (stop, stopped) = watch::channel(false);
+    let tasks: Vec<_> = sockets
+        .into_iter()
+        .map(|socket| {
+            tokio::spawn(receive_updates(
+                socket,
+                sent.clone(),
+                ready.clone(),
+                delivered.clone(),
+                stopped.clone(),
+            ))
+        })
+        .collect();
+
+    // A subscription only starts listening some time after it was sent, so
+    // keep poking the product until every subscriber has seen a message
+    let warm_up_deadline = Instant::now() + WARM_UP_TIMEOUT;
+    while ready.load(Ordering::Relaxed) < subscribers {
+        if Instant::now() > warm_up_deadline {
+            bail!(
+                "{}: only {} of {} subscriptions received anything",
+                framework,
+                ready.load(Ordering::Relaxed),
+                subscribers
+            );
+        }
+        set_inventory(&client, &product_url, original_inventory).await?;
+        sleep(Duration::from_millis(100)).await;
+    }
+
+    let start_time = Instant::now();
+    let mut ticks = interval(Duration::from_secs_f64(1.0 / rate));
+    ticks.set_missed_tick_behavior(MissedTickBehavior::Burst);
+    let mut sends = Vec::with_capacity(updates);
+    for sequence in 0..updates {
+        ticks.tick().await;
+        let (client, product_url, sent) = (client.clone(), product_url.clone(), sent.clone());
+        sends.push(tokio::spawn(async move {
+            let _ = sent[sequence].set(Instant::now());
+            let sent_ok = set_inventory(&client, &product_url, SEQUENCE_BASE + sequence as i64).await;
+            if let Err(e) = &sent_ok {
+                warn!(sequence, error = %e, "product update failed");
+            }
+            sent_ok.is_ok()
+        }));
+    }
+    let sent_ok: Vec<bool> = join_all(sends).await.into_iter().map(|sent| sent.unwrap_or(false)).collect();
+    let updates_sent = sent_ok.iter().filter(|ok| **ok).count();
+
+    let drain_deadline = Instant::now() + DRAIN_TIMEOUT;
+    while delivered.load(Ordering::Relaxed) < updates_sent * subscribers && Instant::now() < drain_deadline {
+        sleep(Duration::from_millis(10)).await;
+    }
+    let elapsed = start_time.elapsed();
+    let _ = stop.send(true);
+
+    let mut latencies = Vec::new();
+    let mut dropped = 0;
+    let mut worst_subscriber_dropped = 0;
+    for (subscriber, task) in join_all(tasks).await.into_iter().enumerate() {
+        let samples = task?;
+        let missed = sent_ok
+            .iter()
+            .zip(&samples.seen)
+            .filter(|(sent, seen)| **sent && !**seen)
+            .count();
+        debug!(framework, subscriber, delivered = samples.latencies.len(), dropped = missed, "subscriber finished");
+        dropped += missed;
+        worst_subscriber_dropped = worst_subscriber_dropped.max(missed);
+        latencies.extend(samples.latencies);
+    }
+    latencies.sort();
+    if let Some(histograms) = &options.histograms {
+        histograms.record(framework, DELIVERY, &latencies);
+    }
+
+    set_inventory(&client, &product_url, original_inventory).await?;
+
+    Ok(SubscriptionResult {
+        framework: framework.to_string(),
+        subscribers,
+        updates: updates_sent,
+        delivered: latencies.len(),
+        dropped,
+        worst_subscriber_dropped,
+        deliveries_per_second: latencies.len() as f64 / elapsed.as_secs_f64(),
+        p50_ms: percentile_ms(&latencies, 50.0),
+        p90_ms: percentile_ms(&latencies, 90.0),
+        p99_ms: percentile_ms(&latencies, 99.0),
+    })
+}
+
+async fn set_inventory(client: &Client, product_url: &str, inventory: i64) -> Result<()> {
+    client
+        .patch(product_url)
+        .json(&json!({ "inventory": inventory }))
+        .send()
+        .await?
+        .error_for_status()?;
+    Ok(())
+}
+
+/// Connects, completes the `connection_init` handshake and subscribes to
+/// updates of `product_id`.
+async fn open_subscription(ws_url: &str, product_id: &str) -> Result<Socket> {
+    let mut request = ws_url.into_client_request()?;
+    request
+        .headers_mut()
+        .insert("sec-websocket-protocol", HeaderValue::from_static(PROTOCOL));
+    let connector = if connection().insecure {
+        Some(Connector::NativeTls(
+            native_tls::TlsConnector::builder().danger_accept_invalid_certs(true).build()?,
+        ))
+    } else {
+        None
+    };
+    let (mut socket, _) = tokio_tungstenite::connect_async_tls_with_config(request, None, false, connector).await?;
+
+    socket.send(Message::text(json!({ "type": "connection_init" }).to_string())).await?;
+    timeout(Duration::from_secs(5), async {
+        while let Some(message) = socket.next().await {
+            if let Message::Text(text) = message? {
+                let message: Value = serde_json::from_str(&text)?;
+                if message["type"] == "connection_ack" {
+                    return Ok(());
+                }
+            }
+        }
+        Err(anyhow!("{} closed the connection before acknowledging it", ws_url))
+    })
+    .await
+    .map_err(|_| anyhow!("{} did not acknowledge the connection", ws_url))??;
+
+    let subscribe = json!({
+        "id": "1",
+        "type": "subscribe",
+        "payload": { "query": SUBSCRIPTION, "variables": { "id": product_id } },
+    });
+    socket.send(Message::text(subscribe.to_string())).await?;
+    Ok(socket)
+}
+
+/// Records updates until `stopped` flips. Any message counts the subscriber
+/// as `ready`; only ones carrying a sequence number are measured.
+async fn receive_updates(
+    mut socket: Socket,
+    sent: SendTimes,
+    ready: Arc<AtomicUsize>,
+    delivered: Arc<AtomicUsize>,
+    mut stopped: watch::Receiver<bool>,
+) -> SubscriberSamples {
+    let mut samples = SubscriberSamples {
+        latencies: Vec::new(),
+        seen: vec![false; sent.len()],
+    };
+    let mut is_ready = false;
+    loop {
+        let message = tokio::select! {
+            message = socket.next() => message,
+            _ = stopped.changed() => break,
+        };
+        let text = match message {
+            Some(Ok(Message::Text(text))) => text,
+            Some(Ok(Message::Close(_))) | None => break,
+            Some(Ok(_)) => continue,
+            Some(Err(e)) => {
+                warn!(error = %e, "subscription connection failed");
+                break;
+            }
+        };
+        let received_at = Instant::now();
+        let Ok(message) = serde_json::from_str::<Value>(&text) else {
+            continue;
+        };
+        if message["type"] != "next" {
+            if message["type"] == "error" {
+                warn!(payload = %message["payload"], "subscription rejected");
+                break;
+            }
+            continue;
+        }
+        if !is_ready {
+            is_ready = true;
+            ready.fetch_add(1, Ordering::Relaxed);
+        }
+
+        let sequence = message
+            .pointer("/payload/data/productUpdates/inventory")
+            .and_then(Value::as_i64)
+            .and_then(|inventory| usize::try_from(inventory - SEQUENCE_BASE).ok())
+            .filter(|sequence| *sequence < sent.len());
+        let Some((sequence, Some(sent_at))) = sequence.map(|sequence| (sequence, sent[sequence].get())) else {
+            continue;
+        };
+        if !samples.seen[sequence] {
+            samples.seen[sequence] = true;
+            samples.latencies.push(received_at.duration_since(*sent_at));
+            delivered.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+    let _ = socket.close(None).await;
+    samples
+}
\ No newline at end of file
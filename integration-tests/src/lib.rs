@@ -0,0 +1,285 @@
+mod slow;
+
+use std::future::IntoFuture;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::Duration;
+
+use actix_web::dev::ServerHandle;
+use anyhow::{anyhow, Result};
+use futures::{SinkExt, StreamExt};
+use reqwest::header::HeaderMap;
+use reqwest::{Client, Method, RequestBuilder};
+use serde_json::{json, Value};
+use shared::auth::{create_jwt, Claims};
+use shared::config::Config;
+use shared::db::QueryTimer;
+use shared::id::Id;
+use shared::repository::ProductRepository;
+use shared::shutdown::InFlightRequests;
+use shared::store::{InMemoryProductStore, ProductStore};
+use sqlx::postgres::PgPoolOptions;
+use sqlx::PgPool;
+use tokio::net::TcpStream;
+use tokio::task::JoinHandle;
+use tokio_tungstenite::tungstenite::client::IntoClientRequest;
+use tokio_tungstenite::tungstenite::http::HeaderValue;
+use tokio_tungstenite::tungstenite::Message;
+use tokio_tungstenite::{MaybeTlsStream, WebSocketStream};
+
+pub use slow::SlowProductStore;
+
+/// HMAC key the test servers sign and verify tokens with.
+const TEST_JWT_KEYS: &str = r#"[{"kid": "test", "secret": "integration-test-secret"}]"#;
+
+/// Subprotocol both servers speak on `/graphql/ws`.
+const WS_PROTOCOL: &str = "graphql-transport-ws";
+
+/// How long a test waits for a subscription message before giving up.
+pub const WS_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// The two servers under test, which should answer every request alike.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Framework {
+    Axum,
+    Actix,
+}
+
+impl Framework {
+    pub const ALL: [Framework; 2] = [Framework::Axum, Framework::Actix];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Framework::Axum => "axum",
+            Framework::Actix => "actix-web",
+        }
+    }
+}
+
+/// Every setting at its default, whatever the process environment holds,
+/// plus a known JWT key and the cheapest bcrypt cost so logins stay fast.
+/// `vars` overrides individual variables.
+pub fn test_config(vars: &[(&str, &str)]) -> Config {
+    let mut all = vec![("JWT_KEYS", TEST_JWT_KEYS), ("BCRYPT_COST", "4")];
+    all.extend_from_slice(vars);
+    Config::from_vars(0, &all).expect("test configuration is valid")
+}
+
+pub fn in_memory_products() -> Arc<dyn ProductStore> {
+    Arc::new(InMemoryProductStore::new())
+}
+
+/// Products in the test's database, as the servers keep them outside tests
+/// but without the read cache.
+pub fn postgres_products(pool: &PgPool) -> Arc<dyn ProductStore> {
+    Arc::new(ProductRepository::new(pool.clone(), QueryTimer::new(Duration::from_secs(1))))
+}
+
+/// A pool that only connects once used, for tests that never touch users.
+pub fn lazy_pool(config: &Config) -> PgPool {
+    PgPoolOptions::new()
+        .connect_lazy(&config.database.database_url)
+        .expect("database URL is valid")
+}
+
+/// One of the servers on a free local port, stopped when dropped. Users
+/// live in the test's database, products in whichever store it was given.
+pub struct TestServer {
+    pub framework: Framework,
+    addr: SocketAddr,
+    client: Client,
+    config: Config,
+    running: Running,
+}
+
+enum Running {
+    Axum(JoinHandle<std::io::Result<()>>),
+    Actix(ServerHandle),
+}
+
+impl TestServer {
+    pub async fn start(
+        framework: Framework,
+        config: Config,
+        pool: PgPool,
+        products: Arc<dyn ProductStore>,
+    ) -> Result<Self> {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0")?;
+        let addr = listener.local_addr()?;
+        let running = match framework {
+            Framework::Axum => {
+                let state = axum_server::AppState::new(&config, pool, products)?;
+                let app = axum_server::router(state, &config, InFlightRequests::new())?;
+                listener.set_nonblocking(true)?;
+                let listener = tokio::net::TcpListener::from_std(listener)?;
+                let app = app.into_make_service_with_connect_info::<SocketAddr>();
+                Running::Axum(tokio::spawn(axum::serve(listener, app).into_future()))
+            }
+            Framework::Actix => {
+                let state = actix_web::web::Data::new(actixweb_server::AppState::new(&config, pool, products)?);
+                let options = actixweb_server::AppOptions::new(&config, InFlightRequests::new());
+                let server = actix_web::HttpServer::new(move || actixweb_server::app(state.clone(), &options))
+                    .workers(1)
+                    .disable_signals()
+                    .listen(listener)?
+                    .run();
+                let handle = server.handle();
+                tokio::spawn(server);
+                Running::Actix(handle)
+            }
+        };
+        Ok(Self {
+            framework,
+            addr,
+            client: Client::new(),
+            config,
+            running,
+        })
+    }
+
+    /// Both servers over the same store, so what one creates the other sees.
+    pub async fn start_both(config: Config, pool: PgPool, products: Arc<dyn ProductStore>) -> Result<[Self; 2]> {
+        Ok([
+            Self::start(Framework::Axum, config.clone(), pool.clone(), products.clone()).await?,
+            Self::start(Framework::Actix, config, pool, products).await?,
+        ])
+    }
+
+    pub fn url(&self, path: &str) -> String {
+        format!("http://{}{}", self.addr, path)
+    }
+
+    pub fn request(&self, method: Method, path: &str) -> RequestBuilder {
+        self.client.request(method, self.url(path))
+    }
+
+    pub fn get(&self, path: &str) -> RequestBuilder {
+        self.request(Method::GET, path)
+    }
+
+    pub fn post(&self, path: &str) -> RequestBuilder {
+        self.request(Method::POST, path)
+    }
+
+    /// A token this server accepts, for a user with `role` who need not
+    /// exist in the database.
+    pub fn token(&self, role: &str) -> String {
+        let claims = Claims::new(Id::new(), "tester".to_string(), "tester@example.com".to_string(), role.to_string());
+        create_jwt(&self.config.jwt_keys, &claims).expect("test token is signed")
+    }
+
+    /// Runs one GraphQL operation, with `token` as the bearer if given.
+    pub async fn graphql(&self, query: &str, variables: Value, token: Option<&str>) -> Result<Reply> {
+        let mut request = self.post("/graphql").json(&json!({ "query": query, "variables": variables }));
+        if let Some(token) = token {
+            request = request.bearer_auth(token);
+        }
+        Reply::read(request).await
+    }
+
+    /// Opens `/graphql/ws` with `headers` on the upgrade request, completes
+    /// the handshake and starts `query`.
+    pub async fn subscribe(&self, query: &str, variables: Value, headers: &[(&str, &str)]) -> Result<Subscription> {
+        let mut request = format!("ws://{}/graphql/ws", self.addr).into_client_request()?;
+        request
+            .headers_mut()
+            .insert("sec-websocket-protocol", HeaderValue::from_static(WS_PROTOCOL));
+        for (name, value) in headers {
+            request
+                .headers_mut()
+                .insert(tokio_tungstenite::tungstenite::http::HeaderName::try_from(*name)?, value.parse()?);
+        }
+        let (socket, _) = tokio_tungstenite::connect_async(request).await?;
+        let mut subscription = Subscription { socket };
+        subscription.send(json!({ "type": "connection_init" })).await?;
+        let ack = subscription.next_message().await?;
+        if ack["type"] != "connection_ack" {
+            return Err(anyhow!("expected connection_ack, got {}", ack));
+        }
+        subscription
+            .send(json!({ "id": "1", "type": "subscribe", "payload": { "query": query, "variables": variables } }))
+            .await?;
+        Ok(subscription)
+    }
+}
+
+impl Drop for TestServer {
+    fn drop(&mut self) {
+        match &self.running {
+            Running::Axum(task) => task.abort(),
+            // The stop command is sent right away; there is no need to wait
+            Running::Actix(handle) => drop(handle.stop(false)),
+        }
+    }
+}
+
+/// A response with its body parsed as JSON, or kept as a string when it
+/// isn't JSON.
+#[derive(Debug)]
+pub struct Reply {
+    pub status: u16,
+    pub headers: HeaderMap,
+    pub body: Value,
+}
+
+impl Reply {
+    pub async fn read(request: RequestBuilder) -> Result<Self> {
+        let response = request.send().await?;
+        let status = response.status().as_u16();
+        let headers = response.headers().clone();
+        let text = response.text().await?;
+        let body = serde_json::from_str(&text).unwrap_or(Value::String(text));
+        Ok(Self { status, headers, body })
+    }
+
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers.get(name).and_then(|value| value.to_str().ok())
+    }
+}
+
+/// A `graphql-transport-ws` connection with one operation running.
+pub struct Subscription {
+    socket: WebSocketStream<MaybeTlsStream<TcpStream>>,
+}
+
+impl Subscription {
+    async fn send(&mut self, message: Value) -> Result<()> {
+        self.socket.send(Message::text(message.to_string())).await?;
+        Ok(())
+    }
+
+    /// The next protocol message other than pings, within `WS_TIMEOUT`.
+    pub async fn next_message(&mut self) -> Result<Value> {
+        tokio::time::timeout(WS_TIMEOUT, async {
+            while let Some(message) = self.socket.next().await {
+                match message? {
+                    Message::Text(text) => {
+                        let message: Value = serde_json::from_str(&text)?;
+                        if message["type"] != "ping" {
+                            return Ok(message);
+                        }
+                    }
+                    Message::Close(_) => break,
+                    _ => {}
+                }
+            }
+            Err(anyhow!("the server closed the connection"))
+        })
+        .await
+        .map_err(|_| anyhow!("no message within {:?}", WS_TIMEOUT))?
+    }
+
+    /// The payload of the next `next` message, failing on anything else.
+    pub async fn next_event(&mut self) -> Result<Value> {
+        let message = self.next_message().await?;
+        if message["type"] != "next" {
+            return Err(anyhow!("expected a next message, got {}", message));
+        }
+        Ok(message["payload"].clone())
+    }
+
+    /// Whether a message arrives within `wait`.
+    pub async fn is_silent_for(&mut self, wait: Duration) -> bool {
+        tokio::time::timeout(wait, self.next_message()).await.is_err()
+    }
+}
\ No newline at end of file
@@ -0,0 +1,104 @@
+use std::collections::HashMap;
+use std::time::Duration;
+
+use async_trait::async_trait;
+use futures::stream::BoxStream;
+use shared::error::AppResult;
+use shared::id::Id;
+use shared::models::{Category, CreateCategory, CreateProduct, Product, ProductFilter, ProductStats, UpdateProduct};
+use shared::repository::ProductChange;
+use shared::store::{InMemoryProductStore, ProductStore};
+
+/// `InMemoryProductStore` whose product lists take `delay` to answer, for
+/// driving `GET /api/products` past the request timeout.
+pub struct SlowProductStore {
+    inner: InMemoryProductStore,
+    delay: Duration,
+}
+
+impl SlowProductStore {
+    pub fn new(delay: Duration) -> Self {
+        Self { inner: InMemoryProductStore::new(), delay }
+    }
+}
+
+#[async_trait]
+impl ProductStore for SlowProductStore {
+    async fn list(&self) -> AppResult<Vec<Product>> {
+        self.inner.list().await
+    }
+
+    async fn list_page(&self, filter: &ProductFilter, limit: i64, offset: i64) -> AppResult<Vec<Product>> {
+        tokio::time::sleep(self.delay).await;
+        self.inner.list_page(filter, limit, offset).await
+    }
+
+    async fn count(&self, filter: &ProductFilter) -> AppResult<i64> {
+        self.inner.count(filter).await
+    }
+
+    fn stream_all(&self) -> BoxStream<'static, AppResult<Product>> {
+        self.inner.stream_all()
+    }
+
+    async fn find_by_id(&self, id: Id) -> AppResult<Option<Product>> {
+        self.inner.find_by_id(id).await
+    }
+
+    async fn create(&self, input: CreateProduct) -> AppResult<Product> {
+        self.inner.create(input).await
+    }
+
+    async fn create_many(&self, inputs: Vec<CreateProduct>) -> AppResult<Vec<Product>> {
+        self.inner.create_many(inputs).await
+    }
+
+    async fn update(&self, id: Id, input: UpdateProduct) -> AppResult<Option<ProductChange>> {
+        self.inner.update(id, input).await
+    }
+
+    async fn update_if_match(
+        &self,
+        id: Id,
+        input: UpdateProduct,
+        if_match: &str,
+    ) -> AppResult<Option<ProductChange>> {
+        self.inner.update_if_match(id, input, if_match).await
+    }
+
+    async fn reserve_inventory(&self, id: Id, quantity: i32) -> AppResult<Product> {
+        self.inner.reserve_inventory(id, quantity).await
+    }
+
+    async fn delete(&self, id: Id) -> AppResult<bool> {
+        self.inner.delete(id).await
+    }
+
+    async fn stats(&self) -> AppResult<ProductStats> {
+        self.inner.stats().await
+    }
+
+    async fn link_shopify_product(&self, id: Id, shopify_product_id: i64) -> AppResult<bool> {
+        self.inner.link_shopify_product(id, shopify_product_id).await
+    }
+
+    async fn list_categories(&self) -> AppResult<Vec<Category>> {
+        self.inner.list_categories().await
+    }
+
+    async fn create_category(&self, input: CreateCategory) -> AppResult<Category> {
+        self.inner.create_category(input).await
+    }
+
+    async fn categories_of(&self, product_ids: &[Id]) -> AppResult<HashMap<Id, Vec<Category>>> {
+        self.inner.categories_of(product_ids).await
+    }
+
+    async fn assign_category(&self, product_id: Id, category_id: Id) -> AppResult<()> {
+        self.inner.assign_category(product_id, category_id).await
+    }
+
+    async fn unassign_category(&self, product_id: Id, category_id: Id) -> AppResult<bool> {
+        self.inner.unassign_category(product_id, category_id).await
+    }
+}
\ No newline at end of file
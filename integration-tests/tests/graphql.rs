@@ -0,0 +1,83 @@
+use integration_tests::{in_memory_products, lazy_pool, test_config, Framework, TestServer};
+use serde_json::{json, Value};
+use sqlx::PgPool;
+
+async fn start(framework: Framework, vars: &[(&str, &str)]) -> TestServer {
+    let config = test_config(vars);
+    let pool = lazy_pool(&config);
+    TestServer::start(framework, config, pool, in_memory_products()).await.unwrap()
+}
+
+#[tokio::test]
+async fn introspection_is_rejected_when_turned_off() {
+    for framework in Framework::ALL {
+        let server = start(framework, &[("GRAPHQL_INTROSPECTION", "false")]).await;
+        for query in [
+            "{ __schema { queryType { name } } }",
+            "{ __type(name: \"Product\") { name } }",
+            "{ ...Hidden } fragment Hidden on Query { __schema { types { name } } }",
+        ] {
+            let reply = server.graphql(query, Value::Null, None).await.unwrap();
+            assert_eq!(reply.status, 200, "{}", framework.name());
+            assert_eq!(reply.body["data"], Value::Null, "{}: {}", framework.name(), query);
+            assert_eq!(
+                reply.body["errors"][0]["extensions"]["code"],
+                "INTROSPECTION_DISABLED",
+                "{}: {}: {:?}",
+                framework.name(),
+                query,
+                reply.body
+            );
+        }
+
+        let reply = server.graphql("{ __typename }", Value::Null, None).await.unwrap();
+        assert_eq!(reply.body, json!({"data": {"__typename": "Query"}}), "{}", framework.name());
+    }
+}
+
+#[tokio::test]
+async fn introspection_answers_when_turned_on() {
+    for framework in Framework::ALL {
+        let server = start(framework, &[("GRAPHQL_INTROSPECTION", "true")]).await;
+        let reply = server.graphql("{ __schema { queryType { name } } }", Value::Null, None).await.unwrap();
+        assert_eq!(
+            reply.body,
+            json!({"data": {"__schema": {"queryType": {"name": "Query"}}}}),
+            "{}",
+            framework.name()
+        );
+    }
+}
+
+#[tokio::test]
+async fn websocket_operations_run_as_the_upgrade_request_user() {
+    let stats = "{ productStats { totalProducts } }";
+    for framework in Framework::ALL {
+        let server = start(framework, &[]).await;
+        let bearer = format!("Bearer {}", server.token("admin"));
+        let mut subscription = server.subscribe(stats, Value::Null, &[("authorization", &bearer)]).await.unwrap();
+        let event = subscription.next_event().await.unwrap();
+        assert_eq!(event, json!({"data": {"productStats": {"totalProducts": 0}}}), "{}", framework.name());
+
+        let mut subscription = server.subscribe(stats, Value::Null, &[]).await.unwrap();
+        let event = subscription.next_event().await.unwrap();
+        assert_eq!(event["errors"][0]["extensions"]["code"], "UNAUTHENTICATED", "{}: {}", framework.name(), event);
+    }
+}
+
+#[sqlx::test(migrations = "../migrations")]
+async fn websocket_logins_see_the_client_address(pool: PgPool) {
+    let login = "mutation { login(email: \"nobody@example.com\", password: \"Nobody0Password\") { token } }";
+    let config = test_config(&[]);
+    for server in TestServer::start_both(config, pool.clone(), in_memory_products()).await.unwrap() {
+        let mut subscription = server.subscribe(login, Value::Null, &[]).await.unwrap();
+        let event = subscription.next_event().await.unwrap();
+        assert_eq!(
+            event["errors"][0]["message"],
+            "Authentication error: invalid email or password",
+            "{}: {}",
+            server.framework.name(),
+            event
+        );
+    }
+}
\ No newline at end of file
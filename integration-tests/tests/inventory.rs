@@ -0,0 +1,96 @@
+use std::sync::Arc;
+
+use futures::future::join_all;
+use integration_tests::{in_memory_products, postgres_products, test_config, Reply, TestServer};
+use serde_json::json;
+use shared::store::ProductStore;
+use sqlx::PgPool;
+
+const STOCK: i64 = 10;
+const RESERVATIONS: usize = 30;
+
+/// Fires `RESERVATIONS` single-unit reservations at both servers at once and
+/// checks that exactly `STOCK` of them went through.
+async fn race_reservations(products: Arc<dyn ProductStore>, pool: PgPool) {
+    let servers = TestServer::start_both(test_config(&[]), pool, products).await.unwrap();
+    let created = Reply::read(servers[0].post("/api/products").json(&json!({
+        "name": "Last few", "description": "", "price": 100, "inventory": STOCK,
+    })))
+    .await
+    .unwrap();
+    let path = format!("/api/products/{}", created.body["id"].as_str().unwrap());
+    let reserve = format!("{}/reserve", path);
+
+    let replies = join_all((0..RESERVATIONS).map(|n| {
+        let server = &servers[n % servers.len()];
+        Reply::read(server.post(&reserve).json(&json!({ "quantity": 1 })))
+    }))
+    .await;
+
+    let mut reserved = 0;
+    for reply in replies {
+        let reply = reply.unwrap();
+        match reply.status {
+            200 => reserved += 1,
+            400 => assert_eq!(reply.body["error"], "Validation error: insufficient inventory", "{:?}", reply.body),
+            status => panic!("unexpected {}: {:?}", status, reply.body),
+        }
+    }
+    assert_eq!(reserved, STOCK);
+
+    let product = Reply::read(servers[1].get(&path)).await.unwrap();
+    assert_eq!(product.body["inventory"], 0);
+}
+
+#[sqlx::test(migrations = "../migrations")]
+async fn concurrent_reservations_never_oversell_in_postgres(pool: PgPool) {
+    race_reservations(postgres_products(&pool), pool).await;
+}
+
+#[sqlx::test(migrations = "../migrations")]
+async fn concurrent_reservations_never_oversell_in_memory(pool: PgPool) {
+    race_reservations(in_memory_products(), pool).await;
+}
+const BATCH: &str = "mutation ($updates: [InventoryUpdateInput!]!) {
+    updateInventoryBatch(updates: $updates) { success error code product { inventory } }
+}";
+
+/// Batch items that would push inventory past `i32::MAX` fail on their own,
+/// and the store keeps serving afterwards.
+async fn overflow_inventory(products: Arc<dyn ProductStore>, pool: PgPool) {
+    for server in TestServer::start_both(test_config(&[]), pool.clone(), products.clone()).await.unwrap() {
+        let name = server.framework.name();
+        let created = Reply::read(server.post("/api/products").json(&json!({
+            "name": "Overstocked", "description": "", "price": 100, "inventory": STOCK,
+        })))
+        .await
+        .unwrap();
+        let id = created.body["id"].as_str().unwrap();
+
+        let updates = json!({ "updates": [
+            { "productId": id, "delta": i32::MAX },
+            { "productId": id, "delta": i32::MIN },
+            { "productId": id, "delta": 1 },
+        ]});
+        let reply = server.graphql(BATCH, updates, None).await.unwrap();
+        let results = &reply.body["data"]["updateInventoryBatch"];
+        assert_eq!(results[0]["success"], false, "{}: {:?}", name, reply.body);
+        assert_eq!(results[0]["error"], "Validation error: inventory is out of range", "{}", name);
+        assert_eq!(results[1]["success"], false, "{}: {:?}", name, reply.body);
+        assert_eq!(results[2]["product"]["inventory"], STOCK + 1, "{}: {:?}", name, reply.body);
+
+        let product = Reply::read(server.get(&format!("/api/products/{}", id))).await.unwrap();
+        assert_eq!(product.status, 200, "{}: {:?}", name, product.body);
+        assert_eq!(product.body["inventory"], STOCK + 1, "{}", name);
+    }
+}
+
+#[sqlx::test(migrations = "../migrations")]
+async fn inventory_overflow_is_a_validation_error_in_postgres(pool: PgPool) {
+    overflow_inventory(postgres_products(&pool), pool).await;
+}
+
+#[sqlx::test(migrations = "../migrations")]
+async fn inventory_overflow_is_a_validation_error_in_memory(pool: PgPool) {
+    overflow_inventory(in_memory_products(), pool).await;
+}
\ No newline at end of file
@@ -0,0 +1,320 @@
+use std::collections::{BTreeMap, HashMap};
+
+use integration_tests::{in_memory_products, test_config, Framework, Reply, TestServer};
+use reqwest::Method;
+use serde_json::{json, Value};
+use sqlx::PgPool;
+
+/// Headers compared by value.
+const COMPARED_HEADERS: &[&str] = &[
+    "content-type",
+    "allow",
+    "link",
+    "x-total-count",
+    "x-pagination-clamped",
+    "retry-after",
+    "x-content-type-options",
+    "x-frame-options",
+    "referrer-policy",
+    "content-security-policy",
+];
+
+/// Headers whose values differ by design, compared by presence only.
+const PRESENT_HEADERS: &[&str] = &["etag", "x-request-id"];
+
+/// Body fields whose values differ by design: issued JWTs and the name of
+/// the framework.
+const MASKED_KEYS: &[&str] = &["token", "framework"];
+
+/// Reads a value out of a response for later vectors to use.
+type Capture = fn(&Reply) -> Option<String>;
+
+/// One request sent unchanged to both servers. `{name}` in the path, the
+/// body or a header value is replaced with what an earlier vector captured
+/// under that name.
+struct Vector {
+    name: &'static str,
+    method: Method,
+    path: &'static str,
+    body: Option<Value>,
+    headers: Vec<(&'static str, &'static str)>,
+    bearer: bool,
+    capture: Option<(&'static str, Capture)>,
+}
+
+impl Vector {
+    fn new(name: &'static str, method: Method, path: &'static str) -> Self {
+        Self { name, method, path, body: None, headers: Vec::new(), bearer: false, capture: None }
+    }
+
+    fn json(mut self, body: Value) -> Self {
+        self.body = Some(body);
+        self
+    }
+
+    fn header(mut self, name: &'static str, value: &'static str) -> Self {
+        self.headers.push((name, value));
+        self
+    }
+
+    /// Sends the token captured as `{token}`.
+    fn bearer(mut self) -> Self {
+        self.bearer = true;
+        self
+    }
+
+    fn capture(mut self, name: &'static str, read: Capture) -> Self {
+        self.capture = Some((name, read));
+        self
+    }
+
+    fn graphql(name: &'static str, query: &str) -> Self {
+        Self::new(name, Method::POST, "/graphql").json(json!({ "query": query }))
+    }
+}
+
+fn body_id(reply: &Reply) -> Option<String> {
+    reply.body["id"].as_str().map(str::to_string)
+}
+
+/// Run in order; later vectors rely on what earlier ones created. Bodies and
+/// query strings the extractors reject are left out, since the message comes
+/// from each framework's deserializer.
+fn vectors() -> Vec<Vector> {
+    let password = "Parity0Password";
+    vec![
+        Vector::new("health", Method::GET, "/health"),
+        Vector::new("unknown route", Method::GET, "/api/nope"),
+        Vector::new("wrong method", Method::DELETE, "/api/products"),
+        Vector::new("register: invalid email", Method::POST, "/api/auth/register")
+            .json(json!({"username": "parity", "email": "notanemail", "password": password})),
+        Vector::new("register: weak password", Method::POST, "/api/auth/register")
+            .json(json!({"username": "parity", "email": "parity@example.com", "password": "short"})),
+        Vector::new("register", Method::POST, "/api/auth/register")
+            .json(json!({"username": "parity", "email": "parity@example.com", "password": password}))
+            .capture("user", body_id),
+        Vector::new("register: taken email", Method::POST, "/api/auth/register")
+            .json(json!({"username": "parity", "email": "parity@example.com", "password": password})),
+        Vector::new("login: wrong password", Method::POST, "/api/auth/login")
+            .json(json!({"email": "parity@example.com", "password": "Wrong0Password"})),
+        Vector::new("login", Method::POST, "/api/auth/login")
+            .json(json!({"email": "parity@example.com", "password": password}))
+            .capture("token", |reply| reply.body["token"].as_str().map(str::to_string)),
+        Vector::new("users: not an admin", Method::GET, "/api/users").bearer(),
+        Vector::new("user: no token", Method::GET, "/api/users/{user}"),
+        Vector::new("user", Method::GET, "/api/users/{user}").bearer(),
+        Vector::new("user: invalid id", Method::GET, "/api/users/not-an-id").bearer(),
+        Vector::new("admin stats: no token", Method::GET, "/api/admin/stats"),
+        Vector::new("create product", Method::POST, "/api/products")
+            .json(json!({"name": "Parity lamp", "description": "parity check", "price": 1999, "inventory": 3}))
+            .capture("product", body_id),
+        Vector::new("create product: invalid", Method::POST, "/api/products")
+            .json(json!({"name": " ", "description": "", "price": -5, "inventory": -1})),
+        Vector::new("bulk create", Method::POST, "/api/products/bulk").json(json!([
+            {"name": "Parity desk", "description": "", "price": 10000, "inventory": 1},
+            {"name": "Parity chair", "description": "", "price": 5000, "inventory": 0},
+        ])),
+        Vector::new("get product", Method::GET, "/api/products/{product}")
+            .capture("etag", |reply| reply.header("etag").map(str::to_string)),
+        Vector::new("get product: not modified", Method::GET, "/api/products/{product}")
+            .header("if-none-match", "{etag}"),
+        Vector::new("get product: invalid id", Method::GET, "/api/products/not-an-id"),
+        Vector::new("get product: missing", Method::GET, "/api/products/00000000-0000-0000-0000-000000000000"),
+        Vector::new("list products: first page", Method::GET, "/api/products?limit=2"),
+        Vector::new("list products: last page", Method::GET, "/api/products?limit=2&offset=2"),
+        Vector::new("list products: clamped", Method::GET, "/api/products?limit=100000"),
+        Vector::new("patch product", Method::PATCH, "/api/products/{product}").json(json!({"inventory": 7})),
+        Vector::new("put product: stale etag", Method::PUT, "/api/products/{product}")
+            .header("if-match", "{etag}")
+            .json(json!({"name": "Parity lamp", "description": "replaced", "price": 2999, "inventory": 1})),
+        Vector::new("reserve: too many", Method::POST, "/api/products/{product}/reserve").json(json!({"quantity": 100})),
+        Vector::new("reserve", Method::POST, "/api/products/{product}/reserve").json(json!({"quantity": 2})),
+        Vector::new("create category", Method::POST, "/api/categories")
+            .json(json!({"name": "Lighting"}))
+            .capture("category", body_id),
+        Vector::new("create category: duplicate", Method::POST, "/api/categories").json(json!({"name": "Lighting"})),
+        Vector::new("assign category", Method::PUT, "/api/products/{product}/categories/{category}"),
+        Vector::new("product categories", Method::GET, "/api/products/{product}/categories"),
+        Vector::graphql("graphql: product", "{ product(id: \"{product}\") { id name price inventory categories { name } } }"),
+        Vector::graphql(
+            "graphql: strict missing product",
+            "{ product(id: \"00000000-0000-0000-0000-000000000000\", strict: true) { id } }",
+        ),
+        Vector::graphql("graphql: users without token", "{ users(first: 1) { edges { node { id } } } }"),
+        Vector::graphql("graphql: user without token", "{ user(id: \"{user}\") { id } }"),
+        Vector::graphql("graphql: parse error", "{ products {"),
+        Vector::graphql("graphql: unknown field", "{ nope }"),
+        Vector::new("graphql: batch", Method::POST, "/graphql").json(json!([
+            {"query": "{ product(id: \"{product}\") { name } }"},
+            {"query": "mutation { updateProduct(id: \"{product}\", input: { price: -1 }) { id } }"},
+        ])),
+        Vector::new("logout: no token", Method::POST, "/api/auth/logout"),
+        Vector::new("logout", Method::POST, "/api/auth/logout").bearer(),
+        Vector::new("logout: revoked token", Method::POST, "/api/auth/logout").bearer(),
+        Vector::new("delete product", Method::DELETE, "/api/products/{product}"),
+        Vector::new("delete product: again", Method::DELETE, "/api/products/{product}"),
+        Vector::new("delete product: idempotent", Method::DELETE, "/api/products/{product}?idempotent=true"),
+    ]
+}
+
+/// What a response must agree on across the servers.
+#[derive(PartialEq)]
+struct Observed {
+    status: u16,
+    headers: BTreeMap<&'static str, String>,
+    body: Value,
+}
+
+impl Observed {
+    fn pretty(&self) -> String {
+        let observed = json!({ "status": self.status, "headers": self.headers, "body": self.body });
+        serde_json::to_string_pretty(&observed).unwrap()
+    }
+}
+
+/// Sends every vector to `server`, capturing values for later vectors as it
+/// goes.
+async fn run(server: &TestServer) -> Vec<Observed> {
+    let mut captured: HashMap<&str, String> = HashMap::new();
+    let mut ids = Placeholders::default();
+    let mut observed = Vec::new();
+    for vector in vectors() {
+        let fill = |text: &str| {
+            captured
+                .iter()
+                .fold(text.to_string(), |text, (name, value)| text.replace(&format!("{{{}}}", name), value))
+        };
+        let mut request = server.request(vector.method.clone(), &fill(vector.path));
+        if let Some(body) = &vector.body {
+            request = request
+                .header("content-type", "application/json")
+                .body(fill(&body.to_string()));
+        }
+        for (name, value) in &vector.headers {
+            request = request.header(*name, fill(value));
+        }
+        if vector.bearer {
+            request = request.bearer_auth(&captured["token"]);
+        }
+        let reply = Reply::read(request)
+            .await
+            .unwrap_or_else(|e| panic!("{} on {}: {}", vector.name, server.framework.name(), e));
+        if let Some((name, read)) = vector.capture {
+            let value = read(&reply)
+                .unwrap_or_else(|| panic!("{} on {} captured no {}", vector.name, server.framework.name(), name));
+            captured.insert(name, value);
+        }
+        observed.push(ids.observe(reply));
+    }
+    observed
+}
+
+/// Replaces ids with `<id-N>`, numbered in order of first appearance so a
+/// response echoing an earlier id still has to echo the same one, and every
+/// timestamp with `<timestamp>`.
+#[derive(Default)]
+struct Placeholders {
+    ids: HashMap<String, String>,
+}
+
+impl Placeholders {
+    fn observe(&mut self, reply: Reply) -> Observed {
+        let mut headers = BTreeMap::new();
+        for name in COMPARED_HEADERS {
+            if let Some(value) = reply.header(name) {
+                headers.insert(*name, value.to_string());
+            }
+        }
+        for name in PRESENT_HEADERS {
+            if reply.header(name).is_some() {
+                headers.insert(*name, format!("<{}>", name));
+            }
+        }
+        Observed {
+            status: reply.status,
+            headers,
+            body: self.normalize(reply.body),
+        }
+    }
+
+    fn normalize(&mut self, value: Value) -> Value {
+        match value {
+            Value::Object(map) => Value::Object(
+                map.into_iter()
+                    .map(|(key, value)| {
+                        let value = if MASKED_KEYS.contains(&key.as_str()) {
+                            Value::String(format!("<{}>", key))
+                        } else {
+                            self.normalize(value)
+                        };
+                        (key, value)
+                    })
+                    .collect(),
+            ),
+            Value::Array(items) => Value::Array(items.into_iter().map(|item| self.normalize(item)).collect()),
+            Value::String(s) => Value::String(self.normalize_str(&s)),
+            other => other,
+        }
+    }
+
+    fn normalize_str(&mut self, s: &str) -> String {
+        if chrono::DateTime::parse_from_rfc3339(s).is_ok() {
+            return "<timestamp>".to_string();
+        }
+        // Ids also show up inside messages and GraphQL queries echoed back
+        let mut out = s.to_string();
+        for word in s.split(|c: char| !(c.is_ascii_hexdigit() || c == '-')) {
+            if uuid::Uuid::try_parse(word).is_ok() {
+                let next = self.ids.len() + 1;
+                let placeholder = self.ids.entry(word.to_string()).or_insert_with(|| format!("<id-{}>", next));
+                out = out.replace(word, placeholder);
+            }
+        }
+        out
+    }
+}
+
+/// A line diff of the two normalized responses.
+fn diff(axum: &str, actix: &str) -> String {
+    let axum: Vec<&str> = axum.lines().collect();
+    let actix: Vec<&str> = actix.lines().collect();
+    (0..axum.len().max(actix.len()))
+        .flat_map(|i| match (axum.get(i), actix.get(i)) {
+            (Some(a), Some(b)) if a == b => vec![format!("        {}", a)],
+            (a, b) => a
+                .map(|a| format!("- axum  {}", a))
+                .into_iter()
+                .chain(b.map(|b| format!("+ actix {}", b)))
+                .collect(),
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+#[sqlx::test(migrations = "../migrations")]
+async fn both_servers_answer_every_vector_alike(pool: PgPool) {
+    let axum = TestServer::start(Framework::Axum, test_config(&[]), pool.clone(), in_memory_products())
+        .await
+        .unwrap();
+    let axum_observed = run(&axum).await;
+    // The second server registers the same account again
+    sqlx::query("TRUNCATE users CASCADE").execute(&pool).await.unwrap();
+    let actix = TestServer::start(Framework::Actix, test_config(&[]), pool, in_memory_products())
+        .await
+        .unwrap();
+    let actix_observed = run(&actix).await;
+
+    let diverged: Vec<String> = vectors()
+        .iter()
+        .zip(axum_observed.iter().zip(&actix_observed))
+        .filter(|(_, (axum, actix))| axum != actix)
+        .map(|(vector, (axum, actix))| format!("{}:\n{}", vector.name, diff(&axum.pretty(), &actix.pretty())))
+        .collect();
+    assert!(
+        diverged.is_empty(),
+        "{} of {} vectors diverged\n\n{}",
+        diverged.len(),
+        axum_observed.len(),
+        diverged.join("\n\n")
+    );
+}
\ No newline at end of file
@@ -0,0 +1,241 @@
+use integration_tests::{in_memory_products, lazy_pool, postgres_products, test_config, Framework, Reply, TestServer};
+use reqwest::Method;
+use serde_json::json;
+use shared::models::MAX_PRICE_CENTS;
+use sqlx::PgPool;
+
+async fn start(framework: Framework) -> TestServer {
+    let config = test_config(&[]);
+    let pool = lazy_pool(&config);
+    TestServer::start(framework, config, pool, in_memory_products()).await.unwrap()
+}
+
+#[tokio::test]
+async fn product_crud_round_trips_on_the_in_memory_store() {
+    for framework in Framework::ALL {
+        let server = start(framework).await;
+
+        let created = Reply::read(
+            server
+                .post("/api/products")
+                .json(&json!({"name": "Lamp", "description": "desk lamp", "price": 2500, "inventory": 4})),
+        )
+        .await
+        .unwrap();
+        assert_eq!(created.status, 200, "{}: {:?}", framework.name(), created.body);
+        assert_eq!(created.body["name"], "Lamp");
+        let id = created.body["id"].as_str().unwrap().to_string();
+        let path = format!("/api/products/{}", id);
+
+        let fetched = Reply::read(server.get(&path)).await.unwrap();
+        assert_eq!(fetched.status, 200, "{}", framework.name());
+        assert_eq!(fetched.body, created.body, "{}", framework.name());
+        assert!(fetched.header("etag").is_some(), "{}", framework.name());
+
+        let patched = Reply::read(server.request(Method::PATCH, &path).json(&json!({"inventory": 9})))
+            .await
+            .unwrap();
+        assert_eq!(patched.status, 200, "{}", framework.name());
+        assert_eq!(patched.body["inventory"], 9);
+        assert_eq!(patched.body["name"], "Lamp");
+
+        let replaced = Reply::read(
+            server
+                .request(Method::PUT, &path)
+                .json(&json!({"name": "Floor lamp", "description": "tall", "price": 4000, "inventory": 1})),
+        )
+        .await
+        .unwrap();
+        assert_eq!(replaced.status, 200, "{}", framework.name());
+        assert_eq!(replaced.body["name"], "Floor lamp");
+
+        let listed = Reply::read(server.get("/api/products")).await.unwrap();
+        assert_eq!(listed.status, 200, "{}", framework.name());
+        assert_eq!(listed.header("x-total-count"), Some("1"), "{}", framework.name());
+        assert_eq!(listed.body["items"][0]["id"], id.as_str(), "{}", framework.name());
+
+        let deleted = Reply::read(server.request(Method::DELETE, &path)).await.unwrap();
+        assert_eq!(deleted.status, 204, "{}", framework.name());
+
+        let missing = Reply::read(server.get(&path)).await.unwrap();
+        assert_eq!(missing.status, 404, "{}", framework.name());
+        assert_eq!(missing.body, json!({"error": "Not found: product", "kind": "NOT_FOUND"}));
+    }
+}
+
+#[tokio::test]
+async fn invalid_products_are_rejected_with_field_errors() {
+    for framework in Framework::ALL {
+        let server = start(framework).await;
+        let reply = Reply::read(
+            server
+                .post("/api/products")
+                .json(&json!({"name": "", "description": "", "price": -1, "inventory": 0})),
+        )
+        .await
+        .unwrap();
+        assert_eq!(reply.status, 400, "{}", framework.name());
+        assert_eq!(reply.body["kind"], "VALIDATION");
+        let fields: Vec<&str> = reply.body["fields"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|field| field["field"].as_str().unwrap())
+            .collect();
+        assert_eq!(fields, ["name", "price"], "{}", framework.name());
+    }
+}
+
+#[tokio::test]
+async fn list_headers_follow_the_page_position() {
+    for framework in Framework::ALL {
+        let server = start(framework).await;
+        for n in 0..5 {
+            let product = json!({"name": format!("Product {}", n), "description": "", "price": 100, "inventory": 1});
+            let reply = Reply::read(server.post("/api/products").json(&product)).await.unwrap();
+            assert_eq!(reply.status, 200, "{}", framework.name());
+        }
+
+        let cases = [
+            ("limit=2", Some(r#"</api/products?limit=2&offset=2>; rel="next""#)),
+            (
+                "limit=2&offset=2",
+                Some(r#"</api/products?limit=2&offset=4>; rel="next", </api/products?limit=2&offset=0>; rel="prev""#),
+            ),
+            ("limit=2&offset=4", Some(r#"</api/products?limit=2&offset=2>; rel="prev""#)),
+            ("limit=5", None),
+        ];
+        for (query, link) in cases {
+            let reply = Reply::read(server.get(&format!("/api/products?{}", query))).await.unwrap();
+            assert_eq!(reply.status, 200, "{}: {}", framework.name(), query);
+            assert_eq!(reply.header("x-total-count"), Some("5"), "{}: {}", framework.name(), query);
+            assert_eq!(reply.header("link"), link, "{}: {}", framework.name(), query);
+        }
+    }
+}
+
+#[tokio::test]
+async fn offsets_near_i64_max_are_rejected_instead_of_overflowing() {
+    let largest = i64::MAX - 100;
+    for framework in Framework::ALL {
+        let server = start(framework).await;
+        let product = json!({"name": "Only", "description": "", "price": 100, "inventory": 1});
+        Reply::read(server.post("/api/products").json(&product)).await.unwrap();
+
+        let reply = Reply::read(server.get(&format!("/api/products?offset={}", largest))).await.unwrap();
+        assert_eq!(reply.status, 200, "{}: {:?}", framework.name(), reply.body);
+        assert_eq!(reply.body["has_more"], false, "{}", framework.name());
+        let prev = r#"</api/products?limit=20&offset=0>; rel="prev""#;
+        assert_eq!(reply.header("link"), Some(prev), "{}", framework.name());
+
+        for offset in [largest + 1, i64::MAX] {
+            let reply = Reply::read(server.get(&format!("/api/products?offset={}", offset))).await.unwrap();
+            assert_eq!(reply.status, 400, "{}: {}", framework.name(), offset);
+            assert_eq!(
+                reply.body["error"],
+                format!("Validation error: offset must be at most {}", largest),
+                "{}",
+                framework.name()
+            );
+        }
+    }
+}
+
+/// A stale or weak `If-Match` is refused with 412 and changes nothing; a
+/// current one, `*` or none at all lets the update through.
+async fn check_if_match(server: &TestServer) {
+    let name = server.framework.name();
+    let created = Reply::read(
+        server
+            .post("/api/products")
+            .json(&json!({"name": "Vase", "description": "", "price": 900, "inventory": 2})),
+    )
+    .await
+    .unwrap();
+    let path = format!("/api/products/{}", created.body["id"].as_str().unwrap());
+    let etag = |reply: &Reply| reply.header("etag").unwrap().to_string();
+    let original = etag(&Reply::read(server.get(&path)).await.unwrap());
+
+    let patch = |if_match: Option<&str>, inventory: i64| {
+        let request = server.request(Method::PATCH, &path).json(&json!({ "inventory": inventory }));
+        Reply::read(match if_match {
+            Some(if_match) => request.header("if-match", if_match),
+            None => request,
+        })
+    };
+
+    let patched = patch(Some(&original), 3).await.unwrap();
+    assert_eq!(patched.status, 200, "{}: {:?}", name, patched.body);
+    let current = etag(&Reply::read(server.get(&path)).await.unwrap());
+    assert_ne!(current, original, "{}", name);
+
+    let stale = Reply::read(
+        server
+            .request(Method::PUT, &path)
+            .header("if-match", &original)
+            .json(&json!({"name": "Jug", "description": "", "price": 900, "inventory": 2})),
+    )
+    .await
+    .unwrap();
+    assert_eq!(stale.status, 412, "{}: {:?}", name, stale.body);
+    assert_eq!(stale.body["kind"], "PRECONDITION_FAILED", "{}", name);
+
+    let weak = patch(Some(&format!("W/{}", current)), 4).await.unwrap();
+    assert_eq!(weak.status, 412, "{}: {:?}", name, weak.body);
+
+    let unchanged = Reply::read(server.get(&path)).await.unwrap();
+    assert_eq!(unchanged.body["name"], "Vase", "{}", name);
+    assert_eq!(unchanged.body["inventory"], 3, "{}", name);
+    assert_eq!(etag(&unchanged), current, "{}", name);
+
+    assert_eq!(patch(Some(&format!("\"other\", {}", current)), 5).await.unwrap().status, 200, "{}", name);
+    assert_eq!(patch(Some("*"), 6).await.unwrap().status, 200, "{}", name);
+    assert_eq!(patch(None, 7).await.unwrap().status, 200, "{}", name);
+}
+
+#[tokio::test]
+async fn if_match_guards_updates_on_the_in_memory_store() {
+    for framework in Framework::ALL {
+        check_if_match(&start(framework).await).await;
+    }
+}
+
+#[sqlx::test(migrations = "../migrations")]
+async fn if_match_guards_updates_in_postgres(pool: PgPool) {
+    let products = postgres_products(&pool);
+    for server in TestServer::start_both(test_config(&[]), pool, products).await.unwrap() {
+        check_if_match(&server).await;
+    }
+}
+/// A catalog worth more than `i64::MAX` cents reports that instead of a 500.
+async fn check_saturated_stats(server: &TestServer) {
+    let name = server.framework.name();
+    let product = json!({"name": "Priceless", "description": "", "price": MAX_PRICE_CENTS, "inventory": i32::MAX});
+    let created = Reply::read(server.post("/api/products").json(&product)).await.unwrap();
+    assert_eq!(created.status, 200, "{}: {:?}", name, created.body);
+
+    let stats = Reply::read(server.get("/api/admin/stats").bearer_auth(server.token("admin"))).await.unwrap();
+    assert_eq!(stats.status, 200, "{}: {:?}", name, stats.body);
+    assert_eq!(stats.body["total_catalog_value_cents"], i64::MAX, "{}", name);
+
+    let reply = server
+        .graphql("{ productStats { totalCatalogValueCents } }", json!({}), Some(&server.token("admin")))
+        .await
+        .unwrap();
+    assert_eq!(reply.body["data"]["productStats"]["totalCatalogValueCents"], i64::MAX, "{}: {:?}", name, reply.body);
+}
+
+#[tokio::test]
+async fn catalog_value_saturates_on_the_in_memory_store() {
+    for framework in Framework::ALL {
+        check_saturated_stats(&start(framework).await).await;
+    }
+}
+
+#[sqlx::test(migrations = "../migrations")]
+async fn catalog_value_saturates_in_postgres(pool: PgPool) {
+    let products = postgres_products(&pool);
+    for server in TestServer::start_both(test_config(&[]), pool, products).await.unwrap() {
+        check_saturated_stats(&server).await;
+    }
+}
\ No newline at end of file
@@ -0,0 +1,32 @@
+use integration_tests::{in_memory_products, test_config, Reply, TestServer};
+use serde_json::json;
+use sqlx::PgPool;
+
+#[sqlx::test(migrations = "../migrations")]
+async fn locked_out_logins_carry_the_rate_limit_headers(pool: PgPool) {
+    let config = test_config(&[("LOGIN_MAX_FAILURES", "2"), ("LOGIN_LOCKOUT_SECS", "30")]);
+    let credentials = json!({"email": "nobody@example.com", "password": "Nobody0Password"});
+    for server in TestServer::start_both(config, pool.clone(), in_memory_products()).await.unwrap() {
+        let name = server.framework.name();
+        for _ in 0..2 {
+            let reply = Reply::read(server.post("/api/auth/login").json(&credentials)).await.unwrap();
+            assert_eq!(reply.status, 401, "{}: {:?}", name, reply.body);
+            assert_eq!(reply.header("retry-after"), None, "{}", name);
+        }
+
+        let reply = Reply::read(server.post("/api/auth/login").json(&credentials)).await.unwrap();
+        assert_eq!(reply.status, 429, "{}: {:?}", name, reply.body);
+        assert_eq!(reply.body["kind"], "RATE_LIMITED", "{}", name);
+        let retry_after: u64 = reply.header("retry-after").unwrap().parse().unwrap();
+        assert!((1..=30).contains(&retry_after), "{}: {}", name, retry_after);
+        assert_eq!(reply.header("x-ratelimit-limit"), Some("2"), "{}", name);
+        assert_eq!(reply.header("x-ratelimit-remaining"), Some("0"), "{}", name);
+        assert_eq!(reply.header("x-ratelimit-reset"), Some(retry_after.to_string().as_str()), "{}", name);
+
+        let query = "mutation { login(email: \"nobody@example.com\", password: \"Nobody0Password\") { token } }";
+        let reply = server.graphql(query, json!({}), None).await.unwrap();
+        let extensions = &reply.body["errors"][0]["extensions"];
+        assert_eq!(extensions["code"], "RATE_LIMITED", "{}: {:?}", name, reply.body);
+        assert!(extensions["retryAfter"].as_u64().is_some_and(|secs| secs <= 30), "{}: {:?}", name, reply.body);
+    }
+}
\ No newline at end of file
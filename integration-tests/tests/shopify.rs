@@ -0,0 +1,85 @@
+use std::future::IntoFuture;
+use std::sync::{Arc, Mutex};
+
+use axum::extract::State;
+use axum::http::{Method, Uri};
+use axum::{Json, Router};
+use integration_tests::{in_memory_products, postgres_products, test_config, Framework, Reply, TestServer};
+use serde_json::{json, Value};
+use shared::store::ProductStore;
+use sqlx::PgPool;
+
+const SHOPIFY_ID: i64 = 4242;
+
+type Seen = Arc<Mutex<Vec<(Method, String, Value)>>>;
+
+/// A stand-in Shopify that records each request and answers with the same
+/// product every time.
+async fn fake_shopify() -> (String, Seen) {
+    let seen = Seen::default();
+    let app = Router::new()
+        .fallback(|State(seen): State<Seen>, method: Method, uri: Uri, Json(body): Json<Value>| async move {
+            seen.lock().unwrap().push((method, uri.path().to_string(), body));
+            Json(json!({ "product": { "id": SHOPIFY_ID } }))
+        })
+        .with_state(seen.clone());
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = format!("http://{}", listener.local_addr().unwrap());
+    tokio::spawn(axum::serve(listener, app).into_future());
+    (url, seen)
+}
+
+/// Only admins may sync, and a second sync updates the Shopify product the
+/// first one created instead of creating another.
+async fn sync_twice(products: Arc<dyn ProductStore>, pool: PgPool) {
+    for framework in Framework::ALL {
+        let name = framework.name();
+        let (shop_url, seen) = fake_shopify().await;
+        let config = test_config(&[("SHOPIFY_SHOP_URL", &shop_url), ("SHOPIFY_ACCESS_TOKEN", "shpat_test")]);
+        let server = TestServer::start(framework, config, pool.clone(), products.clone()).await.unwrap();
+
+        let created = Reply::read(server.post("/api/products").json(&json!({
+            "name": "Synced", "description": "", "price": 1905, "inventory": 3,
+        })))
+        .await
+        .unwrap();
+        let path = format!("/api/products/{}", created.body["id"].as_str().unwrap());
+        let sync = format!("{}/sync-to-shopify", path);
+
+        let anonymous = Reply::read(server.post(&sync)).await.unwrap();
+        assert_eq!(anonymous.status, 401, "{}: {:?}", name, anonymous.body);
+        let user = Reply::read(server.post(&sync).bearer_auth(server.token("user"))).await.unwrap();
+        assert_eq!(user.status, 403, "{}: {:?}", name, user.body);
+        assert!(seen.lock().unwrap().is_empty(), "{}", name);
+
+        for _ in 0..2 {
+            let reply = Reply::read(server.post(&sync).bearer_auth(server.token("admin"))).await.unwrap();
+            assert_eq!(reply.status, 200, "{}: {:?}", name, reply.body);
+            assert_eq!(reply.body["shopify_product_id"], SHOPIFY_ID, "{}", name);
+        }
+
+        let seen = seen.lock().unwrap().clone();
+        let requests: Vec<_> = seen.iter().map(|(method, path, _)| (method.as_str(), path.as_str())).collect();
+        assert_eq!(
+            requests,
+            [("POST", "/admin/api/2024-07/products.json"), ("PUT", "/admin/api/2024-07/products/4242.json")],
+            "{}",
+            name
+        );
+        assert_eq!(seen[1].2["product"]["id"], SHOPIFY_ID, "{}", name);
+        assert_eq!(seen[1].2["product"]["variants"][0]["price"], "19.05", "{}", name);
+
+        let product = Reply::read(server.get(&path)).await.unwrap();
+        assert_eq!(product.body["shopify_product_id"], SHOPIFY_ID, "{}", name);
+    }
+}
+
+#[sqlx::test(migrations = "../migrations")]
+async fn resyncs_update_the_shopify_product_in_postgres(pool: PgPool) {
+    sync_twice(postgres_products(&pool), pool).await;
+}
+
+#[sqlx::test(migrations = "../migrations")]
+async fn resyncs_update_the_shopify_product_in_memory(pool: PgPool) {
+    sync_twice(in_memory_products(), pool).await;
+}
\ No newline at end of file
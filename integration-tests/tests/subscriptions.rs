@@ -0,0 +1,98 @@
+use std::time::Duration;
+
+use integration_tests::{in_memory_products, lazy_pool, test_config, Framework, Reply, Subscription, TestServer};
+use reqwest::Method;
+use serde_json::{json, Value};
+
+const PRODUCT_UPDATES: &str =
+    "subscription ($id: Id) { productUpdates(productId: $id) { id name inventory } }";
+
+async fn start(framework: Framework) -> TestServer {
+    let config = test_config(&[]);
+    let pool = lazy_pool(&config);
+    TestServer::start(framework, config, pool, in_memory_products()).await.unwrap()
+}
+
+async fn create_product(server: &TestServer, name: &str) -> String {
+    let reply = Reply::read(
+        server
+            .post("/api/products")
+            .json(&json!({"name": name, "description": "", "price": 100, "inventory": 1})),
+    )
+    .await
+    .unwrap();
+    assert_eq!(reply.status, 200, "{:?}", reply.body);
+    reply.body["id"].as_str().unwrap().to_string()
+}
+
+async fn set_inventory(server: &TestServer, id: &str, inventory: i64) {
+    let reply = Reply::read(
+        server
+            .request(Method::PATCH, &format!("/api/products/{}", id))
+            .json(&json!({ "inventory": inventory })),
+    )
+    .await
+    .unwrap();
+    assert_eq!(reply.status, 200, "{:?}", reply.body);
+}
+
+/// The server subscribes to the event bus some time after the `subscribe`
+/// message, so `id` is updated until an update comes through.
+async fn wait_until_live(server: &TestServer, subscription: &mut Subscription, id: &str) -> Value {
+    for attempt in 0..50 {
+        set_inventory(server, id, attempt).await;
+        match tokio::time::timeout(Duration::from_millis(100), subscription.next_event()).await {
+            Ok(event) => return event.unwrap(),
+            Err(_) => continue,
+        }
+    }
+    panic!("{}: the subscription never delivered an update", server.framework.name());
+}
+
+#[tokio::test]
+async fn published_product_updates_reach_subscribers() {
+    for framework in Framework::ALL {
+        let server = start(framework).await;
+        let id = create_product(&server, "Kettle").await;
+        let mut subscription = server.subscribe(PRODUCT_UPDATES, json!({ "id": null }), &[]).await.unwrap();
+
+        let event = wait_until_live(&server, &mut subscription, &id).await;
+        assert_eq!(event["data"]["productUpdates"]["id"], id.as_str(), "{}", framework.name());
+        assert_eq!(event["data"]["productUpdates"]["name"], "Kettle");
+
+        set_inventory(&server, &id, 42).await;
+        let event = subscription.next_event().await.unwrap();
+        assert_eq!(
+            event["data"]["productUpdates"],
+            json!({"id": id, "name": "Kettle", "inventory": 42}),
+            "{}",
+            framework.name()
+        );
+    }
+}
+
+#[tokio::test]
+async fn scoped_subscribers_only_see_their_product() {
+    for framework in Framework::ALL {
+        let server = start(framework).await;
+        let watched = create_product(&server, "Watched").await;
+        let other = create_product(&server, "Other").await;
+        let mut subscription = server.subscribe(PRODUCT_UPDATES, json!({ "id": watched }), &[]).await.unwrap();
+        wait_until_live(&server, &mut subscription, &watched).await;
+
+        // Events are delivered in order, so had the update of `other` got
+        // through it would arrive before the one of `watched`
+        set_inventory(&server, &other, 500).await;
+        set_inventory(&server, &watched, 7).await;
+        let event = subscription.next_event().await.unwrap();
+        assert_eq!(
+            event["data"]["productUpdates"],
+            json!({"id": watched, "name": "Watched", "inventory": 7}),
+            "{}",
+            framework.name()
+        );
+
+        set_inventory(&server, &other, 501).await;
+        assert!(subscription.is_silent_for(Duration::from_millis(300)).await, "{}", framework.name());
+    }
+}
\ No newline at end of file
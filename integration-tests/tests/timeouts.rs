@@ -0,0 +1,56 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use integration_tests::{lazy_pool, test_config, Framework, Reply, SlowProductStore, TestServer};
+use serde_json::json;
+
+async fn start(framework: Framework, vars: &[(&str, &str)], delay: Duration) -> TestServer {
+    let config = test_config(vars);
+    let pool = lazy_pool(&config);
+    TestServer::start(framework, config, pool, Arc::new(SlowProductStore::new(delay))).await.unwrap()
+}
+
+/// Answers the middleware gives up with still carry what every response
+/// does.
+fn assert_common_headers(reply: &Reply, framework: Framework) {
+    assert!(reply.header("x-request-id").is_some(), "{}: {:?}", framework.name(), reply.headers);
+    assert_eq!(reply.header("x-content-type-options"), Some("nosniff"), "{}", framework.name());
+    assert_eq!(reply.header("x-frame-options"), Some("DENY"), "{}", framework.name());
+    assert_eq!(reply.header("referrer-policy"), Some("no-referrer"), "{}", framework.name());
+    assert!(reply.header("content-security-policy").is_some(), "{}", framework.name());
+}
+
+#[tokio::test]
+async fn handlers_past_the_request_timeout_answer_504() {
+    for framework in Framework::ALL {
+        let server = start(framework, &[("REQUEST_TIMEOUT_SECS", "1")], Duration::from_secs(3)).await;
+
+        let reply = Reply::read(server.get("/api/products").header("x-request-id", "slow-1")).await.unwrap();
+        assert_eq!(reply.status, 504, "{}", framework.name());
+        assert_eq!(
+            reply.body,
+            json!({"error": "Request timed out", "kind": "TIMEOUT"}),
+            "{}",
+            framework.name()
+        );
+        assert_common_headers(&reply, framework);
+        assert_eq!(reply.header("x-request-id"), Some("slow-1"), "{}", framework.name());
+    }
+}
+
+#[tokio::test]
+async fn requests_past_the_concurrency_limit_answer_503() {
+    for framework in Framework::ALL {
+        let server = start(framework, &[("MAX_CONCURRENT_REQUESTS", "1")], Duration::from_millis(500)).await;
+
+        let (first, second) = tokio::join!(Reply::read(server.get("/api/products")), async {
+            tokio::time::sleep(Duration::from_millis(100)).await;
+            Reply::read(server.get("/api/products")).await
+        });
+        assert_eq!(first.unwrap().status, 200, "{}", framework.name());
+        let shed = second.unwrap();
+        assert_eq!(shed.status, 503, "{}: {:?}", framework.name(), shed.body);
+        assert_eq!(shed.body["kind"], "OVERLOADED", "{}: {:?}", framework.name(), shed.body);
+        assert_common_headers(&shed, framework);
+    }
+}
\ No newline at end of file
@@ -0,0 +1,108 @@
+use chrono::{Duration, Utc};
+use integration_tests::{in_memory_products, test_config, Reply, TestServer};
+use serde_json::{json, Value};
+use shared::id::Id;
+use sqlx::PgPool;
+
+const USERS_PAGE: &str = "query ($after: String) {
+    users(first: 2, after: $after) { edges { node { username } } pageInfo { endCursor hasNextPage } }
+}";
+
+async fn register(server: &TestServer, username: &str) {
+    let reply = Reply::read(server.post("/api/auth/register").json(&json!({
+        "username": username,
+        "email": format!("{}@example.com", username),
+        "password": "Cursor0Password",
+    })))
+    .await
+    .unwrap();
+    assert_eq!(reply.status, 200, "{}: {:?}", server.framework.name(), reply.body);
+}
+
+/// The usernames on the page after `after`, and the cursor that follows it.
+async fn users_page(server: &TestServer, token: &str, after: Option<&str>) -> (Vec<String>, Option<String>) {
+    let reply = server.graphql(USERS_PAGE, json!({ "after": after }), Some(token)).await.unwrap();
+    assert!(reply.body.get("errors").is_none(), "{}: {:?}", server.framework.name(), reply.body);
+    let users = &reply.body["data"]["users"];
+    let usernames = users["edges"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|edge| edge["node"]["username"].as_str().unwrap().to_string())
+        .collect();
+    let next = match users["pageInfo"]["hasNextPage"] {
+        Value::Bool(true) => users["pageInfo"]["endCursor"].as_str().map(str::to_string),
+        _ => None,
+    };
+    (usernames, next)
+}
+
+#[sqlx::test(migrations = "../migrations")]
+async fn user_cursors_hold_when_users_are_inserted_mid_pagination(pool: PgPool) {
+    let config = test_config(&[]);
+    for server in TestServer::start_both(config, pool.clone(), in_memory_products()).await.unwrap() {
+        sqlx::query("TRUNCATE users CASCADE").execute(&pool).await.unwrap();
+        let token = server.token("user");
+        for username in ["ada", "brian", "carol"] {
+            register(&server, username).await;
+        }
+
+        let (first, after) = users_page(&server, &token, None).await;
+        assert_eq!(first, ["ada", "brian"], "{}", server.framework.name());
+
+        // One signup lands after the cursor, and one backdated row before
+        // it, which would shift an offset-based page by one
+        register(&server, "dana").await;
+        sqlx::query(
+            "INSERT INTO users (id, username, email, password_hash, created_at, updated_at)
+             VALUES ($1, 'imported', 'imported@example.com', 'x', $2, $2)",
+        )
+        .bind(Id::new())
+        .bind(Utc::now() - Duration::days(1))
+        .execute(&pool)
+        .await
+        .unwrap();
+
+        let (second, after) = users_page(&server, &token, after.as_deref()).await;
+        assert_eq!(second, ["carol", "dana"], "{}", server.framework.name());
+        assert_eq!(after, None, "{}: dana is the last user", server.framework.name());
+    }
+}
+
+#[sqlx::test(migrations = "../migrations")]
+async fn looking_up_users_requires_a_token(pool: PgPool) {
+    let config = test_config(&[]);
+    for server in TestServer::start_both(config, pool.clone(), in_memory_products()).await.unwrap() {
+        let path = format!("/api/users/{}", Id::new());
+        let anonymous = Reply::read(server.get(&path)).await.unwrap();
+        assert_eq!(anonymous.status, 401, "{}", server.framework.name());
+
+        let signed_in = Reply::read(server.get(&path).bearer_auth(server.token("user"))).await.unwrap();
+        assert_eq!(signed_in.status, 404, "{}", server.framework.name());
+
+        let malformed = server.get("/api/users/not-an-id").bearer_auth(server.token("user"));
+        let malformed = Reply::read(malformed).await.unwrap();
+        assert_eq!(malformed.status, 400, "{}", server.framework.name());
+        assert_eq!(malformed.body["error"], "Validation error: invalid user id", "{}", server.framework.name());
+
+        let query = "query ($id: Id!) { user(id: $id) { id } }";
+        let reply = server.graphql(query, json!({ "id": Id::new() }), None).await.unwrap();
+        assert_eq!(
+            reply.body["errors"][0]["extensions"]["code"],
+            "UNAUTHENTICATED",
+            "{}: {:?}",
+            server.framework.name(),
+            reply.body
+        );
+    }
+}
+#[sqlx::test(migrations = "../migrations")]
+async fn revoking_sessions_of_a_malformed_user_id_is_a_validation_error(pool: PgPool) {
+    let config = test_config(&[]);
+    for server in TestServer::start_both(config, pool.clone(), in_memory_products()).await.unwrap() {
+        let revoke = server.post("/api/admin/users/not-an-id/revoke-sessions").bearer_auth(server.token("admin"));
+        let reply = Reply::read(revoke).await.unwrap();
+        assert_eq!(reply.status, 400, "{}: {:?}", server.framework.name(), reply.body);
+        assert_eq!(reply.body["error"], "Validation error: invalid user id", "{}", server.framework.name());
+    }
+}
\ No newline at end of file
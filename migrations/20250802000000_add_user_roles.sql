@@ -0,0 +1,3 @@
+-- Admins are promoted by hand, e.g.
+-- UPDATE users SET role = 'admin' WHERE email = 'you@example.com';
+ALTER TABLE users ADD COLUMN role TEXT NOT NULL DEFAULT 'user';
@@ -0,0 +1,11 @@
+CREATE TABLE shopify_webhooks (
+    id UUID PRIMARY KEY,
+    -- X-Shopify-Webhook-Id; redeliveries reuse it, so it deduplicates them
+    shopify_webhook_id TEXT NOT NULL UNIQUE,
+    webhook_type TEXT NOT NULL,
+    payload JSONB NOT NULL,
+    processed BOOLEAN NOT NULL DEFAULT FALSE,
+    created_at TIMESTAMPTZ NOT NULL
+);
+
+CREATE INDEX shopify_webhooks_created_at_idx ON shopify_webhooks (created_at, id);
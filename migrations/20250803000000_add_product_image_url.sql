@@ -0,0 +1,2 @@
+-- Set by clients after uploading through POST /api/products/{id}/image-upload-url
+ALTER TABLE products ADD COLUMN image_url TEXT;
\ No newline at end of file
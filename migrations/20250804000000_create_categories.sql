@@ -0,0 +1,14 @@
+CREATE TABLE categories (
+    id UUID PRIMARY KEY,
+    name TEXT NOT NULL UNIQUE,
+    created_at TIMESTAMPTZ NOT NULL
+);
+
+CREATE TABLE product_categories (
+    product_id UUID NOT NULL REFERENCES products (id) ON DELETE CASCADE,
+    category_id UUID NOT NULL REFERENCES categories (id) ON DELETE CASCADE,
+    PRIMARY KEY (product_id, category_id)
+);
+
+-- The primary key serves lookups by product; this one filters by category
+CREATE INDEX product_categories_category_id_idx ON product_categories (category_id, product_id);
\ No newline at end of file
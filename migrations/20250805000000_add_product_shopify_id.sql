@@ -0,0 +1,2 @@
+-- Set by the first POST /api/products/{id}/sync-to-shopify; later syncs update that Shopify product
+ALTER TABLE products ADD COLUMN shopify_product_id BIGINT;
\ No newline at end of file
@@ -0,0 +1,72 @@
+use anyhow::Result;
+use clap::Parser;
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::{Rng, SeedableRng};
+use shared::{
+    db::{connect_pool, PoolSettings, QueryTimer},
+    id::{init_id_kind, IdKind},
+    models::{CreateProduct, ProductFilter},
+    repository::ProductRepository,
+    telemetry::{init_tracing, LogFormat},
+};
+use tracing::info;
+
+const ADJECTIVES: &[&str] = &[
+    "Awesome", "Rustic", "Sleek", "Vintage", "Compact", "Ergonomic", "Durable", "Handmade",
+];
+const NOUNS: &[&str] = &[
+    "Chair", "Lamp", "Backpack", "Mug", "Keyboard", "Notebook", "Jacket", "Speaker",
+];
+
+#[derive(Parser)]
+#[command(name = "seed")]
+#[command(about = "Fill the products table with randomized demo data")]
+struct Cli {
+    /// Number of products to insert
+    #[arg(short, long, default_value_t = 1000)]
+    count: usize,
+    /// RNG seed, so repeated runs generate the same catalog
+    #[arg(long, default_value_t = 42)]
+    seed: u64,
+    /// Insert even if the products table already has rows
+    #[arg(long)]
+    force: bool,
+}
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    init_tracing("seed=info,shared=info", LogFormat::from_env());
+    init_id_kind(IdKind::from_env());
+
+    let cli = Cli::parse();
+    let settings = PoolSettings::from_env()?;
+    let pool = connect_pool(&settings).await?;
+    let products = ProductRepository::new(pool, QueryTimer::from_settings(&settings));
+
+    let existing = products.count(&ProductFilter::default()).await?;
+    if existing > 0 && !cli.force {
+        info!("Products table already has {} rows, skipping (use --force to seed anyway)", existing);
+        return Ok(());
+    }
+
+    let mut rng = StdRng::seed_from_u64(cli.seed);
+    for _ in 0..cli.count {
+        products.create(random_product(&mut rng)).await?;
+    }
+
+    info!("Inserted {} products (seed {})", cli.count, cli.seed);
+    Ok(())
+}
+
+fn random_product(rng: &mut StdRng) -> CreateProduct {
+    let adjective = ADJECTIVES.choose(rng).copied().unwrap_or("Plain");
+    let noun = NOUNS.choose(rng).copied().unwrap_or("Thing");
+
+    CreateProduct {
+        name: format!("{} {}", adjective, noun),
+        description: format!("A {} {} for everyday use", adjective.to_lowercase(), noun.to_lowercase()),
+        price: rng.gen_range(100..100_000),
+        inventory: rng.gen_range(0..500),
+    }
+}
\ No newline at end of file
@@ -1,25 +1,216 @@
-use anyhow::Result;
+use anyhow::{anyhow, Context, Result};
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
 use bcrypt::{hash, verify, DEFAULT_COST};
 use chrono::{Duration, Utc};
-use jsonwebtoken::{decode, encode, DecodingKey, EncodingKey, Header, TokenData, Validation};
+use jsonwebtoken::{decode, decode_header, encode, Algorithm, DecodingKey, EncodingKey, Header, TokenData, Validation};
+use rsa::pkcs1::DecodeRsaPrivateKey;
+use rsa::pkcs8::DecodePrivateKey;
+use rsa::traits::PublicKeyParts;
+use rsa::RsaPrivateKey;
 use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+use std::net::IpAddr;
+use std::sync::Arc;
+use tokio::sync::OnceCell;
 use uuid::Uuid;
 
-const JWT_SECRET: &str = "your-secret-key-here";
+use crate::config::EnvReader;
+use crate::error::{check_fields, AppError, AppResult, FieldError};
+use crate::id::Id;
+use crate::models::{CreateUser, LoginRequest, User, ADMIN_ROLE};
+use crate::repository::UserRepository;
+use crate::revocation::TokenRevocations;
+use crate::telemetry::{in_phase, in_phase_sync};
+use crate::throttle::LoginThrottle;
+use crate::validate::Validate;
 
-#[derive(Debug, Serialize, Deserialize)]
+/// Signs HS256 tokens only when `JWT_DEV_KEY=true` and no key is configured.
+const DEVELOPMENT_JWT_SECRET: &str = "your-secret-key-here";
+const DEFAULT_JWT_ISSUER: &str = "axum-actixweb-demo";
+const DEFAULT_JWT_AUDIENCE: &str = "axum-actixweb-demo";
+
+/// One HMAC secret and the `kid` it is advertised under in token headers.
+#[derive(Clone, Deserialize)]
+pub struct JwtKey {
+    pub kid: String,
+    pub secret: String,
+}
+
+/// An RSA private key that signs tokens with RS256, with its public half
+/// ready to publish as a JWK.
+#[derive(Clone)]
+pub struct RsaSigningKey {
+    /// RFC 7638 thumbprint of the public key, so it changes with the key.
+    pub kid: String,
+    encoding: EncodingKey,
+    decoding: DecodingKey,
+    jwk: Value,
+}
+
+impl RsaSigningKey {
+    /// Parses a PKCS#1 (`BEGIN RSA PRIVATE KEY`) or PKCS#8 (`BEGIN PRIVATE
+    /// KEY`) PEM.
+    pub fn from_pem(pem: &str) -> Result<Self> {
+        let private_key = RsaPrivateKey::from_pkcs8_pem(pem)
+            .or_else(|_| RsaPrivateKey::from_pkcs1_pem(pem))
+            .map_err(|_| anyhow!("not a PKCS#1 or PKCS#8 PEM RSA private key"))?;
+        let n = URL_SAFE_NO_PAD.encode(private_key.n().to_bytes_be());
+        let e = URL_SAFE_NO_PAD.encode(private_key.e().to_bytes_be());
+        // The thumbprint hashes the required members in this exact order
+        let thumbprint = Sha256::digest(format!(r#"{{"e":"{}","kty":"RSA","n":"{}"}}"#, e, n));
+        let kid = URL_SAFE_NO_PAD.encode(thumbprint);
+        Ok(Self {
+            encoding: EncodingKey::from_rsa_pem(pem.as_bytes())?,
+            decoding: DecodingKey::from_rsa_components(&n, &e)?,
+            jwk: json!({ "kty": "RSA", "use": "sig", "alg": "RS256", "kid": kid, "n": n, "e": e }),
+            kid,
+        })
+    }
+
+    /// `RSA_PRIVATE_KEY` is either the PEM itself or the path of a PEM file.
+    fn load(value: &str) -> Result<Self> {
+        if value.trim_start().starts_with("-----BEGIN") {
+            return Self::from_pem(value);
+        }
+        let pem = std::fs::read_to_string(value).with_context(|| format!("failed to read {}", value))?;
+        Self::from_pem(&pem).with_context(|| value.to_string())
+    }
+}
+
+/// Signing and verification keys. The first key signs new tokens; the rest
+/// are kept only so tokens issued before a rotation keep validating.
+/// Tokens are minted with, and must carry, the configured `iss` and `aud`,
+/// so a token issued for another service is not accepted here.
+///
+/// With an RSA key configured, new tokens are signed with it using RS256
+/// instead, and the HMAC keys only validate tokens issued before the switch.
+#[derive(Clone)]
+pub struct JwtKeys {
+    keys: Vec<JwtKey>,
+    rsa: Option<RsaSigningKey>,
+    pub issuer: String,
+    pub audience: String,
+}
+
+impl JwtKeys {
+    pub fn new(keys: Vec<JwtKey>, issuer: String, audience: String) -> AppResult<Self> {
+        if keys.is_empty() {
+            return Err(AppError::Validation("at least one JWT key is required".to_string()));
+        }
+        Ok(Self { keys, rsa: None, issuer, audience })
+    }
+
+    /// Reads `JWT_KEYS`, a JSON array of `{"kid", "secret"}` objects with the
+    /// current key first. `RSA_PRIVATE_KEY` switches signing to RS256, and
+    /// HS256 tokens are then only accepted for keys in `JWT_KEYS`. Without
+    /// either, `JWT_DEV_KEY=true` falls back to a built-in development key,
+    /// which is refused otherwise. `JWT_ISSUER` and `JWT_AUDIENCE` set the
+    /// expected claims.
+    pub fn from_env() -> AppResult<Self> {
+        EnvReader::load(Self::read)
+    }
+
+    pub fn read(env: &mut EnvReader) -> Self {
+        let issuer = env.var("JWT_ISSUER", DEFAULT_JWT_ISSUER.to_string());
+        let audience = env.var("JWT_AUDIENCE", DEFAULT_JWT_AUDIENCE.to_string());
+        env.check(!issuer.is_empty(), "JWT_ISSUER must not be empty");
+        env.check(!audience.is_empty(), "JWT_AUDIENCE must not be empty");
+
+        let dev_key = env.var("JWT_DEV_KEY", false);
+        let configured_keys = env.value("JWT_KEYS").map(|value| match serde_json::from_str::<Vec<JwtKey>>(&value) {
+            Ok(keys) if !keys.is_empty() => keys,
+            Ok(_) => {
+                env.check(false, "JWT_KEYS must contain at least one key");
+                Vec::new()
+            }
+            Err(e) => {
+                env.check(false, format!("JWT_KEYS is not a valid key list: {}", e));
+                Vec::new()
+            }
+        });
+        let rsa_private_key = env.var("RSA_PRIVATE_KEY", String::new());
+        let rsa_configured = !rsa_private_key.is_empty();
+        let rsa = Some(rsa_private_key)
+            .filter(|value| !value.is_empty())
+            .and_then(|value| match RsaSigningKey::load(&value) {
+                Ok(rsa) => Some(rsa),
+                Err(e) => {
+                    env.check(false, format!("RSA_PRIVATE_KEY is not usable: {:#}", e));
+                    None
+                }
+            });
+        let keys = match configured_keys {
+            Some(keys) => keys,
+            None if rsa_configured => Vec::new(),
+            None => {
+                env.check(
+                    dev_key,
+                    "JWT_KEYS or RSA_PRIVATE_KEY must be set; JWT_DEV_KEY=true uses a built-in development key",
+                );
+                vec![JwtKey { kid: "default".to_string(), secret: DEVELOPMENT_JWT_SECRET.to_string() }]
+            }
+        };
+        Self { keys, rsa, issuer, audience }
+    }
+
+    /// `RS256` with an RSA key configured, otherwise `HS256`.
+    pub fn algorithm(&self) -> &'static str {
+        if self.rsa.is_some() {
+            "RS256"
+        } else {
+            "HS256"
+        }
+    }
+
+    /// The JWK Set other services verify our tokens with. HMAC secrets are
+    /// never published, so without an RSA key the set is empty.
+    pub fn jwks(&self) -> Value {
+        json!({ "keys": self.rsa.iter().map(|rsa| &rsa.jwk).collect::<Vec<_>>() })
+    }
+
+    /// The HMAC key new HS256 tokens are signed with; `None` when only the
+    /// RSA key is configured.
+    pub fn current(&self) -> Option<&JwtKey> {
+        self.keys.first()
+    }
+
+    pub fn find(&self, kid: &str) -> Option<&JwtKey> {
+        self.keys.iter().find(|key| key.kid == kid)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Claims {
     pub sub: String, // User ID
     pub username: String,
     pub email: String,
     pub exp: i64,
     pub iat: i64,
+    /// Unique token id, used to revoke a single token on logout.
+    pub jti: String,
+    /// Tokens issued before roles existed carry none and count as `user`.
+    #[serde(default = "default_role")]
+    pub role: String,
+    /// Issuer and audience, filled in from `JwtKeys` by `create_jwt`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub iss: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub aud: Option<String>,
 }
 
+fn default_role() -> String {
+    "user".to_string()
+}
+
+/// How long an issued token stays valid.
+pub const TOKEN_LIFETIME: Duration = Duration::hours(24);
+
 impl Claims {
-    pub fn new(user_id: Uuid, username: String, email: String) -> Self {
+    pub fn new(user_id: Id, username: String, email: String, role: String) -> Self {
         let now = Utc::now();
-        let expires_at = now + Duration::hours(24);
+        let expires_at = now + TOKEN_LIFETIME;
 
         Self {
             sub: user_id.to_string(),
@@ -27,34 +218,426 @@ impl Claims {
             email,
             exp: expires_at.timestamp(),
             iat: now.timestamp(),
+            jti: Uuid::new_v4().to_string(),
+            role,
+            iss: None,
+            aud: None,
         }
     }
 }
 
-pub fn hash_password(password: &str) -> Result<String> {
-    let hashed = hash(password, DEFAULT_COST)?;
-    Ok(hashed)
+/// Rejects passwords shorter than `min_length` characters or missing a
+/// lowercase letter, an uppercase letter or a digit.
+pub fn validate_password_strength(password: &str, min_length: usize) -> AppResult<()> {
+    match password_problem(password, min_length) {
+        Some(reason) => Err(AppError::Validation(reason)),
+        None => Ok(()),
+    }
+}
+
+fn password_problem(password: &str, min_length: usize) -> Option<String> {
+    if password.chars().count() < min_length {
+        Some(format!("password must be at least {} characters long", min_length))
+    } else if !password.chars().any(|c| c.is_lowercase()) {
+        Some("password must contain a lowercase letter".to_string())
+    } else if !password.chars().any(|c| c.is_uppercase()) {
+        Some("password must contain an uppercase letter".to_string())
+    } else if !password.chars().any(|c| c.is_ascii_digit()) {
+        Some("password must contain a digit".to_string())
+    } else {
+        None
+    }
+}
+
+fn registration_field_errors(input: &CreateUser) -> Vec<FieldError> {
+    let mut errors = Vec::new();
+    if input.username.trim().is_empty() {
+        errors.push(FieldError::new("username", "username must not be empty"));
+    }
+    if normalize_email(&input.email).is_err() {
+        errors.push(FieldError::new("email", "invalid email address"));
+    }
+    errors
+}
+
+impl Validate for CreateUser {
+    /// A username and a well-formed email. How strong the password must be
+    /// depends on `PASSWORD_MIN_LENGTH`, so `validate_registration` checks it.
+    fn validate(&self) -> AppResult<()> {
+        check_fields(registration_field_errors(self))
+    }
+}
+
+impl Validate for LoginRequest {
+    /// Only the shape; whether the credentials are right is up to `LoginService`.
+    fn validate(&self) -> AppResult<()> {
+        let mut errors = Vec::new();
+        if normalize_email(&self.email).is_err() {
+            errors.push(FieldError::new("email", "invalid email address"));
+        }
+        if self.password.is_empty() {
+            errors.push(FieldError::new("password", "password must not be empty"));
+        }
+        check_fields(errors)
+    }
+}
+
+/// `CreateUser::validate` plus the password strength, all fields checked at
+/// once; returns the normalized email.
+pub fn validate_registration(input: &CreateUser, min_password_length: usize) -> AppResult<String> {
+    let mut errors = registration_field_errors(input);
+    if let Some(reason) = password_problem(&input.password, min_password_length) {
+        errors.push(FieldError::new("password", reason));
+    }
+    check_fields(errors)?;
+    normalize_email(&input.email)
+}
+
+/// A deliberately small structural check: one `@`, a non-empty local part
+/// and a dotted domain made of alphanumeric or `-` labels.
+pub fn is_valid_email(email: &str) -> bool {
+    let Some((local, domain)) = email.split_once('@') else {
+        return false;
+    };
+
+    !local.is_empty()
+        && !local.chars().any(|c| c.is_whitespace() || c == '@')
+        && domain.contains('.')
+        && domain.split('.').all(|label| {
+            !label.is_empty()
+                && !label.starts_with('-')
+                && !label.ends_with('-')
+                && label.chars().all(|c| c.is_ascii_alphanumeric() || c == '-')
+        })
+}
+
+/// Trims and lowercases an email so storage and lookups agree, rejecting
+/// anything that doesn't pass `is_valid_email`.
+pub fn normalize_email(email: &str) -> AppResult<String> {
+    let email = email.trim().to_lowercase();
+    if !is_valid_email(&email) {
+        return Err(AppError::Validation("invalid email address".to_string()));
+    }
+    Ok(email)
+}
+
+pub async fn hash_password(password: &str) -> AppResult<String> {
+    hash_password_with_cost(password, DEFAULT_COST).await
+}
+
+/// Each step of `cost` doubles hashing time. Low costs are fine for tests
+/// and local runs but make stolen hashes much cheaper to brute-force.
+pub async fn hash_password_with_cost(password: &str, cost: u32) -> AppResult<String> {
+    let password = password.to_string();
+    in_phase("password", run_bcrypt(move || hash(password, cost))).await
 }
 
-pub fn verify_password(password: &str, hash: &str) -> Result<bool> {
-    let is_valid = verify(password, hash)?;
-    Ok(is_valid)
+/// Runs a bcrypt call on the blocking thread pool: at production costs one
+/// takes long enough to stall every request sharing its executor thread.
+async fn run_bcrypt<T: Send + 'static>(
+    f: impl FnOnce() -> Result<T, bcrypt::BcryptError> + Send + 'static,
+) -> AppResult<T> {
+    tokio::task::spawn_blocking(f)
+        .await
+        .map_err(|e| AppError::Internal(anyhow!("bcrypt task failed: {}", e)))?
+        .map_err(AppError::from)
 }
 
-pub fn create_jwt(claims: &Claims) -> Result<String> {
-    let token = encode(
-        &Header::default(),
-        claims,
-        &EncodingKey::from_secret(JWT_SECRET.as_ref()),
-    )?;
+pub async fn verify_password(password: &str, hash: &str) -> AppResult<bool> {
+    let (password, hash) = (password.to_string(), hash.to_string());
+    in_phase("password", run_bcrypt(move || verify(password, &hash))).await
+}
+
+/// Signs `claims` with the RSA key if there is one, otherwise the current
+/// HMAC key, stamped with the configured issuer and audience.
+pub fn create_jwt(keys: &JwtKeys, claims: &Claims) -> Result<String> {
+    let claims = Claims {
+        iss: Some(keys.issuer.clone()),
+        aud: Some(keys.audience.clone()),
+        ..claims.clone()
+    };
+    if let Some(rsa) = &keys.rsa {
+        let header = Header {
+            kid: Some(rsa.kid.clone()),
+            ..Header::new(Algorithm::RS256)
+        };
+        return Ok(encode(&header, &claims, &rsa.encoding)?);
+    }
+
+    let key = keys.current().ok_or_else(|| anyhow!("no JWT signing key configured"))?;
+    let header = Header {
+        kid: Some(key.kid.clone()),
+        ..Header::default()
+    };
+    let token = encode(&header, &claims, &EncodingKey::from_secret(key.secret.as_ref()))?;
     Ok(token)
 }
 
-pub fn validate_jwt(token: &str) -> Result<TokenData<Claims>> {
-    let token_data = decode::<Claims>(
+/// Verifies with the key named by the token's `kid`: the RSA key for RS256
+/// tokens, an HMAC key for HS256 ones. HS256 tokens without a `kid` predate
+/// key rotation and are tried against every HMAC key. `iss` and `aud` must
+/// be present and match the configured values.
+pub fn validate_jwt(keys: &JwtKeys, token: &str) -> Result<TokenData<Claims>> {
+    let header = decode_header(token)?;
+    let validation = |algorithm| {
+        let mut validation = Validation::new(algorithm);
+        validation.set_issuer(&[&keys.issuer]);
+        validation.set_audience(&[&keys.audience]);
+        validation.set_required_spec_claims(&["exp", "iss", "aud"]);
+        validation
+    };
+
+    if header.alg == Algorithm::RS256 {
+        let rsa = keys
+            .rsa
+            .as_ref()
+            .filter(|rsa| header.kid.as_deref() == Some(rsa.kid.as_str()))
+            .ok_or_else(|| anyhow!("unknown RS256 key id: {}", header.kid.as_deref().unwrap_or("none")))?;
+        return Ok(decode::<Claims>(token, &rsa.decoding, &validation(Algorithm::RS256))?);
+    }
+
+    let validation = validation(Algorithm::HS256);
+    let candidates: Vec<&JwtKey> = match header.kid {
+        Some(kid) => vec![keys
+            .find(&kid)
+            .ok_or_else(|| anyhow!("unknown JWT key id: {}", kid))?],
+        None => keys.keys.iter().collect(),
+    };
+
+    let mut last_error = None;
+    for key in candidates {
+        match decode::<Claims>(
+            token,
+            &DecodingKey::from_secret(key.secret.as_ref()),
+            &validation,
+        ) {
+            Ok(token_data) => return Ok(token_data),
+            Err(e) => last_error = Some(e),
+        }
+    }
+    Err(last_error.map(Into::into).unwrap_or_else(|| anyhow!("no JWT keys configured")))
+}
+
+/// Resolves the claims behind an `Authorization: Bearer <token>` header or,
+/// when the request has no `Authorization` header, the token sent in the
+/// auth cookie. Missing, invalid, expired and revoked tokens are rejected
+/// alike.
+pub fn claims_from_auth_header(
+    keys: &JwtKeys,
+    revocations: &TokenRevocations,
+    header: Option<&str>,
+    cookie: Option<&str>,
+) -> AppResult<Claims> {
+    in_phase_sync("auth", || {
+        let token = match header {
+            Some(value) => value.strip_prefix("Bearer "),
+            None => cookie,
+        }
+        .ok_or_else(|| AppError::Authentication("missing bearer token".to_string()))?;
+
+        let claims = validate_jwt(keys, token)
+            .map_err(|_| AppError::Authentication("invalid or expired token".to_string()))?
+            .claims;
+
+        if revocations.is_revoked(&claims) {
+            return Err(AppError::Authentication("token has been revoked".to_string()));
+        }
+        Ok(claims)
+    })
+}
+
+/// Like `claims_from_auth_header`, but also requires the admin role.
+pub fn admin_claims_from_auth_header(
+    keys: &JwtKeys,
+    revocations: &TokenRevocations,
+    header: Option<&str>,
+    cookie: Option<&str>,
+) -> AppResult<Claims> {
+    let claims = claims_from_auth_header(keys, revocations, header, cookie)?;
+    if claims.role != ADMIN_ROLE {
+        return Err(AppError::Authorization("admin role required".to_string()));
+    }
+    Ok(claims)
+}
+
+/// Value of the cookie `name` in a `Cookie` request header.
+pub fn cookie_value<'a>(header: &'a str, name: &str) -> Option<&'a str> {
+    header
+        .split(';')
+        .filter_map(|pair| pair.trim().split_once('='))
+        .find(|(key, _)| *key == name)
+        .map(|(_, value)| value)
+}
+
+/// `Set-Cookie` value handing `token` to a browser. It is `HttpOnly` so
+/// scripts can't read it, `Secure` and `SameSite=Strict` so it only goes
+/// back over HTTPS to this site, and expires with the token.
+pub fn auth_cookie_header(name: &str, token: &str) -> String {
+    format!(
+        "{}={}; Path=/; Max-Age={}; HttpOnly; Secure; SameSite=Strict",
+        name,
         token,
-        &DecodingKey::from_secret(JWT_SECRET.as_ref()),
-        &Validation::default(),
-    )?;
-    Ok(token_data)
+        TOKEN_LIFETIME.num_seconds()
+    )
+}
+
+/// `Set-Cookie` value that removes the auth cookie on logout.
+pub fn expired_auth_cookie_header(name: &str) -> String {
+    format!("{}=; Path=/; Max-Age=0; HttpOnly; Secure; SameSite=Strict", name)
+}
+
+/// Credential check and token issuing, shared by the REST login handlers
+/// and the GraphQL `login` mutation so both apply the same throttling.
+#[derive(Clone)]
+pub struct LoginService {
+    users: UserRepository,
+    throttle: Arc<LoginThrottle>,
+    keys: Arc<JwtKeys>,
+    revocations: Arc<TokenRevocations>,
+    bcrypt_cost: u32,
+    dummy_hash: Arc<OnceCell<String>>,
+}
+
+impl LoginService {
+    pub fn new(
+        users: UserRepository,
+        throttle: Arc<LoginThrottle>,
+        keys: Arc<JwtKeys>,
+        revocations: Arc<TokenRevocations>,
+        bcrypt_cost: u32,
+    ) -> Self {
+        Self { users, throttle, keys, revocations, bcrypt_cost, dummy_hash: Arc::new(OnceCell::new()) }
+    }
+
+    /// A hash at the configured cost that unknown emails are checked
+    /// against, so they take as long to reject as a wrong password.
+    async fn dummy_hash(&self) -> AppResult<&str> {
+        let hash = self
+            .dummy_hash
+            .get_or_try_init(|| hash_password_with_cost("not-a-real-password", self.bcrypt_cost))
+            .await?;
+        Ok(hash)
+    }
+
+    /// Returns a fresh token and the user it was issued to. Unknown emails
+    /// and wrong passwords fail alike and count towards the lockout of the
+    /// email + `ip` pair.
+    pub async fn login(&self, email: &str, password: &str, ip: IpAddr) -> AppResult<(String, User)> {
+        let email = normalize_email(email)?;
+        self.throttle.check(&email, ip)?;
+
+        let verified = match self.users.find_by_email(&email).await? {
+            Some(user) => verify_password(password, &user.password_hash).await?.then_some(user),
+            None => {
+                verify_password(password, self.dummy_hash().await?).await?;
+                None
+            }
+        };
+        let Some(user) = verified else {
+            self.throttle.record_failure(&email, ip);
+            return Err(AppError::Authentication("invalid email or password".to_string()));
+        };
+        self.throttle.record_success(&email, ip);
+
+        let claims = Claims::new(user.id, user.username.clone(), user.email.clone(), user.role.clone());
+        self.revocations.record_issued(&claims);
+        let token = create_jwt(&self.keys, &claims)?;
+        Ok((token, user))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::ServerSettings;
+
+    const RSA_TEST_KEY: &str = include_str!("../testdata/jwt-rsa-test-key.pem");
+
+    fn jwt_keys(vars: &[(&str, &str)]) -> AppResult<JwtKeys> {
+        EnvReader::load_from(vars, JwtKeys::read)
+    }
+
+    fn token(keys: &JwtKeys) -> String {
+        let claims = Claims::new(Id::new(), "ada".to_string(), "ada@example.com".to_string(), "user".to_string());
+        create_jwt(keys, &claims).unwrap()
+    }
+
+    #[test]
+    fn the_development_key_needs_the_dev_flag() {
+        match jwt_keys(&[]) {
+            Err(AppError::Validation(message)) => {
+                assert!(message.contains("JWT_KEYS or RSA_PRIVATE_KEY must be set"), "{}", message)
+            }
+            other => panic!("keys without JWT_KEYS were accepted: {:?}", other.is_ok()),
+        }
+        assert!(jwt_keys(&[("JWT_DEV_KEY", "false")]).is_err());
+
+        let keys = jwt_keys(&[("JWT_DEV_KEY", "true")]).unwrap();
+        assert_eq!(keys.algorithm(), "HS256");
+        assert_eq!(keys.current().unwrap().secret, DEVELOPMENT_JWT_SECRET);
+        assert!(validate_jwt(&keys, &token(&keys)).is_ok());
+    }
+
+    #[test]
+    fn configured_keys_replace_the_development_key() {
+        let keys = jwt_keys(&[("JWT_KEYS", r#"[{"kid": "k1", "secret": "s1"}]"#), ("JWT_DEV_KEY", "true")]).unwrap();
+        assert_eq!(keys.current().unwrap().kid, "k1");
+        assert!(keys.find("default").is_none());
+    }
+
+    #[test]
+    fn rs256_mode_refuses_hs256_tokens_unless_jwt_keys_is_set() {
+        let development = jwt_keys(&[("JWT_DEV_KEY", "true")]).unwrap();
+        let rsa = jwt_keys(&[("RSA_PRIVATE_KEY", RSA_TEST_KEY), ("JWT_DEV_KEY", "true")]).unwrap();
+        assert_eq!(rsa.algorithm(), "RS256");
+        assert!(rsa.current().is_none());
+        assert!(validate_jwt(&rsa, &token(&rsa)).is_ok());
+        assert!(validate_jwt(&rsa, &token(&development)).is_err());
+
+        let hmac = r#"[{"kid": "old", "secret": "rotated-out"}]"#;
+        let previous = jwt_keys(&[("JWT_KEYS", hmac)]).unwrap();
+        let both = jwt_keys(&[("RSA_PRIVATE_KEY", RSA_TEST_KEY), ("JWT_KEYS", hmac)]).unwrap();
+        assert_eq!(both.algorithm(), "RS256");
+        assert!(validate_jwt(&both, &token(&previous)).is_ok());
+        assert!(validate_jwt(&both, &token(&development)).is_err());
+    }
+
+    fn problem(password: &str) -> Option<String> {
+        password_problem(password, 8)
+    }
+
+    #[test]
+    fn password_length_is_inclusive_and_counts_characters() {
+        assert_eq!(problem("Abcdef1"), Some("password must be at least 8 characters long".to_string()));
+        assert_eq!(problem("Abcdefg1"), None);
+        // Eight characters, though more than eight bytes
+        assert_eq!(problem("Äbcdéfg1"), None);
+        assert_eq!(password_problem("", 0), Some("password must contain a lowercase letter".to_string()));
+    }
+
+    #[test]
+    fn passwords_need_every_character_class() {
+        assert_eq!(problem("ABCDEFG1"), Some("password must contain a lowercase letter".to_string()));
+        assert_eq!(problem("abcdefg1"), Some("password must contain an uppercase letter".to_string()));
+        assert_eq!(problem("Abcdefgh"), Some("password must contain a digit".to_string()));
+        // Only ASCII digits count
+        assert_eq!(problem("Abcdefg١"), Some("password must contain a digit".to_string()));
+    }
+
+    #[test]
+    fn the_first_missing_requirement_is_reported() {
+        assert_eq!(problem("abc"), Some("password must be at least 8 characters long".to_string()));
+        assert_eq!(problem("12345678"), Some("password must contain a lowercase letter".to_string()));
+        assert!(validate_password_strength("Abcdefg1", 8).is_ok());
+        assert!(matches!(validate_password_strength("abc", 8), Err(AppError::Validation(_))));
+    }
+
+    #[tokio::test]
+    async fn hashes_use_the_configured_cost_and_verify() {
+        let settings = EnvReader::load_from(&[("BCRYPT_COST", "5")], ServerSettings::read).unwrap();
+        let hashed = hash_password_with_cost("Abcdefg1", settings.bcrypt_cost).await.unwrap();
+        assert!(hashed.starts_with("$2b$05$"), "{}", hashed);
+        assert!(verify_password("Abcdefg1", &hashed).await.unwrap());
+        assert!(!verify_password("Abcdefg2", &hashed).await.unwrap());
+    }
 }
\ No newline at end of file
@@ -0,0 +1,32 @@
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+
+/// What is running, served at `GET /version`. Everything but `framework`
+/// and `version` is captured by `build.rs` when the shared crate is built.
+#[derive(Debug, Clone, Serialize)]
+pub struct BuildInfo {
+    pub framework: &'static str,
+    /// The server crate's version; pass `env!("CARGO_PKG_VERSION")`.
+    pub version: &'static str,
+    /// Short commit hash, or `unknown` outside a git checkout.
+    pub git_sha: &'static str,
+    pub rustc_version: &'static str,
+    #[serde(with = "crate::timestamp::option")]
+    pub built_at: Option<DateTime<Utc>>,
+}
+
+impl BuildInfo {
+    pub fn new(framework: &'static str, version: &'static str) -> Self {
+        let built_at = env!("BUILD_TIMESTAMP")
+            .parse()
+            .ok()
+            .and_then(|secs| DateTime::from_timestamp(secs, 0));
+        Self {
+            framework,
+            version,
+            git_sha: env!("GIT_SHA"),
+            rustc_version: env!("RUSTC_VERSION"),
+            built_at,
+        }
+    }
+}
\ No newline at end of file
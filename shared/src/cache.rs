@@ -0,0 +1,110 @@
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use tokio::sync::OnceCell;
+
+use crate::error::AppResult;
+use crate::id::Id;
+use crate::models::Product;
+
+/// Expired entries are swept out only once the map holds this many, so a
+/// miss doesn't walk the whole map.
+const SWEEP_THRESHOLD: usize = 1_000;
+
+/// Short-lived single-flight cache for product reads by id. Concurrent
+/// lookups of the same id share one load; its result is then served until
+/// the TTL runs out or a write invalidates the entry. Failed loads are not
+/// cached, the next caller retries.
+pub struct ProductReadCache {
+    ttl: Duration,
+    entries: Mutex<Entries>,
+}
+
+struct Entries {
+    by_id: HashMap<Id, CacheEntry>,
+    /// The size that triggers the next sweep: twice what the last one left,
+    /// so a map full of live entries isn't walked again on every miss.
+    sweep_at: usize,
+}
+
+struct CacheEntry {
+    started: Instant,
+    cell: Arc<OnceCell<Option<Product>>>,
+}
+
+impl ProductReadCache {
+    /// A zero `ttl` disables the cache; every lookup then loads.
+    pub fn new(ttl: Duration) -> Self {
+        Self {
+            ttl,
+            entries: Mutex::new(Entries { by_id: HashMap::new(), sweep_at: SWEEP_THRESHOLD }),
+        }
+    }
+
+    pub async fn get_or_load<F>(&self, id: Id, load: F) -> AppResult<Option<Product>>
+    where
+        F: Future<Output = AppResult<Option<Product>>>,
+    {
+        if self.ttl.is_zero() {
+            return load.await;
+        }
+        self.cell(id).get_or_try_init(|| load).await.cloned()
+    }
+
+    /// The entry lookups of `id` should wait on, replacing an expired one.
+    fn cell(&self, id: Id) -> Arc<OnceCell<Option<Product>>> {
+        let now = Instant::now();
+        let mut entries = self.entries.lock().unwrap();
+        if let Some(entry) = entries.by_id.get(&id) {
+            if now.duration_since(entry.started) < self.ttl {
+                return entry.cell.clone();
+            }
+        }
+        if entries.by_id.len() >= entries.sweep_at {
+            entries.by_id.retain(|_, entry| now.duration_since(entry.started) < self.ttl);
+            entries.sweep_at = (entries.by_id.len() * 2).max(SWEEP_THRESHOLD);
+        }
+        let cell = Arc::new(OnceCell::new());
+        entries.by_id.insert(id, CacheEntry { started: now, cell: cell.clone() });
+        cell
+    }
+
+    /// Drops the entry for `id`, so lookups after a write load it afresh.
+    pub fn invalidate(&self, id: Id) {
+        self.entries.lock().unwrap().by_id.remove(&id);
+    }
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn expired_entries_are_swept_once_the_map_is_full() {
+        let cache = ProductReadCache::new(Duration::from_millis(500));
+        for _ in 0..SWEEP_THRESHOLD - 1 {
+            cache.cell(Id::new());
+        }
+        std::thread::sleep(Duration::from_millis(600));
+        cache.cell(Id::new());
+        assert_eq!(cache.entries.lock().unwrap().by_id.len(), SWEEP_THRESHOLD);
+
+        // Only the entry added after the sleep is still live
+        cache.cell(Id::new());
+        let entries = cache.entries.lock().unwrap();
+        assert_eq!(entries.by_id.len(), 2);
+        assert_eq!(entries.sweep_at, SWEEP_THRESHOLD);
+    }
+
+    #[test]
+    fn live_entries_push_the_next_sweep_out() {
+        let cache = ProductReadCache::new(Duration::from_secs(60));
+        for _ in 0..=SWEEP_THRESHOLD {
+            cache.cell(Id::new());
+        }
+        let entries = cache.entries.lock().unwrap();
+        assert_eq!(entries.by_id.len(), SWEEP_THRESHOLD + 1);
+        assert_eq!(entries.sweep_at, 2 * SWEEP_THRESHOLD);
+    }
+}
\ No newline at end of file
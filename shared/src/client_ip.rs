@@ -0,0 +1,126 @@
+use std::net::IpAddr;
+use std::str::FromStr;
+
+/// Address of the client that made the request: the socket peer, or the
+/// address a trusted proxy forwarded for it. Middleware stores it as a
+/// request extension for login throttling and request logging.
+#[derive(Clone, Copy, Debug)]
+pub struct ClientIp(pub IpAddr);
+
+/// Proxies whose `X-Forwarded-For` and `X-Real-IP` headers are believed,
+/// parsed from a comma separated list of addresses and CIDR ranges such as
+/// `10.0.0.0/8,::1`. Empty by default, so forwarding headers are ignored.
+#[derive(Clone, Debug, Default)]
+pub struct TrustedProxies {
+    networks: Vec<IpNetwork>,
+}
+
+#[derive(Clone, Copy, Debug)]
+struct IpNetwork {
+    address: IpAddr,
+    prefix: u32,
+}
+
+impl IpNetwork {
+    fn contains(&self, ip: IpAddr) -> bool {
+        match (self.address, canonical(ip)) {
+            (IpAddr::V4(network), IpAddr::V4(ip)) => {
+                prefix_matches(u32::from(network).into(), u32::from(ip).into(), self.prefix, 32)
+            }
+            (IpAddr::V6(network), IpAddr::V6(ip)) => {
+                prefix_matches(u128::from(network), u128::from(ip), self.prefix, 128)
+            }
+            _ => false,
+        }
+    }
+}
+
+fn prefix_matches(network: u128, ip: u128, prefix: u32, bits: u32) -> bool {
+    prefix == 0 || (network ^ ip) >> (bits - prefix) == 0
+}
+
+/// IPv4-mapped IPv6 addresses (`::ffff:10.0.0.1`), as dual-stack sockets
+/// report IPv4 peers, compared as the IPv4 address they carry.
+fn canonical(ip: IpAddr) -> IpAddr {
+    match ip {
+        IpAddr::V6(v6) => v6.to_ipv4_mapped().map_or(ip, IpAddr::V4),
+        IpAddr::V4(_) => ip,
+    }
+}
+
+impl FromStr for IpNetwork {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let (address, prefix) = match value.split_once('/') {
+            Some((address, prefix)) => (address, Some(prefix)),
+            None => (value, None),
+        };
+        let address = canonical(address.parse().map_err(|_| format!("invalid address: {}", value))?);
+        let bits = if address.is_ipv4() { 32 } else { 128 };
+        let prefix = match prefix {
+            Some(prefix) => prefix
+                .parse()
+                .ok()
+                .filter(|prefix| *prefix <= bits)
+                .ok_or_else(|| format!("invalid prefix length: {}", value))?,
+            None => bits,
+        };
+        Ok(Self { address, prefix })
+    }
+}
+
+impl FromStr for TrustedProxies {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let networks = value
+            .split(',')
+            .map(str::trim)
+            .filter(|network| !network.is_empty())
+            .map(IpNetwork::from_str)
+            .collect::<Result<_, _>>()?;
+        Ok(Self { networks })
+    }
+}
+
+impl TrustedProxies {
+    pub fn len(&self) -> usize {
+        self.networks.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.networks.is_empty()
+    }
+
+    pub fn is_trusted(&self, ip: IpAddr) -> bool {
+        self.networks.iter().any(|network| network.contains(ip))
+    }
+
+    /// The client behind `peer`. Forwarding headers are only read when
+    /// `peer` is trusted; `X-Forwarded-For` is then walked from the right,
+    /// skipping further trusted hops, so a client can't spoof its address by
+    /// prepending entries. `X-Real-IP` is used when there is no
+    /// `X-Forwarded-For`.
+    pub fn client_ip(&self, peer: IpAddr, forwarded_for: Option<&str>, real_ip: Option<&str>) -> IpAddr {
+        if !self.is_trusted(peer) {
+            return peer;
+        }
+        if let Some(forwarded_for) = forwarded_for {
+            let mut client = peer;
+            for hop in forwarded_for.rsplit(',') {
+                let Ok(hop) = hop.trim().parse::<IpAddr>() else {
+                    break;
+                };
+                client = hop;
+                if !self.is_trusted(hop) {
+                    break;
+                }
+            }
+            return client;
+        }
+        real_ip
+            .and_then(|real_ip| real_ip.trim().parse().ok())
+            .unwrap_or(peer)
+    }
+}
\ No newline at end of file
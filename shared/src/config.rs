@@ -0,0 +1,378 @@
+use std::collections::HashMap;
+use std::str::FromStr;
+use std::time::Duration;
+
+use crate::auth::JwtKeys;
+use crate::client_ip::TrustedProxies;
+use crate::db::PoolSettings;
+use crate::error::{AppError, AppResult};
+use crate::graphql::GraphqlUi;
+use crate::pagination::PageLimits;
+use crate::security_headers::SecurityHeaders;
+use crate::shopify::ShopifySettings;
+use crate::storage::StorageSettings;
+use crate::id::IdKind;
+use crate::telemetry::LogFormat;
+use crate::tls::TlsSettings;
+
+/// Everything a server needs from the environment, loaded once at startup.
+#[derive(Clone)]
+pub struct Config {
+    pub host: String,
+    pub port: u16,
+    pub database: PoolSettings,
+    pub jwt_keys: JwtKeys,
+    /// Origins allowed by CORS; empty allows any origin.
+    pub cors_origins: Vec<String>,
+    pub security_headers: SecurityHeaders,
+    pub log_format: LogFormat,
+    /// Kind of new user and product ids; either kind is accepted in lookups.
+    pub id_kind: IdKind,
+    /// Certificate and key to serve HTTPS with; `None` serves plain HTTP.
+    pub tls: Option<TlsSettings>,
+    /// Largest accepted request body, in bytes.
+    pub body_limit: usize,
+    pub server: ServerSettings,
+    /// Outbound product sync; `None` when Shopify isn't configured.
+    pub shopify: Option<ShopifySettings>,
+    /// Secret used to verify inbound Shopify webhooks.
+    pub shopify_webhook_secret: Option<String>,
+    /// Bucket product images are uploaded to; `None` when S3 isn't configured.
+    pub storage: Option<StorageSettings>,
+}
+
+impl Config {
+    /// Reads the whole configuration, reporting every invalid variable in a
+    /// single `AppError::Validation` rather than stopping at the first one.
+    /// `default_port` differs per server so both can run side by side.
+    pub fn from_env(default_port: u16) -> AppResult<Self> {
+        EnvReader::load(|env| Self::read(env, default_port))
+    }
+
+    /// `from_env` over `vars` alone, so tests get the defaults whatever the
+    /// process environment holds.
+    pub fn from_vars(default_port: u16, vars: &[(&str, &str)]) -> AppResult<Self> {
+        EnvReader::load_from(vars, |env| Self::read(env, default_port))
+    }
+
+    fn read(env: &mut EnvReader, default_port: u16) -> Self {
+        let cors_origins: Vec<String> = env
+            .var("CORS_ORIGINS", String::new())
+            .split(',')
+            .map(|origin| origin.trim().to_string())
+            .filter(|origin| !origin.is_empty())
+            .collect();
+        for origin in &cors_origins {
+            env.check(
+                origin.starts_with("http://") || origin.starts_with("https://"),
+                format!("CORS_ORIGINS entry {} must start with http:// or https://", origin),
+            );
+        }
+
+        let config = Self {
+            host: env.var("HOST", "0.0.0.0".to_string()),
+            port: env.var("PORT", default_port),
+            database: PoolSettings::read(env),
+            jwt_keys: JwtKeys::read(env),
+            cors_origins,
+            security_headers: SecurityHeaders::read(env),
+            log_format: env.var("LOG_FORMAT", LogFormat::Pretty),
+            id_kind: env.var("ID_KIND", IdKind::Uuid),
+            tls: TlsSettings::read(env),
+            body_limit: env.var("BODY_LIMIT_BYTES", 2 * 1024 * 1024),
+            server: ServerSettings::read(env),
+            shopify: ShopifySettings::read(env),
+            shopify_webhook_secret: Some(env.var("SHOPIFY_WEBHOOK_SECRET", String::new()))
+                .filter(|secret| !secret.is_empty()),
+            storage: StorageSettings::read(env),
+        };
+        env.check(config.body_limit > 0, "BODY_LIMIT_BYTES must be at least 1");
+        config
+    }
+
+    pub fn bind_address(&self) -> String {
+        format!("{}:{}", self.host, self.port)
+    }
+
+    /// Logs what this instance actually loaded, in one structured event, so a
+    /// mistyped or ignored env var shows up as an unexpected value. Secrets
+    /// are left out; the database URL is logged without its password.
+    pub fn log_summary(&self, framework: &str) {
+        let cors = if self.cors_origins.is_empty() {
+            "any origin".to_string()
+        } else {
+            self.cors_origins.join(",")
+        };
+        let max_concurrent_requests = self
+            .server
+            .max_concurrent_requests
+            .map_or_else(|| "unbounded".to_string(), |max| max.to_string());
+        tracing::info!(
+            framework,
+            bind_address = %self.bind_address(),
+            tls = self.tls.is_some(),
+            id_kind = self.id_kind.name(),
+            database_url = %redact_url_password(&self.database.database_url),
+            db_max_connections = self.database.max_connections,
+            db_min_connections = self.database.min_connections,
+            cors = %cors,
+            content_security_policy = %self.security_headers.content_security_policy,
+            jwt_algorithm = self.jwt_keys.algorithm(),
+            jwt_issuer = %self.jwt_keys.issuer,
+            jwt_audience = %self.jwt_keys.audience,
+            body_limit_bytes = self.body_limit,
+            request_timeout_secs = self.server.request_timeout.as_secs(),
+            shutdown_delay_secs = self.server.shutdown_delay.as_secs(),
+            default_page_size = self.server.page_limits.default,
+            max_page_size = self.server.page_limits.max,
+            max_concurrent_requests = %max_concurrent_requests,
+            graphql_introspection = self.server.graphql_introspection,
+            graphql_tracing = self.server.graphql_tracing,
+            graphql_ui = self.server.graphql_ui.name().unwrap_or("off"),
+            run_migrations = self.server.run_migrations,
+            shopify_sync = self.shopify.is_some(),
+            shopify_webhook_verification = self.shopify_webhook_secret.is_some(),
+            low_stock_webhook = self.server.low_stock_webhook_url.is_some(),
+            image_uploads = self.storage.is_some(),
+            trusted_proxies = self.server.trusted_proxies.len(),
+            "resolved configuration"
+        );
+    }
+}
+
+/// `url` with the password of its userinfo, if any, replaced by `***`.
+fn redact_url_password(url: &str) -> String {
+    let Some((scheme, rest)) = url.split_once("://") else {
+        return url.to_string();
+    };
+    let Some((userinfo, host)) = rest.split_once('@') else {
+        return url.to_string();
+    };
+    match userinfo.split_once(':') {
+        Some((user, _)) => format!("{}://{}:***@{}", scheme, user, host),
+        None => url.to_string(),
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ServerSettings {
+    /// Handlers running longer than this are aborted with a 504.
+    pub request_timeout: Duration,
+    /// Worker threads (Tokio workers for axum, `HttpServer` workers for
+    /// actix-web). Unset or 0 keeps the framework default of one per CPU.
+    pub workers: Option<usize>,
+    /// How long shutdown waits for in-flight requests before abandoning them.
+    pub shutdown_timeout: Duration,
+    /// How long `/health` answers 503 after the signal before the listener
+    /// closes and the drain starts.
+    pub shutdown_delay: Duration,
+    /// Requests handled at once; further ones are shed with a 503. Unset or
+    /// 0 leaves concurrency unbounded.
+    pub max_concurrent_requests: Option<usize>,
+    /// Apply pending migrations from `migrations/` before serving.
+    pub run_migrations: bool,
+    /// Number of parsed queries kept for automatic persisted queries.
+    pub apq_cache_size: usize,
+    /// Deepest selection nesting a GraphQL operation may have.
+    pub graphql_max_depth: usize,
+    /// Highest complexity (roughly, fields selected) per GraphQL operation.
+    pub graphql_max_complexity: usize,
+    /// Most operations accepted in one batched GraphQL request.
+    pub graphql_max_batch_size: usize,
+    /// Largest GraphQL request, counting the query text and the variables
+    /// as JSON of every operation in it. Kept apart from `BODY_LIMIT_BYTES`,
+    /// which bulk REST imports may need to be much higher.
+    pub graphql_max_request_bytes: usize,
+    /// Complexity a signed-in user may spend per rolling minute; 0 is
+    /// unlimited.
+    pub graphql_cost_budget: u64,
+    /// Same for anonymous callers, per client address.
+    pub graphql_anonymous_cost_budget: u64,
+    /// Answer `__schema`/`__type` queries; on by default only in debug builds.
+    pub graphql_introspection: bool,
+    /// Attach per-resolver timings to every GraphQL response.
+    pub graphql_tracing: bool,
+    /// IDE served at `/graphiql`; off by default in release builds.
+    pub graphql_ui: GraphqlUi,
+    /// Consecutive failed logins before an email + IP pair is locked out.
+    pub login_max_failures: u32,
+    /// Length of the first lockout; each further lockout doubles it.
+    pub login_lockout: Duration,
+    /// Proxies allowed to report the client address in `X-Forwarded-For` or
+    /// `X-Real-IP`; requests from any other peer are attributed to the peer.
+    pub trusted_proxies: TrustedProxies,
+    /// Cookie the token is read from when a request has no `Authorization`
+    /// header, and that `POST /api/auth/login?set_cookie=true` sets.
+    pub auth_cookie_name: String,
+    /// Shortest password accepted on register.
+    pub password_min_length: usize,
+    /// bcrypt work factor used when hashing new passwords.
+    pub bcrypt_cost: u32,
+    /// Where low stock events are posted; unset disables them.
+    pub low_stock_webhook_url: Option<String>,
+    /// Inventory below this counts as low stock.
+    pub low_stock_threshold: i32,
+    /// A signed-in user's repeat of an identical product create within this
+    /// window returns the first product; zero turns deduplication off.
+    pub create_dedup_window: Duration,
+    /// `DEFAULT_PAGE_SIZE` and `MAX_PAGE_SIZE`, for every list.
+    pub page_limits: PageLimits,
+}
+
+impl ServerSettings {
+    pub fn from_env() -> AppResult<Self> {
+        EnvReader::load(Self::read)
+    }
+
+    pub fn read(env: &mut EnvReader) -> Self {
+        let settings = Self {
+            request_timeout: Duration::from_secs(env.var("REQUEST_TIMEOUT_SECS", 30)),
+            workers: Some(env.var("SERVER_WORKERS", 0)).filter(|workers| *workers > 0),
+            shutdown_timeout: Duration::from_secs(env.var("SHUTDOWN_TIMEOUT_SECS", 30)),
+            shutdown_delay: Duration::from_secs(env.var("SHUTDOWN_DELAY_SECS", 0)),
+            max_concurrent_requests: Some(env.var("MAX_CONCURRENT_REQUESTS", 0)).filter(|limit| *limit > 0),
+            run_migrations: env.var("RUN_MIGRATIONS", false),
+            apq_cache_size: env.var("GRAPHQL_APQ_CACHE_SIZE", 1024),
+            graphql_max_depth: env.var("GRAPHQL_MAX_DEPTH", 16),
+            graphql_max_complexity: env.var("GRAPHQL_MAX_COMPLEXITY", 1000),
+            graphql_max_batch_size: env.var("GRAPHQL_MAX_BATCH_SIZE", 10),
+            graphql_max_request_bytes: env.var("GRAPHQL_MAX_REQUEST_BYTES", 64 * 1024),
+            graphql_cost_budget: env.var("GRAPHQL_COST_BUDGET", 60_000),
+            graphql_anonymous_cost_budget: env.var("GRAPHQL_ANONYMOUS_COST_BUDGET", 10_000),
+            graphql_introspection: env.var("GRAPHQL_INTROSPECTION", cfg!(debug_assertions)),
+            graphql_tracing: env.var("GRAPHQL_TRACING", false),
+            graphql_ui: env.var(
+                "GRAPHQL_UI",
+                if cfg!(debug_assertions) { GraphqlUi::Graphiql } else { GraphqlUi::Off },
+            ),
+            login_max_failures: env.var("LOGIN_MAX_FAILURES", 5),
+            login_lockout: Duration::from_secs(env.var("LOGIN_LOCKOUT_SECS", 30)),
+            trusted_proxies: env.var("TRUSTED_PROXIES", TrustedProxies::default()),
+            auth_cookie_name: env.var("AUTH_COOKIE_NAME", "auth_token".to_string()),
+            password_min_length: env.var("PASSWORD_MIN_LENGTH", 8),
+            bcrypt_cost: env.var("BCRYPT_COST", bcrypt::DEFAULT_COST),
+            low_stock_webhook_url: Some(env.var("LOW_STOCK_WEBHOOK_URL", String::new()))
+                .filter(|url| !url.is_empty()),
+            low_stock_threshold: env.var("LOW_STOCK_THRESHOLD", 5),
+            create_dedup_window: Duration::from_millis(env.var("CREATE_DEDUP_WINDOW_MS", 0)),
+            page_limits: PageLimits::read(env),
+        };
+
+        // actix-web panics beyond this many workers
+        env.check(
+            settings.workers.is_none_or(|workers| workers <= 512),
+            "SERVER_WORKERS must be at most 512",
+        );
+        env.check(settings.apq_cache_size > 0, "GRAPHQL_APQ_CACHE_SIZE must be at least 1");
+        env.check(settings.graphql_max_batch_size > 0, "GRAPHQL_MAX_BATCH_SIZE must be at least 1");
+        env.check(settings.graphql_max_request_bytes > 0, "GRAPHQL_MAX_REQUEST_BYTES must be at least 1");
+        env.check(
+            !settings.auth_cookie_name.is_empty()
+                && settings
+                    .auth_cookie_name
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_'),
+            "AUTH_COOKIE_NAME must be non-empty and contain only letters, digits, '-' and '_'",
+        );
+        env.check(
+            (4..=31).contains(&settings.bcrypt_cost),
+            "BCRYPT_COST must be between 4 and 31",
+        );
+        settings
+    }
+}
+
+/// Reads env vars while collecting problems, so a misconfigured deployment
+/// is reported in one go. Invalid values fall back to their defaults until
+/// `finish` turns the collected problems into an error.
+#[derive(Default)]
+pub struct EnvReader {
+    problems: Vec<String>,
+    /// Read instead of the process environment when set.
+    vars: Option<HashMap<String, String>>,
+}
+
+impl EnvReader {
+    pub fn load<T>(read: impl FnOnce(&mut EnvReader) -> T) -> AppResult<T> {
+        EnvReader::default().run(read)
+    }
+
+    /// Like `load`, but sees only `vars`.
+    pub fn load_from<T>(vars: &[(&str, &str)], read: impl FnOnce(&mut EnvReader) -> T) -> AppResult<T> {
+        let vars = vars.iter().map(|(name, value)| (name.to_string(), value.to_string())).collect();
+        EnvReader { problems: Vec::new(), vars: Some(vars) }.run(read)
+    }
+
+    fn run<T>(mut self, read: impl FnOnce(&mut EnvReader) -> T) -> AppResult<T> {
+        let value = read(&mut self);
+        self.finish()?;
+        Ok(value)
+    }
+
+    /// The raw value of `name`, for variables that aren't parsed with `FromStr`.
+    pub fn value(&self, name: &str) -> Option<String> {
+        match &self.vars {
+            Some(vars) => vars.get(name).cloned(),
+            None => std::env::var(name).ok(),
+        }
+    }
+
+    /// Parses `name`, using `default` when it is unset.
+    pub fn var<T: FromStr>(&mut self, name: &str, default: T) -> T {
+        match self.value(name) {
+            Some(value) => match value.parse() {
+                Ok(parsed) => parsed,
+                Err(_) => {
+                    self.problems.push(format!("{} has an invalid value: {}", name, value));
+                    default
+                }
+            },
+            None => default,
+        }
+    }
+
+    pub fn check(&mut self, ok: bool, problem: impl Into<String>) {
+        if !ok {
+            self.problems.push(problem.into());
+        }
+    }
+
+    pub fn finish(self) -> AppResult<()> {
+        if self.problems.is_empty() {
+            Ok(())
+        } else {
+            Err(AppError::Validation(format!(
+                "invalid configuration: {}",
+                self.problems.join("; ")
+            )))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bcrypt_cost(value: &str) -> AppResult<u32> {
+        EnvReader::load_from(&[("BCRYPT_COST", value)], ServerSettings::read).map(|settings| settings.bcrypt_cost)
+    }
+
+    #[test]
+    fn bcrypt_cost_must_be_between_4_and_31() {
+        assert_eq!(bcrypt_cost("4").unwrap(), 4);
+        assert_eq!(bcrypt_cost("31").unwrap(), 31);
+        for cost in ["3", "32", "0"] {
+            match bcrypt_cost(cost) {
+                Err(AppError::Validation(message)) => {
+                    assert!(message.contains("BCRYPT_COST must be between 4 and 31"), "{}", message)
+                }
+                other => panic!("BCRYPT_COST={} was accepted: {:?}", cost, other.ok()),
+            }
+        }
+    }
+
+    #[test]
+    fn bcrypt_cost_defaults_to_the_library_default() {
+        assert_eq!(EnvReader::load_from(&[], ServerSettings::read).unwrap().bcrypt_cost, bcrypt::DEFAULT_COST);
+    }
+}
\ No newline at end of file
@@ -0,0 +1,76 @@
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use tokio::sync::OnceCell;
+
+use crate::error::AppResult;
+use crate::models::{CreateProduct, Product};
+
+/// Who asked for which product: user id, name, price and inventory.
+type CreateKey = (String, String, i64, i32);
+
+struct RecentCreate {
+    started: Instant,
+    cell: Arc<OnceCell<Product>>,
+}
+
+/// Best-effort guard against double-submitted creates. When one user asks
+/// for the same name, price and inventory again within the window, the
+/// product the first request created is returned instead of a second one;
+/// a request arriving while the first is still inserting waits for it.
+/// Only signed-in callers are tracked, and only in this process.
+pub struct CreateDedup {
+    window: Duration,
+    recent: Mutex<HashMap<CreateKey, RecentCreate>>,
+}
+
+impl CreateDedup {
+    /// A zero `window` disables deduplication.
+    pub fn new(window: Duration) -> Self {
+        Self {
+            window,
+            recent: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Runs `create` unless `user_id` already created `input` within the
+    /// window. The flag is `false` when the product was not created by this
+    /// call. A failed create is not remembered, so a retry creates afresh.
+    pub async fn create<F, Fut>(&self, user_id: Option<&str>, input: CreateProduct, create: F) -> AppResult<(Product, bool)>
+    where
+        F: FnOnce(CreateProduct) -> Fut,
+        Fut: Future<Output = AppResult<Product>>,
+    {
+        let Some(user_id) = user_id.filter(|_| !self.window.is_zero()) else {
+            return create(input).await.map(|product| (product, true));
+        };
+        let key = (user_id.to_string(), input.name.clone(), input.price, input.inventory);
+        let mut created = false;
+        let product = self
+            .cell(key)
+            .get_or_try_init(|| async {
+                created = true;
+                create(input).await
+            })
+            .await?
+            .clone();
+        Ok((product, created))
+    }
+
+    /// The entry a create of `key` should wait on, replacing an expired one.
+    fn cell(&self, key: CreateKey) -> Arc<OnceCell<Product>> {
+        let now = Instant::now();
+        let mut recent = self.recent.lock().unwrap();
+        if let Some(entry) = recent.get(&key) {
+            if now.duration_since(entry.started) < self.window {
+                return entry.cell.clone();
+            }
+        }
+        recent.retain(|_, entry| now.duration_since(entry.started) < self.window);
+        let cell = Arc::new(OnceCell::new());
+        recent.insert(key, RecentCreate { started: now, cell: cell.clone() });
+        cell
+    }
+}
\ No newline at end of file
@@ -1,9 +1,39 @@
+use serde::Serialize;
+use std::time::Duration;
 use thiserror::Error;
 
+/// One invalid input field, named by its path in the request body
+/// (`price`, `[2].name` for an item of an array body).
+#[derive(Debug, Clone, Serialize, async_graphql::SimpleObject)]
+pub struct FieldError {
+    pub field: String,
+    pub message: String,
+}
+
+impl FieldError {
+    pub fn new(field: impl Into<String>, message: impl Into<String>) -> Self {
+        Self {
+            field: field.into(),
+            message: message.into(),
+        }
+    }
+
+    /// Nests the error under `prefix`, e.g. the index of an array item.
+    pub fn within(self, prefix: &str) -> Self {
+        Self {
+            field: format!("{}.{}", prefix, self.field),
+            message: self.message,
+        }
+    }
+}
+
 #[derive(Error, Debug)]
 pub enum AppError {
     #[error("Database error: {0}")]
     Database(#[from] sqlx::Error),
+
+    #[error("Migration error: {0}")]
+    Migration(#[from] sqlx::migrate::MigrateError),
     
     #[error("Authentication error: {0}")]
     Authentication(String),
@@ -13,9 +43,19 @@ pub enum AppError {
     
     #[error("Validation error: {0}")]
     Validation(String),
+
+    #[error("validation failed")]
+    ValidationFields(Vec<FieldError>),
     
     #[error("Not found: {0}")]
     NotFound(String),
+
+    /// No route matches the request path, which is echoed back in the body.
+    #[error("not found")]
+    RouteNotFound(String),
+
+    #[error("Precondition failed: {0}")]
+    PreconditionFailed(String),
     
     #[error("Internal server error: {0}")]
     Internal(#[from] anyhow::Error),
@@ -25,6 +65,235 @@ pub enum AppError {
     
     #[error("BCrypt error: {0}")]
     Bcrypt(#[from] bcrypt::BcryptError),
+
+    /// The path exists but not for this method. `allow` lists the methods
+    /// it does take; when empty the framework supplies `Allow` itself.
+    #[error("method not allowed")]
+    MethodNotAllowed { allow: Vec<String> },
+
+    /// `limit` is the most bytes accepted.
+    #[error("Request too large: the limit is {limit} bytes")]
+    PayloadTooLarge { limit: usize },
+
+    #[error("Request timed out")]
+    Timeout,
+
+    #[error("Server is at capacity, retry later")]
+    Overloaded,
+
+    #[error("Upstream service error: {0}")]
+    Upstream(String),
+
+    /// A dependency the request needs is down, e.g. the database for the
+    /// readiness check. The message is shown to clients as is.
+    #[error("Service unavailable: {0}")]
+    Unavailable(String),
+
+    /// `limit` is the allowance the caller used up, in whatever unit the
+    /// limiter counts (failed logins, query cost).
+    #[error("Too many requests, retry in {} seconds", retry_after_secs(.retry_after))]
+    RateLimited { retry_after: Duration, limit: u64 },
+}
+
+/// Whole seconds for `Retry-After`, rounded up so clients never retry early.
+fn retry_after_secs(retry_after: &Duration) -> u64 {
+    retry_after.as_secs() + u64::from(retry_after.subsec_nanos() > 0)
+}
+
+pub type AppResult<T> = Result<T, AppError>;
+
+/// `Ok` when `errors` is empty, otherwise a `ValidationFields` error.
+pub fn check_fields(errors: Vec<FieldError>) -> AppResult<()> {
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(AppError::ValidationFields(errors))
+    }
 }
 
-pub type AppResult<T> = Result<T, AppError>;
\ No newline at end of file
+impl AppError {
+    pub fn http_status(&self) -> u16 {
+        match self {
+            AppError::Authentication(_) | AppError::Jwt(_) => 401,
+            AppError::Authorization(_) => 403,
+            AppError::Validation(_) | AppError::ValidationFields(_) => 400,
+            AppError::NotFound(_) | AppError::RouteNotFound(_) => 404,
+            AppError::MethodNotAllowed { .. } => 405,
+            AppError::PreconditionFailed(_) => 412,
+            AppError::PayloadTooLarge { .. } => 413,
+            AppError::Timeout => 504,
+            AppError::Overloaded | AppError::Unavailable(_) => 503,
+            AppError::Upstream(_) => 502,
+            AppError::RateLimited { .. } => 429,
+            AppError::Database(_)
+            | AppError::Migration(_)
+            | AppError::Internal(_)
+            | AppError::Bcrypt(_) => 500,
+        }
+    }
+
+    /// Machine-readable error code, stable across both frameworks.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            AppError::Authentication(_) | AppError::Jwt(_) => "UNAUTHENTICATED",
+            AppError::Authorization(_) => "FORBIDDEN",
+            AppError::Validation(_) | AppError::ValidationFields(_) => "VALIDATION",
+            AppError::NotFound(_) | AppError::RouteNotFound(_) => "NOT_FOUND",
+            AppError::MethodNotAllowed { .. } => "METHOD_NOT_ALLOWED",
+            AppError::PreconditionFailed(_) => "PRECONDITION_FAILED",
+            AppError::PayloadTooLarge { .. } => "PAYLOAD_TOO_LARGE",
+            AppError::Timeout => "TIMEOUT",
+            AppError::Overloaded => "OVERLOADED",
+            AppError::Upstream(_) => "UPSTREAM",
+            AppError::Unavailable(_) => "UNAVAILABLE",
+            AppError::RateLimited { .. } => "RATE_LIMITED",
+            AppError::Database(_)
+            | AppError::Migration(_)
+            | AppError::Internal(_)
+            | AppError::Bcrypt(_) => "INTERNAL",
+        }
+    }
+
+    /// Message safe to show clients. Server-side failures are logged in full
+    /// but only reported generically so database details don't leak.
+    pub fn public_message(&self) -> String {
+        if self.http_status() >= 500 && !matches!(self, AppError::Timeout | AppError::Overloaded | AppError::Unavailable(_)) {
+            tracing::error!("{}", self);
+            "Internal server error".to_string()
+        } else {
+            self.to_string()
+        }
+    }
+
+    /// JSON body returned to REST clients; field errors add a `fields` list
+    /// and an unknown route its `path`.
+    pub fn body(&self) -> serde_json::Value {
+        let mut body = serde_json::json!({
+            "error": self.public_message(),
+            "kind": self.kind(),
+        });
+        if let AppError::ValidationFields(fields) = self {
+            body["fields"] = serde_json::json!(fields);
+        }
+        if let AppError::RouteNotFound(path) = self {
+            body["path"] = serde_json::json!(path);
+        }
+        body
+    }
+
+    /// Extra response headers that belong to this error. A rate limit is
+    /// described by `Retry-After` plus the `X-RateLimit-*` trio; `Reset` is
+    /// in seconds from now, like `Retry-After`.
+    pub fn headers(&self) -> Vec<(&'static str, String)> {
+        match self {
+            AppError::RateLimited { retry_after, limit } => {
+                let retry_after = retry_after_secs(retry_after).to_string();
+                vec![
+                    ("retry-after", retry_after.clone()),
+                    ("x-ratelimit-limit", limit.to_string()),
+                    ("x-ratelimit-remaining", "0".to_string()),
+                    ("x-ratelimit-reset", retry_after),
+                ]
+            }
+            AppError::MethodNotAllowed { allow } if !allow.is_empty() => vec![("allow", allow.join(","))],
+            _ => Vec::new(),
+        }
+    }
+}
+
+/// GraphQL counterpart of `body()`: the same message, with `kind()` exposed
+/// as `extensions.code` and field errors as `extensions.fields`. Resolvers
+/// convert with `ResultExt::extend`.
+impl async_graphql::ErrorExtensions for AppError {
+    fn extend(&self) -> async_graphql::Error {
+        async_graphql::Error::new(self.public_message()).extend_with(|_, e| {
+            e.set("code", self.kind());
+            if let AppError::RateLimited { retry_after, .. } = self {
+                e.set("retryAfter", retry_after_secs(retry_after));
+            }
+            if let AppError::ValidationFields(fields) = self {
+                if let Ok(fields) = async_graphql::Value::from_json(serde_json::json!(fields)) {
+                    e.set("fields", fields);
+                }
+            }
+        })
+    }
+}
+
+/// A query string that doesn't fit the handler's parameters, e.g.
+/// `?limit=abc`, through `axum-server`'s `Query` extractor.
+#[cfg(feature = "axum")]
+impl From<axum::extract::rejection::QueryRejection> for AppError {
+    fn from(rejection: axum::extract::rejection::QueryRejection) -> Self {
+        invalid_query(&rejection)
+    }
+}
+
+/// The actix counterpart of the axum `QueryRejection` conversion, for
+/// `QueryConfig::error_handler`.
+#[cfg(feature = "actix-web")]
+impl From<actix_web::error::QueryPayloadError> for AppError {
+    fn from(err: actix_web::error::QueryPayloadError) -> Self {
+        invalid_query(&err)
+    }
+}
+
+/// Both frameworks wrap the deserializer's message in their own; only that
+/// message is kept so the servers answer alike, though only axum names the
+/// parameter.
+#[cfg(any(feature = "axum", feature = "actix-web"))]
+fn invalid_query(err: &(dyn std::error::Error + 'static)) -> AppError {
+    let detail = err.source().unwrap_or(err);
+    AppError::Validation(format!("invalid query string: {}", detail))
+}
+
+#[cfg(feature = "axum")]
+impl axum::response::IntoResponse for AppError {
+    fn into_response(self) -> axum::response::Response {
+        let status = axum::http::StatusCode::from_u16(self.http_status())
+            .unwrap_or(axum::http::StatusCode::INTERNAL_SERVER_ERROR);
+        let mut response = (status, axum::Json(self.body())).into_response();
+        for (name, value) in self.headers() {
+            if let Ok(value) = axum::http::HeaderValue::from_str(&value) {
+                response.headers_mut().insert(name, value);
+            }
+        }
+        response
+    }
+}
+
+#[cfg(feature = "actix-web")]
+impl actix_web::ResponseError for AppError {
+    fn status_code(&self) -> actix_web::http::StatusCode {
+        actix_web::http::StatusCode::from_u16(self.http_status())
+            .unwrap_or(actix_web::http::StatusCode::INTERNAL_SERVER_ERROR)
+    }
+
+    fn error_response(&self) -> actix_web::HttpResponse {
+        let mut response = actix_web::HttpResponse::build(self.status_code());
+        for header in self.headers() {
+            response.insert_header(header);
+        }
+        response.json(self.body())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rate_limited_headers_round_the_wait_up() {
+        let error = AppError::RateLimited { retry_after: Duration::from_millis(1200), limit: 5 };
+        assert_eq!(
+            error.headers(),
+            [
+                ("retry-after", "2".to_string()),
+                ("x-ratelimit-limit", "5".to_string()),
+                ("x-ratelimit-remaining", "0".to_string()),
+                ("x-ratelimit-reset", "2".to_string()),
+            ]
+        );
+        assert_eq!(error.http_status(), 429);
+    }
+}
\ No newline at end of file
@@ -0,0 +1,123 @@
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex};
+
+use futures::{Stream, StreamExt};
+use serde::Serialize;
+use tokio::sync::broadcast;
+
+use crate::id::Id;
+use crate::models::Product;
+use crate::repository::ProductChange;
+
+/// A change to the catalog. REST handlers and GraphQL mutations publish these
+/// on the `EventBus`; side effects such as subscriptions, the low stock
+/// webhook and metrics subscribe to it instead of being called directly.
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum DomainEvent {
+    ProductCreated { product: Product },
+    ProductUpdated { product: Product },
+    ProductDeleted { product_id: Id },
+    /// Follows `ProductUpdated` when the update changed the inventory.
+    InventoryAdjusted { product: Product, previous_inventory: i32 },
+}
+
+impl DomainEvent {
+    pub fn kind(&self) -> &'static str {
+        match self {
+            DomainEvent::ProductCreated { .. } => "product_created",
+            DomainEvent::ProductUpdated { .. } => "product_updated",
+            DomainEvent::ProductDeleted { .. } => "product_deleted",
+            DomainEvent::InventoryAdjusted { .. } => "inventory_adjusted",
+        }
+    }
+}
+
+const EVENT_BUS_CAPACITY: usize = 256;
+
+/// In-process fan-out of `DomainEvent`s over a broadcast channel. Each
+/// subscriber gets every event published after it subscribed.
+#[derive(Clone)]
+pub struct EventBus {
+    sender: broadcast::Sender<DomainEvent>,
+}
+
+impl EventBus {
+    pub fn new() -> Self {
+        let (sender, _) = broadcast::channel(EVENT_BUS_CAPACITY);
+        Self { sender }
+    }
+
+    pub fn publish(&self, event: DomainEvent) {
+        // Sending only fails when nobody is subscribed, which is not an error
+        let _ = self.sender.send(event);
+    }
+
+    /// Publishes `ProductUpdated`, plus `InventoryAdjusted` if the inventory
+    /// moved.
+    pub fn publish_update(&self, change: ProductChange) {
+        let adjusted = change.product.inventory != change.previous_inventory;
+        self.publish(DomainEvent::ProductUpdated { product: change.product.clone() });
+        if adjusted {
+            self.publish(DomainEvent::InventoryAdjusted {
+                product: change.product,
+                previous_inventory: change.previous_inventory,
+            });
+        }
+    }
+
+    /// Events published after the call. Subscribing happens immediately, not
+    /// on first poll, so nothing published in between is missed.
+    pub fn subscribe(&self) -> impl Stream<Item = DomainEvent> + Send + 'static {
+        self.subscribe_reporting_lag(|_| {})
+    }
+
+    /// Like `subscribe`, calling `on_lag` with the number of events skipped
+    /// each time the subscriber falls more than the bus capacity behind.
+    pub fn subscribe_reporting_lag(
+        &self,
+        on_lag: impl FnMut(u64) + Send + 'static,
+    ) -> impl Stream<Item = DomainEvent> + Send + 'static {
+        futures::stream::unfold((self.sender.subscribe(), on_lag), |(mut receiver, mut on_lag)| async move {
+            loop {
+                match receiver.recv().await {
+                    Ok(event) => return Some((event, (receiver, on_lag))),
+                    // A slow subscriber skips the events it missed rather than ending the stream
+                    Err(broadcast::error::RecvError::Lagged(skipped)) => on_lag(skipped),
+                    Err(broadcast::error::RecvError::Closed) => return None,
+                }
+            }
+        })
+    }
+}
+
+impl Default for EventBus {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Events seen since startup by kind, served at `GET /health/events`.
+#[derive(Clone, Default)]
+pub struct EventMetrics {
+    counts: Arc<Mutex<BTreeMap<&'static str, u64>>>,
+}
+
+impl EventMetrics {
+    /// Starts counting the events published on `events`.
+    pub fn listen(events: &EventBus) -> Self {
+        let metrics = Self::default();
+        let counts = metrics.counts.clone();
+        let mut stream = Box::pin(events.subscribe());
+        tokio::spawn(async move {
+            while let Some(event) = stream.next().await {
+                *counts.lock().unwrap().entry(event.kind()).or_default() += 1;
+            }
+        });
+        metrics
+    }
+
+    pub fn snapshot(&self) -> BTreeMap<&'static str, u64> {
+        self.counts.lock().unwrap().clone()
+    }
+}
\ No newline at end of file
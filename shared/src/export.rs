@@ -0,0 +1,91 @@
+use futures::{Stream, StreamExt};
+
+use crate::error::AppResult;
+use crate::models::Product;
+use crate::timestamp;
+
+/// `Content-Disposition` of `GET /api/products/export.csv`.
+pub const PRODUCT_CSV_DISPOSITION: &str = "attachment; filename=\"products.csv\"";
+
+const PRODUCT_CSV_HEADER: &str = "id,name,description,price,inventory,created_at,updated_at\n";
+
+/// The header line, then one CSV line per product as it arrives. A read
+/// error ends the stream with that error, cutting the download short rather
+/// than silently truncating it.
+pub fn product_csv(
+    products: impl Stream<Item = AppResult<Product>> + Send + 'static,
+) -> impl Stream<Item = AppResult<String>> + Send + 'static {
+    let rows = products.map(|product| {
+        product.map(|product| csv_row(&product)).inspect_err(|e| {
+            tracing::warn!(error = %e, "product export failed partway");
+        })
+    });
+    futures::stream::once(async { Ok(PRODUCT_CSV_HEADER.to_string()) }).chain(rows)
+}
+
+fn csv_row(product: &Product) -> String {
+    format!(
+        "{},{},{},{},{},{},{}\n",
+        product.id,
+        csv_field(&product.name),
+        csv_field(&product.description),
+        product.price,
+        product.inventory,
+        timestamp::format(&product.created_at),
+        timestamp::format(&product.updated_at),
+    )
+}
+
+/// Characters a spreadsheet may read as the start of a formula.
+const FORMULA_PREFIXES: [char; 6] = ['=', '+', '-', '@', '\t', '\r'];
+
+/// Quotes a field containing a separator, quote or line break, doubling any
+/// quotes inside it (RFC 4180). A field that would open the export as a
+/// formula is prefixed with `'` first, so a product name like
+/// `=HYPERLINK(...)` shows as text.
+fn csv_field(value: &str) -> String {
+    let value = if value.starts_with(FORMULA_PREFIXES) {
+        format!("'{}", value)
+    } else {
+        value.to_string()
+    };
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn plain_fields_are_left_alone() {
+        assert_eq!(csv_field("Desk lamp"), "Desk lamp");
+        assert_eq!(csv_field(""), "");
+        assert_eq!(csv_field("50% off - today"), "50% off - today");
+    }
+
+    #[test]
+    fn separators_quotes_and_line_breaks_are_quoted() {
+        assert_eq!(csv_field("red, large"), "\"red, large\"");
+        assert_eq!(csv_field("the \"best\" lamp"), "\"the \"\"best\"\" lamp\"");
+        assert_eq!(csv_field("two\nlines"), "\"two\nlines\"");
+    }
+
+    #[test]
+    fn formula_prefixes_are_neutralised() {
+        for (value, expected) in [
+            ("=1+1", "'=1+1"),
+            ("+1", "'+1"),
+            ("-1", "'-1"),
+            ("@SUM(A1)", "'@SUM(A1)"),
+            ("\tsecret", "'\tsecret"),
+            ("\rsecret", "\"'\rsecret\""),
+            ("=HYPERLINK(\"http://evil\", \"x\")", "\"'=HYPERLINK(\"\"http://evil\"\", \"\"x\"\")\""),
+        ] {
+            assert_eq!(csv_field(value), expected, "{:?}", value);
+        }
+    }
+}
\ No newline at end of file
@@ -1,11 +1,87 @@
-use async_graphql::{Context, Object, Result, Schema, SimpleObject, InputObject, Subscription};
+use async_graphql::extensions::apollo_persisted_queries::{ApolloPersistedQueries, LruCacheStorage};
+use async_graphql::extensions::ApolloTracing;
+use async_graphql::http::{playground_source, GraphQLPlaygroundConfig, GraphiQLSource};
+use async_graphql::connection::{self, Connection, ConnectionNameType, Edge, EdgeNameType, EmptyFields, OpaqueCursor};
+use async_graphql::dataloader::{DataLoader, Loader};
+use async_graphql::{BatchRequest, BatchResponse, ComplexObject, Context, ErrorExtensions, Guard, Object, OutputType, Result, ResultExt, Schema, ServerError, SimpleObject, InputObject, Subscription, Union};
 use chrono::{DateTime, Utc};
-use uuid::Uuid;
-use crate::models::{User, Product, CreateProduct, UpdateProduct};
+use futures::StreamExt;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use crate::auth::{Claims, LoginService};
+use crate::client_ip::ClientIp;
+use crate::config::ServerSettings;
+use crate::dedup::CreateDedup;
+use crate::error::{AppError, FieldError};
+use crate::id::Id;
+use crate::graphql_extensions::{
+    PersistedQueryErrorCodes, QueryCostBudget, QueryCostLogging, RejectIntrospection, RequestErrorCodes, GRAPHQL_PARSE_ERROR,
+};
+use crate::events::{DomainEvent, EventBus};
+use crate::models::{
+    User, Product, ProductStats, Category, CreateProduct, UpdateProduct, ReserveInventory, LoginRequest, ADMIN_ROLE,
+};
+use crate::pagination::{PageLimits, PAGINATION_CLAMPED_HEADER};
+use crate::query_budget::QueryCostBudgets;
+use crate::repository::{check_bulk_size, ProductChange, UserRepository};
+use crate::store::ProductStore;
+use crate::telemetry::{in_phase, SubscriptionConnection};
+use crate::validate::Validate;
+
+/// In-browser IDE served at `/graphiql`, selected with `GRAPHQL_UI`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GraphqlUi {
+    Graphiql,
+    Playground,
+    Off,
+}
+
+impl GraphqlUi {
+    pub fn name(self) -> Option<&'static str> {
+        match self {
+            GraphqlUi::Graphiql => Some("GraphiQL"),
+            GraphqlUi::Playground => Some("GraphQL Playground"),
+            GraphqlUi::Off => None,
+        }
+    }
+
+    /// The UI page, pointed at `/graphql` and `/graphql/ws`; `None` when off.
+    pub fn page(self) -> Option<String> {
+        match self {
+            GraphqlUi::Graphiql => Some(
+                GraphiQLSource::build()
+                    .endpoint("/graphql")
+                    .subscription_endpoint("/graphql/ws")
+                    .finish(),
+            ),
+            GraphqlUi::Playground => Some(playground_source(
+                GraphQLPlaygroundConfig::new("/graphql").subscription_endpoint("/graphql/ws"),
+            )),
+            GraphqlUi::Off => None,
+        }
+    }
+}
+
+impl FromStr for GraphqlUi {
+    type Err = ();
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "graphiql" => Ok(GraphqlUi::Graphiql),
+            "playground" => Ok(GraphqlUi::Playground),
+            "off" => Ok(GraphqlUi::Off),
+            _ => Err(()),
+        }
+    }
+}
 
 #[derive(SimpleObject)]
 pub struct UserGraphQL {
-    pub id: Uuid,
+    pub id: Id,
     pub username: String,
     pub email: String,
     pub created_at: DateTime<Utc>,
@@ -22,14 +98,18 @@ impl From<User> for UserGraphQL {
     }
 }
 
-#[derive(SimpleObject)]
+#[derive(SimpleObject, Clone, Serialize)]
+#[graphql(complex)]
 pub struct ProductGraphQL {
-    pub id: Uuid,
+    pub id: Id,
     pub name: String,
     pub description: String,
     pub price: i64,
     pub inventory: i32,
+    pub image_url: Option<String>,
+    #[serde(with = "crate::timestamp")]
     pub created_at: DateTime<Utc>,
+    #[serde(with = "crate::timestamp")]
     pub updated_at: DateTime<Utc>,
 }
 
@@ -41,12 +121,135 @@ impl From<Product> for ProductGraphQL {
             description: product.description,
             price: product.price,
             inventory: product.inventory,
+            image_url: product.image_url,
             created_at: product.created_at,
             updated_at: product.updated_at,
         }
     }
 }
 
+#[ComplexObject]
+impl ProductGraphQL {
+    /// By name. Loaded through `CategoryLoader`, so a list of products
+    /// costs one query for all their categories rather than one each.
+    async fn categories(&self, ctx: &Context<'_>) -> Result<Vec<CategoryGraphQL>> {
+        let categories = ctx.data::<DataLoader<CategoryLoader>>()?.load_one(self.id).await?;
+        Ok(categories.unwrap_or_default().into_iter().map(CategoryGraphQL::from).collect())
+    }
+}
+
+/// Same totals as `GET /api/admin/stats`.
+#[derive(SimpleObject)]
+#[graphql(name = "ProductStats")]
+pub struct ProductStatsGraphQL {
+    pub total_products: i64,
+    pub total_inventory_units: i64,
+    pub total_catalog_value_cents: i64,
+    pub avg_price_cents: i64,
+    pub out_of_stock_count: i64,
+}
+
+impl From<ProductStats> for ProductStatsGraphQL {
+    fn from(stats: ProductStats) -> Self {
+        Self {
+            total_products: stats.total_products,
+            total_inventory_units: stats.total_inventory_units,
+            total_catalog_value_cents: stats.total_catalog_value_cents,
+            avg_price_cents: stats.avg_price_cents,
+            out_of_stock_count: stats.out_of_stock_count,
+        }
+    }
+}
+
+#[derive(SimpleObject, Clone)]
+#[graphql(name = "Category")]
+pub struct CategoryGraphQL {
+    pub id: Id,
+    pub name: String,
+}
+
+impl From<Category> for CategoryGraphQL {
+    fn from(category: Category) -> Self {
+        Self {
+            id: category.id,
+            name: category.name,
+        }
+    }
+}
+
+/// Batches the `categories` of every product resolved together into one
+/// `ProductStore::categories_of` call. Nothing is cached between requests.
+pub struct CategoryLoader {
+    products: Arc<dyn ProductStore>,
+}
+
+impl Loader<Id> for CategoryLoader {
+    type Value = Vec<Category>;
+    type Error = async_graphql::Error;
+
+    async fn load(&self, keys: &[Id]) -> Result<HashMap<Id, Self::Value>> {
+        self.products.categories_of(keys).await.extend()
+    }
+}
+
+/// Input a mutation rejected, returned in `data` so clients can select on it
+/// instead of parsing `errors`. `field` and `message` describe the first
+/// problem (`field` is null when it isn't about one field); `fields` lists
+/// every invalid field.
+#[derive(SimpleObject)]
+pub struct ValidationError {
+    pub field: Option<String>,
+    pub message: String,
+    pub fields: Vec<FieldError>,
+}
+
+impl ValidationError {
+    /// `None` for errors that aren't about the input.
+    fn from_app_error(error: &AppError) -> Option<Self> {
+        match error {
+            AppError::Validation(message) => Some(Self {
+                field: None,
+                message: message.clone(),
+                fields: Vec::new(),
+            }),
+            AppError::ValidationFields(fields) => Some(Self {
+                field: fields.first().map(|error| error.field.clone()),
+                message: fields
+                    .first()
+                    .map_or_else(|| error.public_message(), |error| error.message.clone()),
+                fields: fields.clone(),
+            }),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Union)]
+pub enum CreateProductResult {
+    Product(ProductGraphQL),
+    ValidationError(ValidationError),
+}
+
+/// One item of `updateInventoryBatch`: moves the stock of `product_id` by
+/// `delta`, negative to take units out.
+#[derive(InputObject)]
+pub struct InventoryUpdateInput {
+    pub product_id: Id,
+    pub delta: i32,
+}
+
+/// Outcome of one `updateInventoryBatch` item. On success `product` is the
+/// updated product; otherwise `error` and `code` (the REST `kind`, e.g.
+/// `NOT_FOUND`) say why the item was left unchanged.
+#[derive(SimpleObject)]
+pub struct InventoryUpdateResult {
+    pub product_id: Id,
+    pub success: bool,
+    pub product: Option<ProductGraphQL>,
+    pub error: Option<String>,
+    pub code: Option<&'static str>,
+}
+
 #[derive(InputObject)]
 pub struct CreateProductInput {
     pub name: String,
@@ -72,6 +275,7 @@ pub struct UpdateProductInput {
     pub description: Option<String>,
     pub price: Option<i64>,
     pub inventory: Option<i32>,
+    pub image_url: Option<String>,
 }
 
 impl From<UpdateProductInput> for UpdateProduct {
@@ -81,60 +285,307 @@ impl From<UpdateProductInput> for UpdateProduct {
             description: input.description,
             price: input.price,
             inventory: input.inventory,
+            image_url: input.image_url,
+        }
+    }
+}
+
+/// Position of a user in `(created_at, id)` order, base64-encoded as the
+/// opaque `after` cursor.
+#[derive(Serialize, Deserialize)]
+pub struct UserCursor {
+    created_at: DateTime<Utc>,
+    id: Id,
+}
+
+pub struct UserConnectionName;
+
+impl ConnectionNameType for UserConnectionName {
+    fn type_name<T: OutputType>() -> String {
+        "UserConnection".to_string()
+    }
+}
+
+pub struct UserEdgeName;
+
+impl EdgeNameType for UserEdgeName {
+    fn type_name<T: OutputType>() -> String {
+        "UserEdge".to_string()
+    }
+}
+
+pub type UserConnection =
+    Connection<OpaqueCursor<UserCursor>, UserGraphQL, EmptyFields, EmptyFields, UserConnectionName, UserEdgeName>;
+
+/// Lets a field through only when the servers attached verified `Claims`
+/// from the request's bearer token.
+pub struct AuthGuard;
+
+impl Guard for AuthGuard {
+    async fn check(&self, ctx: &Context<'_>) -> Result<()> {
+        match ctx.data_opt::<Claims>() {
+            Some(_) => Ok(()),
+            None => Err(AppError::Authentication("authentication required".to_string()).extend()),
         }
     }
 }
 
+/// `AuthGuard` that also requires the admin role.
+pub struct AdminGuard;
+
+impl Guard for AdminGuard {
+    async fn check(&self, ctx: &Context<'_>) -> Result<()> {
+        match ctx.data_opt::<Claims>() {
+            Some(claims) if claims.role == ADMIN_ROLE => Ok(()),
+            Some(_) => Err(AppError::Authorization("admin role required".to_string()).extend()),
+            None => Err(AppError::Authentication("authentication required".to_string()).extend()),
+        }
+    }
+}
+
+/// Identifies the running server to `Query::server_info`.
+pub struct ServerInfo {
+    framework: &'static str,
+    started_at: Instant,
+}
+
+impl ServerInfo {
+    /// Call once at startup; uptime is measured from here.
+    pub fn new(framework: &'static str) -> Self {
+        Self {
+            framework,
+            started_at: Instant::now(),
+        }
+    }
+}
+
+#[derive(SimpleObject)]
+pub struct ServerInfoGraphQL {
+    pub framework: String,
+    pub version: String,
+    pub uptime_seconds: u64,
+    pub git_sha: String,
+}
+
 pub struct Query;
 
 #[Object]
 impl Query {
-    async fn users(&self, _ctx: &Context<'_>) -> Result<Vec<UserGraphQL>> {
-        // Mock implementation - in real app would fetch from database
-        Ok(vec![])
+    /// Cheap liveness and build check; touches neither the database nor auth.
+    async fn server_info(&self, ctx: &Context<'_>) -> Result<ServerInfoGraphQL> {
+        let info = ctx.data::<ServerInfo>()?;
+        Ok(ServerInfoGraphQL {
+            framework: info.framework.to_string(),
+            version: env!("CARGO_PKG_VERSION").to_string(),
+            uptime_seconds: info.started_at.elapsed().as_secs(),
+            git_sha: env!("GIT_SHA").to_string(),
+        })
+    }
+
+    /// Totals over the whole catalog, from one aggregate query. Admin only.
+    #[graphql(guard = "AdminGuard")]
+    async fn product_stats(&self, ctx: &Context<'_>) -> Result<ProductStatsGraphQL> {
+        let stats = ctx.data::<Arc<dyn ProductStore>>()?.stats().await.extend()?;
+        Ok(stats.into())
+    }
+
+    /// Pages through users in signup order. Requires authentication.
+    #[graphql(guard = "AuthGuard")]
+    async fn users(
+        &self,
+        ctx: &Context<'_>,
+        first: Option<i32>,
+        after: Option<String>,
+    ) -> Result<UserConnection> {
+        connection::query(after, None, first, None, |after: Option<OpaqueCursor<UserCursor>>, _, first, _| async move {
+            let limits = ctx.data::<PageLimits>()?;
+            let first = first.map(|first| first as i64);
+            if limits.clamps(first) {
+                ctx.insert_http_header(PAGINATION_CLAMPED_HEADER, "true");
+            }
+            let limit = limits.limit("first", first).extend()? as usize;
+            let after = after.map(|cursor| (cursor.created_at, cursor.id));
+            let has_previous_page = after.is_some();
+
+            let mut users = ctx
+                .data::<UserRepository>()?
+                .list_after(after, limit as i64 + 1)
+                .await
+                .extend()?;
+            let has_next_page = users.len() > limit;
+            users.truncate(limit);
+
+            let mut connection = UserConnection::new(has_previous_page, has_next_page);
+            connection.edges.extend(users.into_iter().map(|user| {
+                let cursor = OpaqueCursor(UserCursor { created_at: user.created_at, id: user.id });
+                Edge::new(cursor, UserGraphQL::from(user))
+            }));
+            Ok::<_, async_graphql::Error>(connection)
+        })
+        .await
     }
 
-    async fn user(&self, _ctx: &Context<'_>, _id: Uuid) -> Result<Option<UserGraphQL>> {
-        // Mock implementation
-        Ok(None)
+    /// Requires authentication, like `users`.
+    #[graphql(guard = "AuthGuard")]
+    async fn user(&self, ctx: &Context<'_>, id: Id) -> Result<Option<UserGraphQL>> {
+        let user = ctx.data::<UserRepository>()?.find_by_id(id).await.extend()?;
+        Ok(user.map(UserGraphQL::from))
     }
 
-    async fn products(&self, _ctx: &Context<'_>) -> Result<Vec<ProductGraphQL>> {
-        // Mock implementation
-        Ok(vec![])
+    async fn products(&self, ctx: &Context<'_>) -> Result<Vec<ProductGraphQL>> {
+        let products = ctx.data::<Arc<dyn ProductStore>>()?.list().await.extend()?;
+        Ok(products.into_iter().map(ProductGraphQL::from).collect())
     }
 
-    async fn product(&self, _ctx: &Context<'_>, _id: Uuid) -> Result<Option<ProductGraphQL>> {
-        // Mock implementation
-        Ok(None)
+    /// A missing product resolves to `null`, following GraphQL's nullable
+    /// field semantics, whereas REST answers 404. Pass `strict: true` to get
+    /// a `NOT_FOUND` error instead, matching the REST behaviour.
+    async fn product(
+        &self,
+        ctx: &Context<'_>,
+        id: Id,
+        #[graphql(default = false)] strict: bool,
+    ) -> Result<Option<ProductGraphQL>> {
+        match ctx.data::<Arc<dyn ProductStore>>()?.find_by_id(id).await.extend()? {
+            Some(product) => Ok(Some(product.into())),
+            None if strict => Err(AppError::NotFound("product".to_string()).extend()),
+            None => Ok(None),
+        }
     }
 }
 
+#[derive(SimpleObject)]
+pub struct LoginPayload {
+    pub token: String,
+    pub user: UserGraphQL,
+}
+
 pub struct Mutation;
 
 #[Object]
 impl Mutation {
-    async fn create_product(&self, _ctx: &Context<'_>, input: CreateProductInput) -> Result<ProductGraphQL> {
-        // Mock implementation
-        Ok(ProductGraphQL {
-            id: Uuid::new_v4(),
-            name: input.name,
-            description: input.description,
-            price: input.price,
-            inventory: input.inventory,
-            created_at: Utc::now(),
-            updated_at: Utc::now(),
-        })
+    /// Same checks and lockout as `POST /api/auth/login`.
+    async fn login(&self, ctx: &Context<'_>, email: String, password: String) -> Result<LoginPayload> {
+        let request = LoginRequest { email, password };
+        request.validate().extend()?;
+        let ClientIp(ip) = *ctx.data::<ClientIp>()?;
+        let (token, user) = ctx
+            .data::<LoginService>()?
+            .login(&request.email, &request.password, ip)
+            .await
+            .extend()?;
+        Ok(LoginPayload { token, user: user.into() })
     }
 
-    async fn update_product(&self, _ctx: &Context<'_>, _id: Uuid, _input: UpdateProductInput) -> Result<Option<ProductGraphQL>> {
-        // Mock implementation
-        Ok(None)
+    /// Invalid input comes back as a `ValidationError` member; anything else
+    /// that goes wrong is still a GraphQL error.
+    async fn create_product(&self, ctx: &Context<'_>, input: CreateProductInput) -> Result<CreateProductResult> {
+        let input = CreateProduct::from(input);
+        let store = ctx.data::<Arc<dyn ProductStore>>()?;
+        let user_id = ctx.data_opt::<Claims>().map(|claims| claims.sub.as_str());
+        let created = match input.validate() {
+            Ok(()) => {
+                let dedup = ctx.data::<Arc<CreateDedup>>()?;
+                dedup.create(user_id, input, |input| store.create(input)).await
+            }
+            Err(e) => Err(e),
+        };
+        match created {
+            Ok((product, created)) => {
+                if created {
+                    ctx.data::<EventBus>()?.publish(DomainEvent::ProductCreated { product: product.clone() });
+                }
+                Ok(CreateProductResult::Product(product.into()))
+            }
+            Err(e) => match ValidationError::from_app_error(&e) {
+                Some(validation) => Ok(CreateProductResult::ValidationError(validation)),
+                None => Err(e.extend()),
+            },
+        }
     }
 
-    async fn delete_product(&self, _ctx: &Context<'_>, _id: Uuid) -> Result<bool> {
-        // Mock implementation
-        Ok(true)
+    async fn update_product(&self, ctx: &Context<'_>, id: Id, input: UpdateProductInput) -> Result<Option<ProductGraphQL>> {
+        let input = UpdateProduct::from(input);
+        input.validate().extend()?;
+        let Some(change) = ctx
+            .data::<Arc<dyn ProductStore>>()?
+            .update(id, input)
+            .await
+            .extend()?
+        else {
+            return Ok(None);
+        };
+        let product = ProductGraphQL::from(change.product.clone());
+        ctx.data::<EventBus>()?.publish_update(change);
+        Ok(Some(product))
+    }
+
+    /// Same as `POST /api/products/{id}/reserve`.
+    async fn reserve_inventory(&self, ctx: &Context<'_>, id: Id, quantity: i32) -> Result<ProductGraphQL> {
+        ReserveInventory { quantity }.validate().extend()?;
+        let product = ctx
+            .data::<Arc<dyn ProductStore>>()?
+            .reserve_inventory(id, quantity)
+            .await
+            .extend()?;
+        ctx.data::<EventBus>()?.publish_update(ProductChange {
+            previous_inventory: product.inventory + quantity,
+            product: product.clone(),
+        });
+        Ok(product.into())
+    }
+
+    /// Applies each item on its own, in order: a failing item is reported in
+    /// its result and the others still commit. At most `MAX_BULK_PRODUCTS`
+    /// items per call.
+    async fn update_inventory_batch(
+        &self,
+        ctx: &Context<'_>,
+        updates: Vec<InventoryUpdateInput>,
+    ) -> Result<Vec<InventoryUpdateResult>> {
+        check_bulk_size(updates.len()).extend()?;
+        let store = ctx.data::<Arc<dyn ProductStore>>()?;
+        let events = ctx.data::<EventBus>()?;
+        let mut results = Vec::with_capacity(updates.len());
+        for update in updates {
+            // A negative reservation adds stock, so one conditional update
+            // covers both directions and never lets inventory go below zero
+            let adjusted = match update.delta.checked_neg() {
+                Some(quantity) => store.reserve_inventory(update.product_id, quantity).await,
+                None => Err(AppError::Validation("delta is out of range".to_string())),
+            };
+            results.push(match adjusted {
+                Ok(product) => {
+                    events.publish_update(ProductChange {
+                        previous_inventory: product.inventory - update.delta,
+                        product: product.clone(),
+                    });
+                    InventoryUpdateResult {
+                        product_id: update.product_id,
+                        success: true,
+                        product: Some(product.into()),
+                        error: None,
+                        code: None,
+                    }
+                }
+                Err(e) => InventoryUpdateResult {
+                    product_id: update.product_id,
+                    success: false,
+                    product: None,
+                    error: Some(e.public_message()),
+                    code: Some(e.kind()),
+                },
+            });
+        }
+        Ok(results)
+    }
+
+    async fn delete_product(&self, ctx: &Context<'_>, id: Id) -> Result<bool> {
+        let deleted = ctx.data::<Arc<dyn ProductStore>>()?.delete(id).await.extend()?;
+        if deleted {
+            ctx.data::<EventBus>()?.publish(DomainEvent::ProductDeleted { product_id: id });
+        }
+        Ok(deleted)
     }
 }
 
@@ -142,10 +593,209 @@ pub struct Subscription;
 
 #[Subscription]
 impl Subscription {
-    async fn product_updates(&self) -> impl futures::Stream<Item = ProductGraphQL> {
-        // Mock implementation
-        futures::stream::empty()
+    /// Every created or updated product, or with `productId` only that one.
+    async fn product_updates(
+        &self,
+        ctx: &Context<'_>,
+        product_id: Option<Id>,
+    ) -> Result<impl futures::Stream<Item = ProductGraphQL>> {
+        let connection = ctx
+            .data_opt::<SubscriptionConnection>()
+            .cloned()
+            .unwrap_or_else(|| SubscriptionConnection::new(None));
+        tracing::info!(
+            connection_id = %connection.connection_id,
+            request_id = %connection.request_id,
+            product_id = ?product_id,
+            "productUpdates subscribed"
+        );
+
+        let lagging = connection.clone();
+        let events = ctx.data::<EventBus>()?.subscribe_reporting_lag(move |skipped| {
+            tracing::warn!(
+                connection_id = %lagging.connection_id,
+                request_id = %lagging.request_id,
+                skipped,
+                "productUpdates subscriber lagged, events dropped"
+            );
+        });
+        let mut lifecycle = SubscriptionLifecycle { connection, delivered: 0 };
+        Ok(product_updates(events, product_id).map(move |product| {
+            lifecycle.delivered += 1;
+            tracing::debug!(
+                connection_id = %lifecycle.connection.connection_id,
+                request_id = %lifecycle.connection.request_id,
+                product_id = %product.id,
+                "productUpdates event delivered"
+            );
+            product
+        }))
+    }
+}
+
+/// Logs the end of a subscription when its stream is dropped, which is how
+/// async-graphql unsubscribes: on `complete` or when the socket closes.
+struct SubscriptionLifecycle {
+    connection: SubscriptionConnection,
+    delivered: u64,
+}
+
+impl Drop for SubscriptionLifecycle {
+    fn drop(&mut self) {
+        tracing::info!(
+            connection_id = %self.connection.connection_id,
+            request_id = %self.connection.request_id,
+            delivered = self.delivered,
+            "productUpdates unsubscribed"
+        );
     }
 }
 
-pub type GraphQLSchema = Schema<Query, Mutation, Subscription>;
\ No newline at end of file
+pub type GraphQLSchema = Schema<Query, Mutation, Subscription>;
+
+/// Products created or updated after the call, for the GraphQL subscription
+/// and the SSE endpoints.
+pub fn product_update_stream(
+    events: &EventBus,
+    product_id: Option<Id>,
+) -> impl futures::Stream<Item = ProductGraphQL> + Send + 'static {
+    product_updates(events.subscribe(), product_id)
+}
+
+fn product_updates(
+    events: impl futures::Stream<Item = DomainEvent> + Send + 'static,
+    product_id: Option<Id>,
+) -> impl futures::Stream<Item = ProductGraphQL> + Send + 'static {
+    events.filter_map(move |event| async move {
+        match event {
+            DomainEvent::ProductCreated { product } | DomainEvent::ProductUpdated { product }
+                if product_id.is_none_or(|id| id == product.id) =>
+            {
+                Some(product.into())
+            }
+            _ => None,
+        }
+    })
+}
+
+/// Interval of the keep-alive comments on the SSE product stream.
+pub const SSE_HEARTBEAT_INTERVAL: Duration = Duration::from_secs(15);
+
+pub fn build_schema(
+    products: Arc<dyn ProductStore>,
+    users: UserRepository,
+    events: EventBus,
+    settings: &ServerSettings,
+    info: ServerInfo,
+    login: LoginService,
+    create_dedup: Arc<CreateDedup>,
+) -> GraphQLSchema {
+    let mut builder = Schema::build(Query, Mutation, Subscription)
+        .extension(RequestErrorCodes)
+        .extension(PersistedQueryErrorCodes)
+        .extension(QueryCostLogging)
+        .extension(QueryCostBudget(Arc::new(QueryCostBudgets::new(
+            settings.graphql_cost_budget,
+            settings.graphql_anonymous_cost_budget,
+        ))))
+        .extension(ApolloPersistedQueries::new(LruCacheStorage::new(settings.apq_cache_size)))
+        // Checked per operation, so every entry of a batch gets its own budget
+        .limit_depth(settings.graphql_max_depth)
+        .limit_complexity(settings.graphql_max_complexity)
+        .data(DataLoader::new(CategoryLoader { products: products.clone() }, tokio::spawn))
+        .data(products)
+        .data(users)
+        .data(events)
+        .data(info)
+        .data(login)
+        .data(create_dedup)
+        .data(settings.page_limits);
+    if settings.graphql_tracing {
+        // Per-resolver timings under `extensions.tracing`, in the Apollo
+        // tracing format
+        builder = builder.extension(ApolloTracing);
+    }
+
+    if settings.graphql_introspection {
+        builder.finish()
+    } else {
+        builder.disable_introspection().extension(RejectIntrospection).finish()
+    }
+}
+
+/// Runs a single or batched request; a batch answers with an array in the
+/// same order, each operation succeeding or failing on its own. `claims`
+/// from the bearer token and the client's address are attached to every
+/// operation. A request over `max_request_bytes` is refused as a whole,
+/// before any query is parsed.
+pub async fn execute_graphql(
+    schema: &GraphQLSchema,
+    request: BatchRequest,
+    claims: Option<Claims>,
+    client_ip: IpAddr,
+    max_batch_size: usize,
+    max_request_bytes: usize,
+) -> BatchResponse {
+    if request_size(&request) > max_request_bytes {
+        let error = AppError::PayloadTooLarge { limit: max_request_bytes }.extend();
+        let mut server_error = ServerError::new(error.message, None);
+        server_error.extensions = error.extensions;
+        return BatchResponse::Single(async_graphql::Response::from_errors(vec![server_error]));
+    }
+    if let BatchRequest::Batch(requests) = &request {
+        if requests.len() > max_batch_size {
+            let error = ServerError::new(
+                format!("batch of {} operations exceeds the limit of {}", requests.len(), max_batch_size),
+                None,
+            );
+            return BatchResponse::Single(async_graphql::Response::from_errors(vec![error]));
+        }
+    }
+
+    let request = request.data(ClientIp(client_ip));
+    let request = match claims {
+        Some(claims) => request.data(claims),
+        None => request,
+    };
+    in_phase("execute", schema.execute_batch(request)).await
+}
+
+/// Data shared by every operation on one `/graphql/ws` connection: its id
+/// for the logs, plus the `claims` and client address of the upgrade
+/// request, attached as `execute_graphql` does over HTTP.
+pub fn websocket_data(connection: SubscriptionConnection, claims: Option<Claims>, client_ip: IpAddr) -> async_graphql::Data {
+    let mut data = async_graphql::Data::default();
+    data.insert(connection);
+    data.insert(ClientIp(client_ip));
+    if let Some(claims) = claims {
+        data.insert(claims);
+    }
+    data
+}
+
+/// Whether `response` answers a single operation that failed to parse or
+/// validate, which the handlers send with `400` rather than `200`. In a
+/// batch each operation fails on its own, so a batch is always `200`.
+pub fn rejected_before_execution(response: &BatchResponse) -> bool {
+    let BatchResponse::Single(response) = response else {
+        return false;
+    };
+    response.data == async_graphql::Value::Null
+        && response.errors.iter().any(|err| {
+            err.extensions
+                .as_ref()
+                .and_then(|extensions| extensions.get("code"))
+                .is_some_and(|code| *code == async_graphql::Value::from(GRAPHQL_PARSE_ERROR))
+        })
+}
+
+/// Bytes of query text and variables, as JSON, across every operation.
+fn request_size(request: &BatchRequest) -> usize {
+    request
+        .iter()
+        .map(|request| {
+            let variables = serde_json::to_vec(&request.variables).map_or(0, |json| json.len());
+            request.query.len() + variables
+        })
+        .sum()
+}
\ No newline at end of file
@@ -0,0 +1,251 @@
+use async_graphql::extensions::{
+    Extension, ExtensionContext, ExtensionFactory, NextExecute, NextParseQuery, NextPrepareRequest, NextValidation,
+};
+use async_graphql::parser::types::{DocumentOperations, ExecutableDocument, Selection, SelectionSet};
+use async_graphql::{ErrorExtensions, Pos, Request, Response, ServerError, ServerResult, ValidationResult, Variables};
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+
+use crate::auth::Claims;
+use crate::client_ip::ClientIp;
+use crate::query_budget::QueryCostBudgets;
+
+/// Code of errors that stopped an operation before it ran: a document that
+/// doesn't parse, or one that fails validation against the schema or the
+/// depth and complexity limits. A single such operation is answered with
+/// `400`; execution errors stay `200`.
+pub const GRAPHQL_PARSE_ERROR: &str = "GRAPHQL_PARSE_ERROR";
+
+/// Tags parse and validation errors with `GRAPHQL_PARSE_ERROR`, leaving the
+/// codes other extensions set, such as `RATE_LIMITED`, alone. Must be
+/// registered first so it sees every other extension's errors.
+pub struct RequestErrorCodes;
+
+impl ExtensionFactory for RequestErrorCodes {
+    fn create(&self) -> Arc<dyn Extension> {
+        Arc::new(RequestErrorCodesExtension)
+    }
+}
+
+struct RequestErrorCodesExtension;
+
+fn tag_parse_error(mut err: ServerError) -> ServerError {
+    let extensions = err.extensions.get_or_insert_with(Default::default);
+    if extensions.get("code").is_none() {
+        extensions.set("code", GRAPHQL_PARSE_ERROR);
+    }
+    err
+}
+
+#[async_graphql::async_trait::async_trait]
+impl Extension for RequestErrorCodesExtension {
+    async fn parse_query(
+        &self,
+        ctx: &ExtensionContext<'_>,
+        query: &str,
+        variables: &Variables,
+        next: NextParseQuery<'_>,
+    ) -> ServerResult<ExecutableDocument> {
+        next.run(ctx, query, variables).await.map_err(tag_parse_error)
+    }
+
+    async fn validation(
+        &self,
+        ctx: &ExtensionContext<'_>,
+        next: NextValidation<'_>,
+    ) -> Result<ValidationResult, Vec<ServerError>> {
+        next.run(ctx)
+            .await
+            .map_err(|errors| errors.into_iter().map(tag_parse_error).collect())
+    }
+}
+
+/// Tags async-graphql's `PersistedQueryNotFound` error with the
+/// `PERSISTED_QUERY_NOT_FOUND` code that Apollo clients look for before
+/// resending the full query text. Must be registered before the APQ
+/// extension so it wraps it.
+pub struct PersistedQueryErrorCodes;
+
+impl ExtensionFactory for PersistedQueryErrorCodes {
+    fn create(&self) -> Arc<dyn Extension> {
+        Arc::new(PersistedQueryErrorCodesExtension)
+    }
+}
+
+struct PersistedQueryErrorCodesExtension;
+
+#[async_graphql::async_trait::async_trait]
+impl Extension for PersistedQueryErrorCodesExtension {
+    async fn prepare_request(
+        &self,
+        ctx: &ExtensionContext<'_>,
+        request: Request,
+        next: NextPrepareRequest<'_>,
+    ) -> ServerResult<Request> {
+        next.run(ctx, request).await.map_err(|mut err| {
+            if err.message == "PersistedQueryNotFound" {
+                err.extensions
+                    .get_or_insert_with(Default::default)
+                    .set("code", "PERSISTED_QUERY_NOT_FOUND");
+            }
+            err
+        })
+    }
+}
+
+/// With introspection disabled async-graphql quietly resolves `__schema` to
+/// null; this turns such queries into an `INTROSPECTION_DISABLED` error so
+/// clients can tell the schema is hidden rather than empty.
+pub struct RejectIntrospection;
+
+impl ExtensionFactory for RejectIntrospection {
+    fn create(&self) -> Arc<dyn Extension> {
+        Arc::new(RejectIntrospectionExtension)
+    }
+}
+
+struct RejectIntrospectionExtension;
+
+#[async_graphql::async_trait::async_trait]
+impl Extension for RejectIntrospectionExtension {
+    async fn parse_query(
+        &self,
+        ctx: &ExtensionContext<'_>,
+        query: &str,
+        variables: &Variables,
+        next: NextParseQuery<'_>,
+    ) -> ServerResult<ExecutableDocument> {
+        let document = next.run(ctx, query, variables).await?;
+        let introspects = document
+            .operations
+            .iter()
+            .any(|(_, operation)| selects_introspection(&document, &operation.node.selection_set.node));
+        if introspects {
+            let mut err = ServerError::new("introspection is disabled", None);
+            err.extensions
+                .get_or_insert_with(Default::default)
+                .set("code", "INTROSPECTION_DISABLED");
+            return Err(err);
+        }
+        Ok(document)
+    }
+}
+
+/// Looks for `__schema`/`__type` among the root fields, following fragments.
+fn selects_introspection(document: &ExecutableDocument, selection_set: &SelectionSet) -> bool {
+    selection_set.items.iter().any(|selection| match &selection.node {
+        Selection::Field(field) => matches!(field.node.name.node.as_str(), "__schema" | "__type"),
+        Selection::InlineFragment(fragment) => selects_introspection(document, &fragment.node.selection_set.node),
+        Selection::FragmentSpread(spread) => document
+            .fragments
+            .get(&spread.node.fragment_name.node)
+            .is_some_and(|fragment| selects_introspection(document, &fragment.node.selection_set.node)),
+    })
+}
+
+/// Charges each operation's complexity to its caller's per-minute budget and
+/// rejects it with `RATE_LIMITED` once that is spent. Callers are told apart
+/// by the `Claims` subject, or by client address when anonymous.
+pub struct QueryCostBudget(pub Arc<QueryCostBudgets>);
+
+impl ExtensionFactory for QueryCostBudget {
+    fn create(&self) -> Arc<dyn Extension> {
+        Arc::new(QueryCostBudgetExtension(self.0.clone()))
+    }
+}
+
+struct QueryCostBudgetExtension(Arc<QueryCostBudgets>);
+
+#[async_graphql::async_trait::async_trait]
+impl Extension for QueryCostBudgetExtension {
+    async fn validation(
+        &self,
+        ctx: &ExtensionContext<'_>,
+        next: NextValidation<'_>,
+    ) -> Result<ValidationResult, Vec<ServerError>> {
+        let result = next.run(ctx).await?;
+        let charged = match ctx.data_opt::<Claims>() {
+            Some(claims) => self.0.charge(&format!("user:{}", claims.sub), false, result.complexity),
+            None => {
+                let key = ctx
+                    .data_opt::<ClientIp>()
+                    .map_or_else(|| "anonymous".to_string(), |ClientIp(ip)| format!("ip:{}", ip));
+                self.0.charge(&key, true, result.complexity)
+            }
+        };
+        charged.map_err(|e| {
+            // The budget covers the whole operation, not a place in it
+            let mut err = e.extend().into_server_error(Pos::default());
+            err.locations.clear();
+            vec![err]
+        })?;
+        Ok(result)
+    }
+}
+
+/// Logs the complexity, depth and execution time of every executed
+/// operation, to help tune `GRAPHQL_MAX_COMPLEXITY` and `GRAPHQL_MAX_DEPTH`.
+/// Operations rejected by validation are not logged.
+pub struct QueryCostLogging;
+
+impl ExtensionFactory for QueryCostLogging {
+    fn create(&self) -> Arc<dyn Extension> {
+        Arc::new(QueryCostLoggingExtension::default())
+    }
+}
+
+#[derive(Default)]
+struct QueryCostLoggingExtension {
+    /// Name of the document's only named operation, used when the request
+    /// doesn't pick one.
+    document_operation: Mutex<Option<String>>,
+    validation: Mutex<Option<ValidationResult>>,
+}
+
+#[async_graphql::async_trait::async_trait]
+impl Extension for QueryCostLoggingExtension {
+    async fn parse_query(
+        &self,
+        ctx: &ExtensionContext<'_>,
+        query: &str,
+        variables: &Variables,
+        next: NextParseQuery<'_>,
+    ) -> ServerResult<ExecutableDocument> {
+        let document = next.run(ctx, query, variables).await?;
+        if let DocumentOperations::Multiple(operations) = &document.operations {
+            if operations.len() == 1 {
+                *self.document_operation.lock().unwrap() = operations.keys().next().map(|name| name.to_string());
+            }
+        }
+        Ok(document)
+    }
+
+    async fn validation(
+        &self,
+        ctx: &ExtensionContext<'_>,
+        next: NextValidation<'_>,
+    ) -> Result<ValidationResult, Vec<ServerError>> {
+        let result = next.run(ctx).await?;
+        *self.validation.lock().unwrap() = Some(result);
+        Ok(result)
+    }
+
+    async fn execute(&self, ctx: &ExtensionContext<'_>, operation_name: Option<&str>, next: NextExecute<'_>) -> Response {
+        let started = Instant::now();
+        let response = next.run(ctx, operation_name).await;
+        let elapsed = started.elapsed();
+
+        if let Some(validation) = self.validation.lock().unwrap().take() {
+            let document_operation = self.document_operation.lock().unwrap().take();
+            tracing::info!(
+                operation = operation_name.or(document_operation.as_deref()),
+                complexity = validation.complexity,
+                depth = validation.depth,
+                elapsed_ms = elapsed.as_millis() as u64,
+                errors = response.errors.len(),
+                "executed GraphQL operation"
+            );
+        }
+        response
+    }
+}
\ No newline at end of file
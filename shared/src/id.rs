@@ -0,0 +1,134 @@
+use std::fmt;
+use std::str::FromStr;
+use std::sync::OnceLock;
+
+use async_graphql::{InputValueError, InputValueResult, Scalar, ScalarType, Value};
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+use ulid::Ulid;
+use uuid::Uuid;
+
+/// How new ids are generated and how every id is written out.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum IdKind {
+    /// Random UUIDv4, e.g. `67e55044-10b1-426f-9247-bb680e5fe0c8`.
+    #[default]
+    Uuid,
+    /// Time-ordered ULID, e.g. `01ARZ3NDEKTSV4RRFFQ69G5FAV`, so new rows land
+    /// at the end of the primary key index instead of all over it.
+    Ulid,
+}
+
+impl IdKind {
+    /// Reads `ID_KIND`, defaulting to UUIDs, for tools that don't load a
+    /// full `Config`.
+    pub fn from_env() -> Self {
+        std::env::var("ID_KIND")
+            .ok()
+            .and_then(|value| value.parse().ok())
+            .unwrap_or_default()
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            IdKind::Uuid => "uuid",
+            IdKind::Ulid => "ulid",
+        }
+    }
+}
+
+impl FromStr for IdKind {
+    type Err = ();
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.to_ascii_lowercase().as_str() {
+            "uuid" => Ok(IdKind::Uuid),
+            "ulid" => Ok(IdKind::Ulid),
+            _ => Err(()),
+        }
+    }
+}
+
+static ID_KIND: OnceLock<IdKind> = OnceLock::new();
+
+/// Picks the kind of every id this process creates and prints. Call once at
+/// startup, before any id is made; later calls are ignored.
+pub fn init_id_kind(kind: IdKind) {
+    let _ = ID_KIND.set(kind);
+}
+
+fn id_kind() -> IdKind {
+    ID_KIND.get().copied().unwrap_or_default()
+}
+
+/// Id of a user or product. Either kind is 128 bits, stored in the same
+/// `UUID` column, so switching `ID_KIND` needs no migration: existing rows
+/// keep their value and are just written in the new form. Both forms are
+/// accepted when parsing, so ids handed out before a switch still resolve.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, sqlx::Type)]
+#[sqlx(transparent)]
+pub struct Id(Uuid);
+
+impl Id {
+    /// A fresh id of the configured kind.
+    #[allow(clippy::new_without_default)]
+    pub fn new() -> Self {
+        match id_kind() {
+            IdKind::Uuid => Self(Uuid::new_v4()),
+            IdKind::Ulid => Self(Ulid::new().into()),
+        }
+    }
+}
+
+impl From<Uuid> for Id {
+    fn from(uuid: Uuid) -> Self {
+        Self(uuid)
+    }
+}
+
+impl fmt::Display for Id {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match id_kind() {
+            IdKind::Uuid => self.0.fmt(f),
+            IdKind::Ulid => Ulid::from(self.0).fmt(f),
+        }
+    }
+}
+
+impl FromStr for Id {
+    type Err = ();
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        Uuid::parse_str(value)
+            .map(Self)
+            .or_else(|_| Ulid::from_string(value).map(|ulid| Self(ulid.into())))
+            .map_err(|_| ())
+    }
+}
+
+impl Serialize for Id {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for Id {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let value = String::deserialize(deserializer)?;
+        value.parse().map_err(|_| de::Error::custom(format!("invalid id: {}", value)))
+    }
+}
+
+/// A UUID or a ULID, written in the server's configured form.
+#[Scalar(name = "Id")]
+impl ScalarType for Id {
+    fn parse(value: Value) -> InputValueResult<Self> {
+        match &value {
+            Value::String(raw) => raw.parse().map_err(|_| InputValueError::custom("invalid id")),
+            _ => Err(InputValueError::expected_type(value)),
+        }
+    }
+
+    fn to_value(&self) -> Value {
+        Value::String(self.to_string())
+    }
+}
\ No newline at end of file
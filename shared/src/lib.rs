@@ -1,9 +1,57 @@
 pub mod models;
 pub mod auth;
+pub mod build_info;
+pub mod client_ip;
 pub mod graphql;
+pub mod graphql_extensions;
+pub mod id;
 pub mod error;
+pub mod db;
+pub mod dedup;
+pub mod config;
+pub mod pagination;
+pub mod query_budget;
+pub mod repository;
+pub mod cache;
+pub mod store;
+pub mod telemetry;
+pub mod throttle;
+pub mod tls;
+pub mod revocation;
+pub mod security_headers;
+pub mod low_stock;
+pub mod events;
+pub mod export;
+pub mod shopify;
+pub mod shutdown;
+pub mod storage;
+pub mod timestamp;
+pub mod validate;
 
 pub use models::*;
 pub use auth::*;
+pub use build_info::*;
+pub use client_ip::*;
 pub use graphql::*;
-pub use error::*;
\ No newline at end of file
+pub use graphql_extensions::*;
+pub use id::*;
+pub use error::*;
+pub use db::*;
+pub use dedup::*;
+pub use config::*;
+pub use pagination::*;
+pub use query_budget::*;
+pub use repository::*;
+pub use cache::*;
+pub use store::*;
+pub use throttle::*;
+pub use revocation::*;
+pub use security_headers::*;
+pub use low_stock::*;
+pub use events::*;
+pub use export::*;
+pub use shopify::*;
+pub use shutdown::*;
+pub use storage::*;
+pub use tls::*;
+pub use validate::*;
\ No newline at end of file
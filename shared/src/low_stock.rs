@@ -0,0 +1,91 @@
+use std::time::Duration;
+
+use chrono::Utc;
+use futures::StreamExt;
+use serde_json::json;
+
+use crate::events::{DomainEvent, EventBus};
+use crate::models::Product;
+
+const DELIVERY_ATTEMPTS: u32 = 3;
+const RETRY_BASE_DELAY: Duration = Duration::from_millis(500);
+const DELIVERY_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Posts a `product.low_stock` event to `LOW_STOCK_WEBHOOK_URL` whenever an
+/// `InventoryAdjusted` event lowers a product's inventory below the
+/// threshold. Delivery runs in a background task so the response never waits
+/// on the receiver.
+#[derive(Clone)]
+pub struct LowStockNotifier {
+    client: reqwest::Client,
+    webhook_url: Option<String>,
+    threshold: i32,
+}
+
+impl LowStockNotifier {
+    /// Without a `webhook_url` the notifier does nothing.
+    pub fn new(webhook_url: Option<String>, threshold: i32) -> Self {
+        let client = reqwest::Client::builder()
+            .timeout(DELIVERY_TIMEOUT)
+            .build()
+            .unwrap_or_default();
+        Self {
+            client,
+            webhook_url,
+            threshold,
+        }
+    }
+
+    /// Subscribes to `events`; does nothing without a webhook URL.
+    pub fn listen(self, events: &EventBus) {
+        if self.webhook_url.is_none() {
+            return;
+        }
+        let mut stream = Box::pin(events.subscribe());
+        tokio::spawn(async move {
+            while let Some(event) = stream.next().await {
+                if let DomainEvent::InventoryAdjusted { product, previous_inventory } = event {
+                    self.inventory_adjusted(&product, previous_inventory);
+                }
+            }
+        });
+    }
+
+    fn inventory_adjusted(&self, product: &Product, previous_inventory: i32) {
+        let Some(url) = self.webhook_url.clone() else {
+            return;
+        };
+        if product.inventory >= previous_inventory || product.inventory >= self.threshold {
+            return;
+        }
+
+        let event = json!({
+            "event": "product.low_stock",
+            "product_id": product.id,
+            "name": product.name,
+            "inventory": product.inventory,
+            "previous_inventory": previous_inventory,
+            "threshold": self.threshold,
+            "occurred_at": Utc::now(),
+        });
+        let client = self.client.clone();
+        let product_id = product.id;
+
+        tokio::spawn(async move {
+            for attempt in 0..DELIVERY_ATTEMPTS {
+                if attempt > 0 {
+                    tokio::time::sleep(RETRY_BASE_DELAY * 2u32.pow(attempt - 1)).await;
+                }
+                match client.post(&url).json(&event).send().await {
+                    Ok(response) if response.status().is_success() => return,
+                    Ok(response) => tracing::warn!(
+                        %product_id, attempt, status = response.status().as_u16(),
+                        "low stock webhook rejected"
+                    ),
+                    Err(e) => tracing::warn!(%product_id, attempt, error = %e, "low stock webhook failed"),
+                }
+            }
+            tracing::error!(%product_id, "giving up on low stock webhook after {} attempts", DELIVERY_ATTEMPTS);
+        });
+    }
+}
\ No newline at end of file
@@ -1,17 +1,48 @@
 use serde::{Deserialize, Serialize};
+use std::collections::hash_map::DefaultHasher;
+use std::fmt;
+use std::hash::{Hash, Hasher};
 use uuid::Uuid;
 use chrono::{DateTime, Utc};
 
-#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+use crate::error::{check_fields, AppError, AppResult, FieldError};
+use crate::id::Id;
+use crate::pagination::{Page, PageLimits};
+use crate::validate::Validate;
+
+#[derive(Clone, Serialize, Deserialize, sqlx::FromRow)]
 pub struct User {
-    pub id: Uuid,
+    pub id: Id,
     pub username: String,
     pub email: String,
+    /// Never serialized and redacted from `Debug`, so a `User` that ends up
+    /// in a response or a log line can't leak it.
+    #[serde(skip_serializing)]
     pub password_hash: String,
+    pub role: String,
+    #[serde(with = "crate::timestamp")]
     pub created_at: DateTime<Utc>,
+    #[serde(with = "crate::timestamp")]
     pub updated_at: DateTime<Utc>,
 }
 
+impl fmt::Debug for User {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("User")
+            .field("id", &self.id)
+            .field("username", &self.username)
+            .field("email", &self.email)
+            .field("password_hash", &"[redacted]")
+            .field("role", &self.role)
+            .field("created_at", &self.created_at)
+            .field("updated_at", &self.updated_at)
+            .finish()
+    }
+}
+
+/// Role with access to the admin endpoints.
+pub const ADMIN_ROLE: &str = "admin";
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CreateUser {
     pub username: String,
@@ -25,6 +56,14 @@ pub struct LoginRequest {
     pub password: String,
 }
 
+/// Query string of `POST /api/auth/login`.
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct LoginParams {
+    /// Also hand the token over in the auth cookie.
+    #[serde(default)]
+    pub set_cookie: bool,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct LoginResponse {
     pub token: String,
@@ -33,9 +72,10 @@ pub struct LoginResponse {
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct UserResponse {
-    pub id: Uuid,
+    pub id: Id,
     pub username: String,
     pub email: String,
+    #[serde(with = "crate::timestamp")]
     pub created_at: DateTime<Utc>,
 }
 
@@ -50,17 +90,60 @@ impl From<User> for UserResponse {
     }
 }
 
+/// Like `parse_product_id`, for user ids in URL paths.
+pub fn parse_user_id(raw: &str) -> AppResult<Id> {
+    raw.parse().map_err(|_| AppError::Validation("invalid user id".to_string()))
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
 pub struct Product {
-    pub id: Uuid,
+    pub id: Id,
     pub name: String,
     pub description: String,
     pub price: i64, // Price in cents
     pub inventory: i32,
+    /// Where the product's image was uploaded; `None` until one is.
+    pub image_url: Option<String>,
+    /// The Shopify product it was synced to; `None` until the first sync.
+    pub shopify_product_id: Option<i64>,
+    #[serde(with = "crate::timestamp")]
     pub created_at: DateTime<Utc>,
+    #[serde(with = "crate::timestamp")]
     pub updated_at: DateTime<Utc>,
 }
 
+/// Strong ETag for a product; it changes whenever the product is updated.
+pub fn etag_for(product: &Product) -> String {
+    let mut hasher = DefaultHasher::new();
+    product.id.hash(&mut hasher);
+    product.updated_at.hash(&mut hasher);
+    format!("\"{:016x}\"", hasher.finish())
+}
+
+/// Whether an `If-None-Match` header value matches `etag`, accepting `*`,
+/// comma-separated lists and weak validators.
+pub fn etag_matches(header: &str, etag: &str) -> bool {
+    header.split(',').map(str::trim).any(|candidate| {
+        candidate == "*" || candidate.trim_start_matches("W/") == etag
+    })
+}
+
+/// Whether an `If-Match` header value matches `etag`. Unlike `etag_matches`
+/// this is the strong comparison RFC 9110 requires for `If-Match`, so a weak
+/// validator never matches.
+pub fn etag_matches_strongly(header: &str, etag: &str) -> bool {
+    header
+        .split(',')
+        .map(str::trim)
+        .any(|candidate| candidate == "*" || candidate == etag)
+}
+
+/// Parses a product id taken from a URL path, so malformed ids get the
+/// shared validation error instead of a framework-default rejection.
+pub fn parse_product_id(raw: &str) -> AppResult<Id> {
+    raw.parse().map_err(|_| AppError::Validation("invalid product id".to_string()))
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CreateProduct {
     pub name: String,
@@ -75,13 +158,467 @@ pub struct UpdateProduct {
     pub description: Option<String>,
     pub price: Option<i64>,
     pub inventory: Option<i32>,
+    pub image_url: Option<String>,
+}
+
+/// A full replacement expressed as an update that sets every field but the
+/// image, which is only changed once a new one is uploaded.
+impl From<CreateProduct> for UpdateProduct {
+    fn from(input: CreateProduct) -> Self {
+        Self {
+            name: Some(input.name),
+            description: Some(input.description),
+            price: Some(input.price),
+            inventory: Some(input.inventory),
+            image_url: None,
+        }
+    }
+}
+
+impl Validate for CreateProduct {
+    /// Rejects a blank name and a negative price or inventory.
+    fn validate(&self) -> AppResult<()> {
+        check_fields(product_field_errors(Some(&self.name), Some(self.price), Some(self.inventory)))
+    }
+}
+
+impl Validate for UpdateProduct {
+    /// Same rules as for `CreateProduct` on the fields being changed; an
+    /// image URL must also be `http` or `https`.
+    fn validate(&self) -> AppResult<()> {
+        let mut errors = product_field_errors(self.name.as_deref(), self.price, self.inventory);
+        if self
+            .image_url
+            .as_deref()
+            .is_some_and(|url| !url.starts_with("https://") && !url.starts_with("http://"))
+        {
+            errors.push(FieldError::new("image_url", "image_url must be an http or https URL"));
+        }
+        check_fields(errors)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+pub struct Category {
+    pub id: Id,
+    pub name: String,
+    #[serde(with = "crate::timestamp")]
+    pub created_at: DateTime<Utc>,
 }
 
+/// Body of `POST /api/categories`.
 #[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CreateCategory {
+    pub name: String,
+}
+
+impl Validate for CreateCategory {
+    fn validate(&self) -> AppResult<()> {
+        let mut errors = Vec::new();
+        if self.name.trim().is_empty() {
+            errors.push(FieldError::new("name", "name must not be empty"));
+        }
+        check_fields(errors)
+    }
+}
+
+/// `parse_product_id` for the category id in
+/// `/api/products/{id}/categories/{category_id}`.
+pub fn parse_category_id(raw: &str) -> AppResult<Id> {
+    raw.parse().map_err(|_| AppError::Validation("invalid category id".to_string()))
+}
+
+/// Body of `GET /api/admin/stats`: totals over the whole catalog. Empty
+/// catalogs report zeros.
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+pub struct ProductStats {
+    pub total_products: i64,
+    pub total_inventory_units: i64,
+    /// Sum of `price * inventory`, saturated at `i64::MAX`: prices up to
+    /// `MAX_PRICE_CENTS` over large stocks can add up to more than that.
+    pub total_catalog_value_cents: i64,
+    /// Rounded to whole cents.
+    pub avg_price_cents: i64,
+    /// Products with no inventory left.
+    pub out_of_stock_count: i64,
+}
+
+/// Body of `POST /api/products/{id}/reserve`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReserveInventory {
+    pub quantity: i32,
+}
+
+impl Validate for ReserveInventory {
+    fn validate(&self) -> AppResult<()> {
+        let mut errors = Vec::new();
+        if self.quantity <= 0 {
+            errors.push(FieldError::new("quantity", "quantity must be positive"));
+        }
+        check_fields(errors)
+    }
+}
+
+/// Largest image an upload URL is signed for.
+pub const MAX_IMAGE_BYTES: u64 = 10 * 1024 * 1024;
+
+/// Body of `POST /api/products/{id}/image-upload-url`. The URL handed out
+/// only accepts an upload of exactly this type and size.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CreateImageUpload {
+    pub content_type: String,
+    pub content_length: u64,
+}
+
+impl Validate for CreateImageUpload {
+    fn validate(&self) -> AppResult<()> {
+        let mut errors = Vec::new();
+        if !is_image_content_type(&self.content_type) {
+            errors.push(FieldError::new("content_type", "content_type must be an image type such as image/png"));
+        }
+        if !(1..=MAX_IMAGE_BYTES).contains(&self.content_length) {
+            errors.push(FieldError::new(
+                "content_length",
+                format!("content_length must be between 1 and {} bytes", MAX_IMAGE_BYTES),
+            ));
+        }
+        check_fields(errors)
+    }
+}
+
+/// `image/` and a plain subtype, without parameters. SVG is refused: served
+/// from the bucket it could run scripts.
+fn is_image_content_type(value: &str) -> bool {
+    value.strip_prefix("image/").is_some_and(|subtype| {
+        !subtype.is_empty()
+            && subtype != "svg+xml"
+            && subtype.bytes().all(|byte| byte.is_ascii_alphanumeric() || b"+-.".contains(&byte))
+    })
+}
+
+/// Validates a bulk create, naming fields by item index (`[1].price`).
+pub fn validate_products(inputs: &[CreateProduct]) -> AppResult<()> {
+    let errors = inputs
+        .iter()
+        .enumerate()
+        .flat_map(|(index, input)| {
+            let prefix = format!("[{}]", index);
+            product_field_errors(Some(&input.name), Some(input.price), Some(input.inventory))
+                .into_iter()
+                .map(move |error| error.within(&prefix))
+        })
+        .collect();
+    check_fields(errors)
+}
+
+/// Highest accepted price, in cents (10 billion in currency units), far
+/// above any real product. It does not keep `price * inventory` within an
+/// `i64`: at the cap that takes fewer than 10 million units, so anything
+/// multiplying prices by quantities has to work in wider integers.
+pub const MAX_PRICE_CENTS: i64 = 1_000_000_000_000;
+
+fn product_field_errors(name: Option<&str>, price: Option<i64>, inventory: Option<i32>) -> Vec<FieldError> {
+    let mut errors = Vec::new();
+    if name.is_some_and(|name| name.trim().is_empty()) {
+        errors.push(FieldError::new("name", "name must not be empty"));
+    }
+    match price {
+        Some(price) if price < 0 => errors.push(FieldError::new("price", "price must not be negative")),
+        Some(price) if price > MAX_PRICE_CENTS => errors.push(FieldError::new(
+            "price",
+            format!("price must be at most {} cents", MAX_PRICE_CENTS),
+        )),
+        _ => {}
+    }
+    if inventory.is_some_and(|inventory| inventory < 0) {
+        errors.push(FieldError::new("inventory", "inventory must not be negative"));
+    }
+    errors
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
 pub struct ShopifyWebhook {
     pub id: Uuid,
+    pub shopify_webhook_id: String,
     pub webhook_type: String,
     pub payload: serde_json::Value,
     pub processed: bool,
+    #[serde(with = "crate::timestamp")]
     pub created_at: DateTime<Utc>,
+}
+
+/// Query string of `DELETE /api/products/{id}`.
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct DeleteParams {
+    /// Answer 204 even when there was nothing to delete.
+    #[serde(default)]
+    pub idempotent: bool,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct WebhookListParams {
+    pub processed: Option<bool>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RevokeSessionsResponse {
+    pub user_id: Id,
+    /// Unexpired tokens this server had issued to the user.
+    pub revoked_sessions: usize,
+}
+
+/// Total number of items across all pages of a list response.
+pub const TOTAL_COUNT_HEADER: &str = "x-total-count";
+
+/// Order of the product list, `?sort=`; ties are broken by id so pages
+/// never overlap.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SortOrder {
+    #[default]
+    Oldest,
+    Newest,
+    PriceAsc,
+    PriceDesc,
+    Name,
+}
+
+impl SortOrder {
+    pub fn name(self) -> &'static str {
+        match self {
+            SortOrder::Oldest => "oldest",
+            SortOrder::Newest => "newest",
+            SortOrder::PriceAsc => "price_asc",
+            SortOrder::PriceDesc => "price_desc",
+            SortOrder::Name => "name",
+        }
+    }
+
+    /// The SQL `ORDER BY` list for this order.
+    pub fn order_by(self) -> &'static str {
+        match self {
+            SortOrder::Oldest => "created_at, id",
+            SortOrder::Newest => "created_at DESC, id DESC",
+            SortOrder::PriceAsc => "price, created_at, id",
+            SortOrder::PriceDesc => "price DESC, created_at, id",
+            SortOrder::Name => "name, id",
+        }
+    }
+
+    /// `order_by` for products held in memory.
+    pub fn compare(self, a: &Product, b: &Product) -> std::cmp::Ordering {
+        match self {
+            SortOrder::Oldest => (a.created_at, a.id).cmp(&(b.created_at, b.id)),
+            SortOrder::Newest => (b.created_at, b.id).cmp(&(a.created_at, a.id)),
+            SortOrder::PriceAsc => (a.price, a.created_at, a.id).cmp(&(b.price, b.created_at, b.id)),
+            SortOrder::PriceDesc => b.price.cmp(&a.price).then((a.created_at, a.id).cmp(&(b.created_at, b.id))),
+            SortOrder::Name => (&a.name, a.id).cmp(&(&b.name, b.id)),
+        }
+    }
+}
+
+/// Which products a list includes, and in what order.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct ProductFilter {
+    /// Inclusive bounds on the price, in cents.
+    pub min_price: Option<i64>,
+    pub max_price: Option<i64>,
+    /// Only products assigned to this category.
+    pub category: Option<Id>,
+    pub sort: SortOrder,
+}
+
+impl ProductFilter {
+    /// The price bounds only; stores check `category` against their own
+    /// assignments.
+    pub fn matches(&self, product: &Product) -> bool {
+        self.min_price.is_none_or(|min| product.price >= min) && self.max_price.is_none_or(|max| product.price <= max)
+    }
+
+    /// `path` with the non-default parts as its query, e.g.
+    /// `/api/products?min_price=100&sort=price_desc`, for links to other
+    /// pages of the same list.
+    pub fn path_with_query(&self, path: &str) -> String {
+        let mut params = Vec::new();
+        if let Some(min) = self.min_price {
+            params.push(format!("min_price={}", min));
+        }
+        if let Some(max) = self.max_price {
+            params.push(format!("max_price={}", max));
+        }
+        if let Some(category) = self.category {
+            params.push(format!("category={}", category));
+        }
+        if self.sort != SortOrder::default() {
+            params.push(format!("sort={}", self.sort.name()));
+        }
+        if params.is_empty() {
+            path.to_string()
+        } else {
+            format!("{}?{}", path, params.join("&"))
+        }
+    }
+}
+
+/// `?limit=&offset=&min_price=&max_price=&category=&sort=` for the product
+/// list.
+#[derive(Debug, Clone, Deserialize)]
+pub struct ProductListParams {
+    pub limit: Option<i64>,
+    pub offset: Option<i64>,
+    pub min_price: Option<i64>,
+    pub max_price: Option<i64>,
+    /// Category id.
+    pub category: Option<Id>,
+    pub sort: Option<SortOrder>,
+}
+
+impl ProductListParams {
+    pub fn page(&self, limits: PageLimits) -> AppResult<Page> {
+        Page::parse(self.limit, self.offset, limits)
+    }
+
+    /// Rejects negative prices and a `min_price` above `max_price`.
+    pub fn filter(&self) -> AppResult<ProductFilter> {
+        let mut errors = Vec::new();
+        for (field, price) in [("min_price", self.min_price), ("max_price", self.max_price)] {
+            if price.is_some_and(|price| price < 0) {
+                errors.push(FieldError::new(field, "must not be negative"));
+            }
+        }
+        if let (Some(min), Some(max)) = (self.min_price, self.max_price) {
+            if min > max {
+                errors.push(FieldError::new("min_price", "must not exceed max_price"));
+            }
+        }
+        check_fields(errors)?;
+        Ok(ProductFilter {
+            min_price: self.min_price,
+            max_price: self.max_price,
+            category: self.category,
+            sort: self.sort.unwrap_or_default(),
+        })
+    }
+}
+
+/// `?limit=&offset=` for the admin user list.
+#[derive(Debug, Clone, Deserialize)]
+pub struct UserListParams {
+    pub limit: Option<i64>,
+    pub offset: Option<i64>,
+}
+
+impl UserListParams {
+    pub fn page(&self, limits: PageLimits) -> AppResult<Page> {
+        Page::parse(self.limit, self.offset, limits)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn user() -> User {
+        let now = Utc.with_ymd_and_hms(2025, 8, 1, 12, 0, 0).unwrap();
+        User {
+            id: Id::new(),
+            username: "ada".to_string(),
+            email: "ada@example.com".to_string(),
+            password_hash: "$2b$04$secrethashsecrethashsecrethashsecrethashsecrethash".to_string(),
+            role: "user".to_string(),
+            created_at: now,
+            updated_at: now,
+        }
+    }
+
+    #[test]
+    fn serialized_users_have_no_password_hash() {
+        let json = serde_json::to_value(user()).unwrap();
+        let fields: Vec<&str> = json.as_object().unwrap().keys().map(String::as_str).collect();
+        assert_eq!(fields, ["created_at", "email", "id", "role", "updated_at", "username"]);
+        assert!(!json.to_string().contains("secrethash"));
+    }
+
+    #[test]
+    fn debug_output_redacts_the_password_hash() {
+        let debug = format!("{:?}", user());
+        assert!(debug.contains("password_hash: \"[redacted]\""), "{}", debug);
+        assert!(!debug.contains("secrethash"), "{}", debug);
+    }
+
+    fn price_errors(price: i64) -> Vec<String> {
+        let product = CreateProduct { name: "Lamp".to_string(), description: String::new(), price, inventory: 1 };
+        match product.validate() {
+            Ok(()) => Vec::new(),
+            Err(AppError::ValidationFields(errors)) => {
+                errors.into_iter().map(|error| format!("{}: {}", error.field, error.message)).collect()
+            }
+            Err(other) => panic!("unexpected error for {}: {:?}", price, other),
+        }
+    }
+
+    #[test]
+    fn prices_must_be_within_bounds() {
+        assert!(price_errors(0).is_empty());
+        assert!(price_errors(MAX_PRICE_CENTS).is_empty());
+        let too_high = ["price: price must be at most 1000000000000 cents"];
+        assert_eq!(price_errors(MAX_PRICE_CENTS + 1), too_high);
+        assert_eq!(price_errors(i64::MAX), too_high);
+        let negative = ["price: price must not be negative"];
+        assert_eq!(price_errors(-1), negative);
+        assert_eq!(price_errors(i64::MIN), negative);
+    }
+
+    #[test]
+    fn updates_check_the_price_bounds_too() {
+        for price in [i64::MAX, -1] {
+            let update =
+                UpdateProduct { name: None, description: None, price: Some(price), inventory: None, image_url: None };
+            assert!(matches!(update.validate(), Err(AppError::ValidationFields(_))), "{}", price);
+        }
+    }
+
+    #[test]
+    fn prices_beyond_i64_fail_to_deserialize_instead_of_wrapping() {
+        let body = |price: &str| format!(r#"{{"name": "Lamp", "description": "", "price": {}, "inventory": 1}}"#, price);
+        let parsed: CreateProduct = serde_json::from_str(&body("9223372036854775807")).unwrap();
+        assert_eq!(parsed.price, i64::MAX);
+        assert!(parsed.validate().is_err());
+        assert!(serde_json::from_str::<CreateProduct>(&body("9223372036854775808")).is_err());
+    }
+
+    #[test]
+    fn image_uploads_take_image_types_up_to_the_size_cap() {
+        let upload = |content_type: &str, content_length| {
+            CreateImageUpload { content_type: content_type.to_string(), content_length }.validate().is_ok()
+        };
+        assert!(upload("image/png", 1));
+        assert!(upload("image/vnd.microsoft.icon", MAX_IMAGE_BYTES));
+        let refused = ["text/html", "image/", "image/svg+xml", "image/png; charset=x", "IMAGE/PNG", "image/png\r\n"];
+        for content_type in refused {
+            assert!(!upload(content_type, 1), "{:?}", content_type);
+        }
+        assert!(!upload("image/png", 0));
+        assert!(!upload("image/png", MAX_IMAGE_BYTES + 1));
+    }
+
+    #[test]
+    fn if_none_match_accepts_weak_tags_and_lists() {
+        assert!(etag_matches("\"abc\"", "\"abc\""));
+        assert!(etag_matches("W/\"abc\"", "\"abc\""));
+        assert!(etag_matches("\"x\", W/\"abc\"", "\"abc\""));
+        assert!(etag_matches("*", "\"abc\""));
+        assert!(!etag_matches("\"abd\"", "\"abc\""));
+    }
+
+    #[test]
+    fn if_match_rejects_weak_tags() {
+        assert!(etag_matches_strongly("\"abc\"", "\"abc\""));
+        assert!(etag_matches_strongly("\"x\", \"abc\"", "\"abc\""));
+        assert!(etag_matches_strongly("*", "\"abc\""));
+        assert!(!etag_matches_strongly("W/\"abc\"", "\"abc\""));
+        assert!(!etag_matches_strongly("\"x\", W/\"abc\"", "\"abc\""));
+        assert!(!etag_matches_strongly("", "\"abc\""));
+    }
 }
\ No newline at end of file
@@ -0,0 +1,128 @@
+use serde::Serialize;
+
+use crate::config::EnvReader;
+use crate::error::{AppError, AppResult};
+
+/// Set to `true` on a list response whose requested page size was above
+/// `MAX_PAGE_SIZE` and was cut down to it.
+pub const PAGINATION_CLAMPED_HEADER: &str = "x-pagination-clamped";
+
+/// Default and largest page size of every list, REST and GraphQL.
+#[derive(Debug, Clone, Copy)]
+pub struct PageLimits {
+    pub default: i64,
+    pub max: i64,
+}
+
+impl PageLimits {
+    pub fn read(env: &mut EnvReader) -> Self {
+        let limits = Self {
+            default: env.var("DEFAULT_PAGE_SIZE", 20),
+            max: env.var("MAX_PAGE_SIZE", 100),
+        };
+        env.check(limits.default >= 1, "DEFAULT_PAGE_SIZE must be at least 1");
+        env.check(
+            limits.default <= limits.max,
+            "DEFAULT_PAGE_SIZE must not be larger than MAX_PAGE_SIZE",
+        );
+        limits
+    }
+
+    /// `requested`, or the default when unset, cut down to `max`;
+    /// `Validation` naming `param` below 1.
+    pub fn limit(&self, param: &str, requested: Option<i64>) -> AppResult<i64> {
+        let limit = requested.unwrap_or(self.default);
+        if limit < 1 {
+            return Err(AppError::Validation(format!("{} must be at least 1", param)));
+        }
+        Ok(limit.min(self.max))
+    }
+
+    /// Whether `limit` cuts `requested` down.
+    pub fn clamps(&self, requested: Option<i64>) -> bool {
+        requested.is_some_and(|requested| requested > self.max)
+    }
+}
+
+/// A validated limit/offset window, shared by every list endpoint so they
+/// all apply the same defaults and bounds.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Page {
+    pub limit: i64,
+    pub offset: i64,
+    /// The requested limit was above the maximum; answer with
+    /// `PAGINATION_CLAMPED_HEADER`.
+    pub clamped: bool,
+}
+
+impl Page {
+    /// Applies the defaults of `limits` and clamps the limit to their
+    /// maximum; rejects a limit below 1, a negative offset and one so large
+    /// that `offset + limit` would overflow.
+    pub fn parse(limit: Option<i64>, offset: Option<i64>, limits: PageLimits) -> AppResult<Self> {
+        let clamped = limits.clamps(limit);
+        let limit = limits.limit("limit", limit)?;
+        let offset = offset.unwrap_or(0);
+        if offset < 0 {
+            return Err(AppError::Validation("offset must not be negative".to_string()));
+        }
+        let max_offset = i64::MAX - limits.max;
+        if offset > max_offset {
+            return Err(AppError::Validation(format!("offset must be at most {}", max_offset)));
+        }
+        Ok(Self { limit, offset, clamped })
+    }
+
+    /// `Link` header value with `rel="next"` and `rel="prev"` entries for
+    /// this page of `path`; `None` on a page with neither, i.e. the only one.
+    /// A query already on `path`, such as list filters, is kept. URLs are
+    /// relative, so they stay correct behind a proxy.
+    pub fn links(&self, path: &str, total: i64) -> Option<String> {
+        let Self { limit, offset, .. } = *self;
+        let separator = if path.contains('?') { '&' } else { '?' };
+        let mut links = Vec::new();
+        if offset + limit < total {
+            links.push(format!("<{}{}limit={}&offset={}>; rel=\"next\"", path, separator, limit, offset + limit));
+        }
+        if offset > 0 {
+            // Past the end, step back to the last page rather than an empty one
+            let prev = (offset - limit).min(total - limit).max(0);
+            links.push(format!("<{}{}limit={}&offset={}>; rel=\"prev\"", path, separator, limit, prev));
+        }
+        (!links.is_empty()).then(|| links.join(", "))
+    }
+}
+
+/// Body of a paginated REST list: one page of `items` plus where it sits in
+/// the `total`.
+#[derive(Debug, Clone, Serialize)]
+pub struct Paginated<T> {
+    pub items: Vec<T>,
+    pub total: i64,
+    pub limit: i64,
+    pub offset: i64,
+    /// Whether items follow this page.
+    pub has_more: bool,
+}
+
+impl<T> Paginated<T> {
+    pub fn new(items: Vec<T>, total: i64, page: Page) -> Self {
+        Self {
+            items,
+            total,
+            limit: page.limit,
+            offset: page.offset,
+            has_more: page.offset + page.limit < total,
+        }
+    }
+
+    pub fn map<U>(self, f: impl FnMut(T) -> U) -> Paginated<U> {
+        Paginated {
+            items: self.items.into_iter().map(f).collect(),
+            total: self.total,
+            limit: self.limit,
+            offset: self.offset,
+            has_more: self.has_more,
+        }
+    }
+}
\ No newline at end of file
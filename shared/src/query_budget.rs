@@ -0,0 +1,81 @@
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use crate::error::{AppError, AppResult};
+
+const SHARDS: usize = 16;
+
+/// Beyond this many tracked callers per shard, fully refilled entries are
+/// dropped so the map can't grow without bound.
+const MAX_KEYS_PER_SHARD: usize = 1_000;
+
+const WINDOW: Duration = Duration::from_secs(60);
+
+/// Cost one caller has spent, draining back at the budget's rate so that at
+/// most one budget's worth is spent in any rolling minute.
+struct Spent {
+    cost: f64,
+    at: Instant,
+}
+
+impl Spent {
+    fn drained(&self, budget: u64, now: Instant) -> f64 {
+        let refund = now.duration_since(self.at).as_secs_f64() / WINDOW.as_secs_f64() * budget as f64;
+        (self.cost - refund).max(0.0)
+    }
+}
+
+/// Per-minute GraphQL query cost budgets, keyed by user id for signed-in
+/// callers and by client address for anonymous ones. The map is split into
+/// independently locked shards so concurrent requests rarely contend.
+pub struct QueryCostBudgets {
+    user_budget: u64,
+    anonymous_budget: u64,
+    shards: Vec<Mutex<HashMap<String, Spent>>>,
+}
+
+impl QueryCostBudgets {
+    /// A zero budget leaves that kind of caller unlimited.
+    pub fn new(user_budget: u64, anonymous_budget: u64) -> Self {
+        Self {
+            user_budget,
+            anonymous_budget,
+            shards: (0..SHARDS).map(|_| Mutex::new(HashMap::new())).collect(),
+        }
+    }
+
+    /// Charges `cost` to `key`, failing with `RateLimited` instead when the
+    /// caller's budget is already used up. An operation is admitted while
+    /// anything is left, so one within `GRAPHQL_MAX_COMPLEXITY` can always
+    /// run eventually.
+    pub fn charge(&self, key: &str, anonymous: bool, cost: usize) -> AppResult<()> {
+        let budget = if anonymous { self.anonymous_budget } else { self.user_budget };
+        if budget == 0 {
+            return Ok(());
+        }
+        let now = Instant::now();
+        let mut shard = self.shard(key).lock().unwrap();
+        if shard.len() >= MAX_KEYS_PER_SHARD {
+            shard.retain(|_, spent| spent.drained(budget, now) > 0.0);
+        }
+        let spent = shard.get(key).map_or(0.0, |spent| spent.drained(budget, now));
+        if spent >= budget as f64 {
+            let excess = spent - budget as f64;
+            return Err(AppError::RateLimited {
+                retry_after: WINDOW.mul_f64(excess / budget as f64).max(Duration::from_millis(1)),
+                limit: budget,
+            });
+        }
+        shard.insert(key.to_string(), Spent { cost: spent + cost as f64, at: now });
+        Ok(())
+    }
+
+    fn shard(&self, key: &str) -> &Mutex<HashMap<String, Spent>> {
+        let mut hasher = DefaultHasher::new();
+        key.hash(&mut hasher);
+        &self.shards[hasher.finish() as usize % SHARDS]
+    }
+}
\ No newline at end of file
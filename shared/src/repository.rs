@@ -0,0 +1,620 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use futures::future::BoxFuture;
+use futures::{Stream, StreamExt};
+use sqlx::{PgExecutor, PgPool, Postgres, Transaction};
+use tokio::sync::mpsc;
+use uuid::Uuid;
+
+use crate::cache::ProductReadCache;
+use crate::db::QueryTimer;
+use crate::error::{AppError, AppResult, FieldError};
+use crate::id::Id;
+use crate::models::{
+    etag_for, etag_matches_strongly, Category, CreateCategory, CreateProduct, Product, ProductFilter, ProductStats, ShopifyWebhook,
+    UpdateProduct, User,
+};
+
+/// Most products one bulk create may insert.
+pub const MAX_BULK_PRODUCTS: usize = 100;
+
+/// Rows `ProductRepository::stream_all` reads ahead of a slow consumer.
+const STREAM_BUFFER_ROWS: usize = 64;
+
+/// `WHERE` clause of a `ProductFilter`: the price bounds bound as `$1` and
+/// `$2`, the category as `$3`. An unset one is `NULL` and matches everything.
+const PRODUCT_FILTER: &str = "($1::BIGINT IS NULL OR price >= $1) AND ($2::BIGINT IS NULL OR price <= $2) \
+    AND ($3::UUID IS NULL OR EXISTS (\
+        SELECT 1 FROM product_categories pc WHERE pc.product_id = products.id AND pc.category_id = $3))";
+
+/// Runs `f` in a transaction that commits when it returns `Ok` and rolls back
+/// on `Err`. The closure's error is returned unchanged, so a `Validation` or
+/// `NotFound` raised halfway through is not reported as a database error.
+///
+/// The closure returns a boxed future borrowing the transaction:
+/// `with_transaction(&pool, |tx| Box::pin(async move { ... }))`.
+pub async fn with_transaction<T, F>(pool: &PgPool, f: F) -> AppResult<T>
+where
+    F: for<'t> FnOnce(&'t mut Transaction<'static, Postgres>) -> BoxFuture<'t, AppResult<T>>,
+{
+    let mut tx = pool.begin().await?;
+    match f(&mut tx).await {
+        Ok(value) => {
+            tx.commit().await?;
+            Ok(value)
+        }
+        Err(e) => {
+            if let Err(rollback_error) = tx.rollback().await {
+                tracing::warn!(error = %rollback_error, "failed to roll back transaction");
+            }
+            Err(e)
+        }
+    }
+}
+
+/// An updated product together with its inventory before the update.
+#[derive(Debug, sqlx::FromRow)]
+pub struct ProductChange {
+    #[sqlx(flatten)]
+    pub product: Product,
+    pub previous_inventory: i32,
+}
+
+#[derive(Clone)]
+pub struct ProductRepository {
+    pool: PgPool,
+    timer: QueryTimer,
+    cache: Arc<ProductReadCache>,
+}
+
+impl ProductRepository {
+    /// Reads by id are not cached; see `with_read_cache`.
+    pub fn new(pool: PgPool, timer: QueryTimer) -> Self {
+        Self {
+            pool,
+            timer,
+            cache: Arc::new(ProductReadCache::new(Duration::ZERO)),
+        }
+    }
+
+    /// Shares concurrent `find_by_id` lookups of one product and reuses the
+    /// row for `ttl`. Writes through this repository invalidate the entry;
+    /// writes from elsewhere show up once it expires.
+    pub fn with_read_cache(self, ttl: Duration) -> Self {
+        Self {
+            cache: Arc::new(ProductReadCache::new(ttl)),
+            ..self
+        }
+    }
+
+    pub async fn list(&self) -> AppResult<Vec<Product>> {
+        let query = sqlx::query_as::<_, Product>("SELECT * FROM products ORDER BY created_at, id")
+            .fetch_all(&self.pool);
+        let products = self.timer.time("products.list", query).await?;
+        Ok(products)
+    }
+
+    /// One page of the products `filter` matches, in its order.
+    pub async fn list_page(&self, filter: &ProductFilter, limit: i64, offset: i64) -> AppResult<Vec<Product>> {
+        let sql = format!(
+            "SELECT * FROM products WHERE {} ORDER BY {} LIMIT $4 OFFSET $5",
+            PRODUCT_FILTER,
+            filter.sort.order_by()
+        );
+        let query = sqlx::query_as::<_, Product>(&sql)
+            .bind(filter.min_price)
+            .bind(filter.max_price)
+            .bind(filter.category)
+            .bind(limit)
+            .bind(offset)
+            .fetch_all(&self.pool);
+        let products = self.timer.time("products.list_page", query).await?;
+        Ok(products)
+    }
+
+    /// Every product in `list` order, read from a database cursor on a
+    /// spawned task. At most `STREAM_BUFFER_ROWS` rows are held at a time,
+    /// and dropping the stream stops the read.
+    pub fn stream_all(&self) -> impl Stream<Item = AppResult<Product>> + Send + 'static {
+        let (sender, receiver) = mpsc::channel(STREAM_BUFFER_ROWS);
+        let pool = self.pool.clone();
+        tokio::spawn(async move {
+            let mut rows = sqlx::query_as::<_, Product>("SELECT * FROM products ORDER BY created_at, id")
+                .fetch(&pool);
+            while let Some(row) = rows.next().await {
+                let failed = row.is_err();
+                if sender.send(row.map_err(AppError::from)).await.is_err() || failed {
+                    break;
+                }
+            }
+        });
+        futures::stream::unfold(receiver, |mut receiver| async move {
+            receiver.recv().await.map(|row| (row, receiver))
+        })
+    }
+
+    /// How many products `filter` matches.
+    pub async fn count(&self, filter: &ProductFilter) -> AppResult<i64> {
+        let sql = format!("SELECT COUNT(*) FROM products WHERE {}", PRODUCT_FILTER);
+        let query = sqlx::query_scalar(&sql)
+            .bind(filter.min_price)
+            .bind(filter.max_price)
+            .bind(filter.category)
+            .fetch_one(&self.pool);
+        let count = self.timer.time("products.count", query).await?;
+        Ok(count)
+    }
+
+    pub async fn find_by_id(&self, id: Id) -> AppResult<Option<Product>> {
+        self.cache.get_or_load(id, self.load_by_id(id)).await
+    }
+
+    async fn load_by_id(&self, id: Id) -> AppResult<Option<Product>> {
+        let query = sqlx::query_as::<_, Product>("SELECT * FROM products WHERE id = $1")
+            .bind(id)
+            .fetch_optional(&self.pool);
+        let product = self.timer.time("products.find_by_id", query).await?;
+        Ok(product)
+    }
+
+    pub async fn with_transaction<T, F>(&self, f: F) -> AppResult<T>
+    where
+        F: for<'t> FnOnce(&'t mut Transaction<'static, Postgres>) -> BoxFuture<'t, AppResult<T>>,
+    {
+        with_transaction(&self.pool, f).await
+    }
+
+    pub async fn create(&self, input: CreateProduct) -> AppResult<Product> {
+        insert_product(&self.pool, self.timer, input).await
+    }
+
+    /// Creates all products or none of them, returned in input order.
+    pub async fn create_many(&self, inputs: Vec<CreateProduct>) -> AppResult<Vec<Product>> {
+        check_bulk_size(inputs.len())?;
+
+        let timer = self.timer;
+        self.with_transaction(|tx| {
+            Box::pin(async move {
+                let mut products = Vec::with_capacity(inputs.len());
+                for input in inputs {
+                    products.push(insert_product(&mut **tx, timer, input).await?);
+                }
+                Ok(products)
+            })
+        })
+        .await
+    }
+
+    /// Also reports the inventory the row had before the update, read in the
+    /// same statement so concurrent updates can't skew it.
+    pub async fn update(&self, id: Id, input: UpdateProduct) -> AppResult<Option<ProductChange>> {
+        let change = update_product(&self.pool, self.timer, id, input).await?;
+        self.cache.invalidate(id);
+        Ok(change)
+    }
+
+    /// `update` only if `if_match` matches the product's current ETag. The
+    /// row stays locked from the comparison to the write, so a concurrent
+    /// update can't slip in between.
+    pub async fn update_if_match(
+        &self,
+        id: Id,
+        input: UpdateProduct,
+        if_match: &str,
+    ) -> AppResult<Option<ProductChange>> {
+        let timer = self.timer;
+        let if_match = if_match.to_string();
+        let change = self
+            .with_transaction(|tx| {
+                Box::pin(async move {
+                    let query = sqlx::query_as::<_, Product>("SELECT * FROM products WHERE id = $1 FOR UPDATE")
+                        .bind(id)
+                        .fetch_optional(&mut **tx);
+                    let Some(current) = timer.time("products.lock", query).await? else {
+                        return Ok(None);
+                    };
+                    check_if_match(&if_match, &current)?;
+                    update_product(&mut **tx, timer, id, input).await
+                })
+            })
+            .await?;
+        self.cache.invalidate(id);
+        Ok(change)
+    }
+
+    /// Takes `quantity` units out of stock in a single conditional update, so
+    /// concurrent reservations can never drive the inventory below zero. Nor
+    /// can putting units back push it past the INTEGER column.
+    pub async fn reserve_inventory(&self, id: Id, quantity: i32) -> AppResult<Product> {
+        let query = sqlx::query_as::<_, Product>(
+            "UPDATE products SET inventory = inventory - $2, updated_at = $3
+             WHERE id = $1 AND inventory >= $2 AND inventory::BIGINT - $2 <= 2147483647
+             RETURNING *",
+        )
+        .bind(id)
+        .bind(quantity)
+        .bind(Utc::now())
+        .fetch_optional(&self.pool);
+        let reserved = self.timer.time("products.reserve_inventory", query).await?;
+        self.cache.invalidate(id);
+        match reserved {
+            Some(product) => Ok(product),
+            None => Err(self.reservation_failure(id, quantity).await),
+        }
+    }
+
+    /// Why a reservation matched no row: the product is gone, short on stock,
+    /// or would end up with more than the column holds.
+    async fn reservation_failure(&self, id: Id, quantity: i32) -> AppError {
+        match self.find_by_id(id).await {
+            Ok(Some(product)) if product.inventory < quantity => {
+                AppError::Validation("insufficient inventory".to_string())
+            }
+            Ok(Some(_)) => AppError::Validation("inventory is out of range".to_string()),
+            Ok(None) => AppError::NotFound("product".to_string()),
+            Err(e) => e,
+        }
+    }
+
+    /// Returns whether a row was actually removed.
+    pub async fn delete(&self, id: Id) -> AppResult<bool> {
+        let query = sqlx::query("DELETE FROM products WHERE id = $1")
+            .bind(id)
+            .execute(&self.pool);
+        let result = self.timer.time("products.delete", query).await?;
+        self.cache.invalidate(id);
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// One aggregate over every product. `price * inventory` is summed as
+    /// NUMERIC so nothing overflows, then capped to fit the BIGINT.
+    pub async fn stats(&self) -> AppResult<ProductStats> {
+        let query = sqlx::query_as::<_, ProductStats>(
+            "SELECT COUNT(*) AS total_products,
+                    COALESCE(SUM(inventory), 0)::BIGINT AS total_inventory_units,
+                    LEAST(COALESCE(SUM(price::NUMERIC * inventory), 0), 9223372036854775807)::BIGINT
+                        AS total_catalog_value_cents,
+                    COALESCE(ROUND(AVG(price)), 0)::BIGINT AS avg_price_cents,
+                    COUNT(*) FILTER (WHERE inventory <= 0) AS out_of_stock_count
+             FROM products",
+        )
+        .fetch_one(&self.pool);
+        let stats = self.timer.time("products.stats", query).await?;
+        Ok(stats)
+    }
+
+    /// Returns whether the product exists.
+    pub async fn link_shopify_product(&self, id: Id, shopify_product_id: i64) -> AppResult<bool> {
+        let query = sqlx::query("UPDATE products SET shopify_product_id = $2 WHERE id = $1")
+            .bind(id)
+            .bind(shopify_product_id)
+            .execute(&self.pool);
+        let result = self.timer.time("products.link_shopify_product", query).await?;
+        self.cache.invalidate(id);
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Every category, by name.
+    pub async fn list_categories(&self) -> AppResult<Vec<Category>> {
+        let query = sqlx::query_as::<_, Category>("SELECT * FROM categories ORDER BY name").fetch_all(&self.pool);
+        let categories = self.timer.time("categories.list", query).await?;
+        Ok(categories)
+    }
+
+    /// A `name` field error when a category of that name exists.
+    pub async fn create_category(&self, input: CreateCategory) -> AppResult<Category> {
+        let query = sqlx::query_as::<_, Category>(
+            "INSERT INTO categories (id, name, created_at) VALUES ($1, $2, $3)
+             ON CONFLICT (name) DO NOTHING
+             RETURNING *",
+        )
+        .bind(Id::new())
+        .bind(input.name)
+        .bind(Utc::now())
+        .fetch_optional(&self.pool);
+        self.timer
+            .time("categories.insert", query)
+            .await?
+            .ok_or_else(duplicate_category)
+    }
+
+    /// The categories of each of `product_ids`, by name, in one query.
+    /// Products without any are left out of the map.
+    pub async fn categories_of(&self, product_ids: &[Id]) -> AppResult<HashMap<Id, Vec<Category>>> {
+        let query = sqlx::query_as::<_, ProductCategory>(
+            "SELECT pc.product_id, c.* FROM product_categories pc
+             JOIN categories c ON c.id = pc.category_id
+             WHERE pc.product_id = ANY($1)
+             ORDER BY c.name",
+        )
+        .bind(product_ids)
+        .fetch_all(&self.pool);
+        let rows = self.timer.time("categories.of_products", query).await?;
+        let mut categories: HashMap<Id, Vec<Category>> = HashMap::new();
+        for row in rows {
+            categories.entry(row.product_id).or_default().push(row.category);
+        }
+        Ok(categories)
+    }
+
+    /// Assigning a category twice is not an error. `NotFound` names the
+    /// product or the category when either doesn't exist.
+    pub async fn assign_category(&self, product_id: Id, category_id: Id) -> AppResult<()> {
+        let query = sqlx::query(
+            "INSERT INTO product_categories (product_id, category_id)
+             SELECT p.id, c.id FROM products p, categories c WHERE p.id = $1 AND c.id = $2
+             ON CONFLICT DO NOTHING",
+        )
+        .bind(product_id)
+        .bind(category_id)
+        .execute(&self.pool);
+        if self.timer.time("categories.assign", query).await?.rows_affected() > 0 {
+            return Ok(());
+        }
+        // Nothing inserted: either already assigned or something is missing
+        let query = sqlx::query_as::<_, (bool, bool)>(
+            "SELECT EXISTS (SELECT 1 FROM products WHERE id = $1), EXISTS (SELECT 1 FROM categories WHERE id = $2)",
+        )
+        .bind(product_id)
+        .bind(category_id)
+        .fetch_one(&self.pool);
+        match self.timer.time("categories.assign_check", query).await? {
+            (false, _) => Err(AppError::NotFound("product".to_string())),
+            (_, false) => Err(AppError::NotFound("category".to_string())),
+            _ => Ok(()),
+        }
+    }
+
+    /// Returns whether the category was assigned.
+    pub async fn unassign_category(&self, product_id: Id, category_id: Id) -> AppResult<bool> {
+        let query = sqlx::query("DELETE FROM product_categories WHERE product_id = $1 AND category_id = $2")
+            .bind(product_id)
+            .bind(category_id)
+            .execute(&self.pool);
+        let result = self.timer.time("categories.unassign", query).await?;
+        Ok(result.rows_affected() > 0)
+    }
+}
+
+/// A category together with one product it is assigned to.
+#[derive(sqlx::FromRow)]
+struct ProductCategory {
+    product_id: Id,
+    #[sqlx(flatten)]
+    category: Category,
+}
+
+/// The error every `ProductStore::create_category` gives for a taken name.
+pub(crate) fn duplicate_category() -> AppError {
+    AppError::ValidationFields(vec![FieldError::new("name", "a category with this name already exists")])
+}
+
+/// Bounds shared by every `ProductStore::create_many`.
+pub(crate) fn check_bulk_size(count: usize) -> AppResult<()> {
+    if count == 0 {
+        return Err(AppError::Validation("at least one product is required".to_string()));
+    }
+    if count > MAX_BULK_PRODUCTS {
+        return Err(AppError::Validation(format!(
+            "at most {} products can be created at once",
+            MAX_BULK_PRODUCTS
+        )));
+    }
+    Ok(())
+}
+
+/// `PreconditionFailed` unless `if_match`, an `If-Match` header value,
+/// matches the ETag of `current`.
+pub(crate) fn check_if_match(if_match: &str, current: &Product) -> AppResult<()> {
+    if etag_matches_strongly(if_match, &etag_for(current)) {
+        Ok(())
+    } else {
+        Err(AppError::PreconditionFailed("product has changed since it was read".to_string()))
+    }
+}
+
+async fn update_product<'e>(
+    executor: impl PgExecutor<'e>,
+    timer: QueryTimer,
+    id: Id,
+    input: UpdateProduct,
+) -> AppResult<Option<ProductChange>> {
+    let query = sqlx::query_as::<_, ProductChange>(
+        "UPDATE products p SET
+            name = COALESCE($2, p.name),
+            description = COALESCE($3, p.description),
+            price = COALESCE($4, p.price),
+            inventory = COALESCE($5, p.inventory),
+            image_url = COALESCE($6, p.image_url),
+            updated_at = $7
+         FROM (SELECT id, inventory FROM products WHERE id = $1 FOR UPDATE) previous
+         WHERE p.id = previous.id
+         RETURNING p.*, previous.inventory AS previous_inventory",
+    )
+    .bind(id)
+    .bind(input.name)
+    .bind(input.description)
+    .bind(input.price)
+    .bind(input.inventory)
+    .bind(input.image_url)
+    .bind(Utc::now())
+    .fetch_optional(executor);
+    let change = timer.time("products.update", query).await?;
+    Ok(change)
+}
+
+async fn insert_product<'e>(
+    executor: impl PgExecutor<'e>,
+    timer: QueryTimer,
+    input: CreateProduct,
+) -> AppResult<Product> {
+    let now = Utc::now();
+    let query = sqlx::query_as::<_, Product>(
+        "INSERT INTO products (id, name, description, price, inventory, created_at, updated_at)
+         VALUES ($1, $2, $3, $4, $5, $6, $6)
+         RETURNING *",
+    )
+    .bind(Id::new())
+    .bind(input.name)
+    .bind(input.description)
+    .bind(input.price)
+    .bind(input.inventory)
+    .bind(now)
+    .fetch_one(executor);
+    let product = timer.time("products.insert", query).await?;
+    Ok(product)
+}
+
+#[derive(Clone)]
+pub struct UserRepository {
+    pool: PgPool,
+    timer: QueryTimer,
+}
+
+impl UserRepository {
+    pub fn new(pool: PgPool, timer: QueryTimer) -> Self {
+        Self { pool, timer }
+    }
+
+    pub async fn list(&self, limit: i64, offset: i64) -> AppResult<Vec<User>> {
+        let query = sqlx::query_as::<_, User>(
+            "SELECT * FROM users ORDER BY created_at, id LIMIT $1 OFFSET $2",
+        )
+        .bind(limit)
+        .bind(offset)
+        .fetch_all(&self.pool);
+        let users = self.timer.time("users.list", query).await?;
+        Ok(users)
+    }
+
+    pub async fn count(&self) -> AppResult<i64> {
+        let query = sqlx::query_scalar("SELECT COUNT(*) FROM users").fetch_one(&self.pool);
+        let count = self.timer.time("users.count", query).await?;
+        Ok(count)
+    }
+
+    /// Keyset page in `(created_at, id)` order: users strictly after the
+    /// `after` position, so rows inserted meanwhile never shift a page.
+    pub async fn list_after(
+        &self,
+        after: Option<(DateTime<Utc>, Id)>,
+        limit: i64,
+    ) -> AppResult<Vec<User>> {
+        let query = match after {
+            Some((created_at, id)) => sqlx::query_as::<_, User>(
+                "SELECT * FROM users WHERE (created_at, id) > ($1, $2)
+                 ORDER BY created_at, id LIMIT $3",
+            )
+            .bind(created_at)
+            .bind(id)
+            .bind(limit),
+            None => sqlx::query_as::<_, User>("SELECT * FROM users ORDER BY created_at, id LIMIT $1")
+                .bind(limit),
+        };
+        let users = self.timer.time("users.list_after", query.fetch_all(&self.pool)).await?;
+        Ok(users)
+    }
+
+    pub async fn find_by_id(&self, id: Id) -> AppResult<Option<User>> {
+        let query = sqlx::query_as::<_, User>("SELECT * FROM users WHERE id = $1")
+            .bind(id)
+            .fetch_optional(&self.pool);
+        let user = self.timer.time("users.find_by_id", query).await?;
+        Ok(user)
+    }
+
+    /// Expects an already normalized (lowercase) email.
+    pub async fn find_by_email(&self, email: &str) -> AppResult<Option<User>> {
+        let query = sqlx::query_as::<_, User>("SELECT * FROM users WHERE LOWER(email) = $1")
+            .bind(email)
+            .fetch_optional(&self.pool);
+        let user = self.timer.time("users.find_by_email", query).await?;
+        Ok(user)
+    }
+
+    pub async fn create(&self, username: &str, email: &str, password_hash: &str) -> AppResult<User> {
+        let now = Utc::now();
+        let query = sqlx::query_as::<_, User>(
+            "INSERT INTO users (id, username, email, password_hash, created_at, updated_at)
+             VALUES ($1, $2, $3, $4, $5, $5)
+             RETURNING *",
+        )
+        .bind(Id::new())
+        .bind(username)
+        .bind(email)
+        .bind(password_hash)
+        .bind(now)
+        .fetch_one(&self.pool);
+        let user = self.timer.time("users.create", query).await?;
+        Ok(user)
+    }
+}
+
+#[derive(Clone)]
+pub struct WebhookRepository {
+    pool: PgPool,
+    timer: QueryTimer,
+}
+
+impl WebhookRepository {
+    pub fn new(pool: PgPool, timer: QueryTimer) -> Self {
+        Self { pool, timer }
+    }
+
+    /// Stores a delivery unless one with the same Shopify webhook id exists;
+    /// returns `None` for such duplicates.
+    pub async fn insert_if_new(
+        &self,
+        shopify_webhook_id: &str,
+        webhook_type: &str,
+        payload: &serde_json::Value,
+    ) -> AppResult<Option<ShopifyWebhook>> {
+        let query = sqlx::query_as::<_, ShopifyWebhook>(
+            "INSERT INTO shopify_webhooks (id, shopify_webhook_id, webhook_type, payload, processed, created_at)
+             VALUES ($1, $2, $3, $4, FALSE, $5)
+             ON CONFLICT (shopify_webhook_id) DO NOTHING
+             RETURNING *",
+        )
+        .bind(Uuid::new_v4())
+        .bind(shopify_webhook_id)
+        .bind(webhook_type)
+        .bind(payload)
+        .bind(Utc::now())
+        .fetch_optional(&self.pool);
+        let webhook = self.timer.time("webhooks.insert_if_new", query).await?;
+        Ok(webhook)
+    }
+
+    pub async fn with_transaction<T, F>(&self, f: F) -> AppResult<T>
+    where
+        F: for<'t> FnOnce(&'t mut Transaction<'static, Postgres>) -> BoxFuture<'t, AppResult<T>>,
+    {
+        with_transaction(&self.pool, f).await
+    }
+
+    /// Takes an executor so it can commit together with the work done for
+    /// the webhook.
+    pub async fn mark_processed<'e>(&self, executor: impl PgExecutor<'e>, id: Uuid) -> AppResult<()> {
+        let query = sqlx::query("UPDATE shopify_webhooks SET processed = TRUE WHERE id = $1")
+            .bind(id)
+            .execute(executor);
+        self.timer.time("webhooks.mark_processed", query).await?;
+        Ok(())
+    }
+
+    /// Oldest first; `processed` filters when given.
+    pub async fn list(&self, processed: Option<bool>) -> AppResult<Vec<ShopifyWebhook>> {
+        let query = sqlx::query_as::<_, ShopifyWebhook>(
+            "SELECT * FROM shopify_webhooks
+             WHERE $1::BOOLEAN IS NULL OR processed = $1
+             ORDER BY created_at, id",
+        )
+        .bind(processed)
+        .fetch_all(&self.pool);
+        let webhooks = self.timer.time("webhooks.list", query).await?;
+        Ok(webhooks)
+    }
+}
\ No newline at end of file
@@ -0,0 +1,159 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+use chrono::Utc;
+
+use crate::auth::{Claims, TOKEN_LIFETIME};
+
+/// In-memory token revocation. Single tokens are revoked by id (`jti`) on
+/// logout; all of a user's tokens at once by a per-user cutoff on `iat`.
+/// `iat` only has whole seconds, so within the cutoff second only tokens
+/// this instance issued after the revocation are let through; a user who
+/// logs in again right away isn't locked out until the next second.
+/// Entries are kept only until the tokens they cover would have expired,
+/// after which the signature check rejects them anyway.
+#[derive(Default)]
+pub struct TokenRevocations {
+    revoked: Mutex<HashMap<String, i64>>,
+    users: Mutex<HashMap<String, UserSessions>>,
+}
+
+#[derive(Default)]
+struct UserSessions {
+    /// Tokens issued before this second are rejected, and ones issued in it
+    /// unless they are in `issued`.
+    not_before: Option<i64>,
+    /// `jti` and `exp` of the tokens issued since the last cutoff.
+    issued: HashMap<String, i64>,
+}
+
+impl UserSessions {
+    fn prune(&mut self, now: i64) {
+        self.issued.retain(|_, expires_at| *expires_at > now);
+        if self
+            .not_before
+            .is_some_and(|cutoff| cutoff + TOKEN_LIFETIME.num_seconds() <= now)
+        {
+            self.not_before = None;
+        }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.not_before.is_none() && self.issued.is_empty()
+    }
+
+    fn rejects(&self, claims: &Claims) -> bool {
+        self.not_before.is_some_and(|cutoff| {
+            claims.iat < cutoff || (claims.iat == cutoff && !self.issued.contains_key(&claims.jti))
+        })
+    }
+}
+
+impl TokenRevocations {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Tracks a freshly issued token so `revoke_user` can report it. Call it
+    /// before handing the token out, so a revocation that lands in between
+    /// still covers it.
+    pub fn record_issued(&self, claims: &Claims) {
+        let now = Utc::now().timestamp();
+        let mut users = self.users.lock().unwrap();
+        users.retain(|_, sessions| {
+            sessions.prune(now);
+            !sessions.is_empty()
+        });
+        users
+            .entry(claims.sub.clone())
+            .or_default()
+            .issued
+            .insert(claims.jti.clone(), claims.exp);
+    }
+
+    pub fn revoke(&self, claims: &Claims) {
+        let now = Utc::now().timestamp();
+        let mut revoked = self.revoked.lock().unwrap();
+        revoked.retain(|_, expires_at| *expires_at > now);
+        revoked.insert(claims.jti.clone(), claims.exp);
+        drop(revoked);
+
+        if let Some(sessions) = self.users.lock().unwrap().get_mut(&claims.sub) {
+            sessions.issued.remove(&claims.jti);
+        }
+    }
+
+    /// Rejects every token issued to `user_id` up to now, including ones
+    /// issued by other instances. Returns how many unexpired tokens this
+    /// instance had issued to the user, i.e. the sessions known to end.
+    pub fn revoke_user(&self, user_id: &str) -> usize {
+        let now = Utc::now().timestamp();
+        let mut users = self.users.lock().unwrap();
+        let sessions = users.entry(user_id.to_string()).or_default();
+        sessions.prune(now);
+        let ended = sessions.issued.len();
+        sessions.issued.clear();
+        sessions.not_before = Some(now);
+        ended
+    }
+
+    pub fn is_revoked(&self, claims: &Claims) -> bool {
+        let now = Utc::now().timestamp();
+        let jti_revoked = self
+            .revoked
+            .lock()
+            .unwrap()
+            .get(&claims.jti)
+            .is_some_and(|expires_at| *expires_at > now);
+
+        jti_revoked
+            || self
+                .users
+                .lock()
+                .unwrap()
+                .get(&claims.sub)
+                .is_some_and(|sessions| sessions.rejects(claims))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::id::Id;
+
+    fn claims(user_id: Id) -> Claims {
+        Claims::new(user_id, "ada".to_string(), "ada@example.com".to_string(), "user".to_string())
+    }
+
+    #[test]
+    fn revoking_a_user_rejects_earlier_tokens_but_not_a_new_login() {
+        let revocations = TokenRevocations::new();
+        let user_id = Id::new();
+        let before = claims(user_id);
+        revocations.record_issued(&before);
+
+        assert_eq!(revocations.revoke_user(&before.sub), 1);
+        let after = claims(user_id);
+        revocations.record_issued(&after);
+
+        assert!(revocations.is_revoked(&before));
+        assert!(!revocations.is_revoked(&after));
+    }
+
+    #[test]
+    fn unknown_tokens_from_the_cutoff_second_are_rejected() {
+        let revocations = TokenRevocations::new();
+        let user_id = Id::new();
+        revocations.revoke_user(&user_id.to_string());
+        let cutoff = revocations.users.lock().unwrap()[&user_id.to_string()].not_before.unwrap();
+
+        let [mut elsewhere, mut earlier, mut later] = [claims(user_id), claims(user_id), claims(user_id)];
+        elsewhere.iat = cutoff;
+        earlier.iat = cutoff - 1;
+        later.iat = cutoff + 1;
+
+        assert!(revocations.is_revoked(&elsewhere));
+        assert!(revocations.is_revoked(&earlier));
+        assert!(!revocations.is_revoked(&later));
+    }
+}
\ No newline at end of file
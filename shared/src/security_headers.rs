@@ -0,0 +1,55 @@
+use crate::config::EnvReader;
+
+/// Blocks everything a JSON API has no use for, including being framed.
+pub const DEFAULT_CONTENT_SECURITY_POLICY: &str = "default-src 'none'; frame-ancestors 'none'";
+
+/// Path of the in-browser GraphQL IDE, whichever one `GRAPHQL_UI` picks.
+pub const GRAPHQL_UI_PATH: &str = "/graphiql";
+
+/// What GraphiQL and GraphQL Playground need to load: their inline bootstrap
+/// script and styles, assets from the CDNs they pull from, and requests back
+/// to this server over HTTP and WebSocket.
+pub const GRAPHQL_UI_CONTENT_SECURITY_POLICY: &str = "default-src 'self'; \
+    script-src 'self' 'unsafe-inline' 'unsafe-eval' https://unpkg.com https://cdn.jsdelivr.net; \
+    style-src 'self' 'unsafe-inline' https://unpkg.com https://cdn.jsdelivr.net https://fonts.googleapis.com; \
+    font-src 'self' data: https://fonts.gstatic.com; \
+    img-src 'self' data: https:; \
+    connect-src 'self' ws: wss:; \
+    frame-ancestors 'none'";
+
+/// Hardening headers added to every response by both servers. A header the
+/// handler already set is left alone.
+#[derive(Debug, Clone)]
+pub struct SecurityHeaders {
+    /// `Content-Security-Policy` everywhere but `GRAPHQL_UI_PATH`, which
+    /// always gets `GRAPHQL_UI_CONTENT_SECURITY_POLICY`.
+    pub content_security_policy: String,
+}
+
+impl SecurityHeaders {
+    pub fn read(env: &mut EnvReader) -> Self {
+        let content_security_policy =
+            env.var("CONTENT_SECURITY_POLICY", DEFAULT_CONTENT_SECURITY_POLICY.to_string());
+        env.check(
+            !content_security_policy.trim().is_empty()
+                && content_security_policy.chars().all(|c| c.is_ascii() && !c.is_ascii_control()),
+            "CONTENT_SECURITY_POLICY must be non-empty printable ASCII",
+        );
+        Self { content_security_policy }
+    }
+
+    /// The headers for a response to `path`, as name and value.
+    pub fn for_path(&self, path: &str) -> [(&'static str, &str); 4] {
+        let content_security_policy = if path == GRAPHQL_UI_PATH {
+            GRAPHQL_UI_CONTENT_SECURITY_POLICY
+        } else {
+            &self.content_security_policy
+        };
+        [
+            ("x-content-type-options", "nosniff"),
+            ("x-frame-options", "DENY"),
+            ("referrer-policy", "no-referrer"),
+            ("content-security-policy", content_security_policy),
+        ]
+    }
+}
\ No newline at end of file
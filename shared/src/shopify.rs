@@ -0,0 +1,293 @@
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+
+use base64::Engine;
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+use tokio::sync::mpsc;
+
+use crate::config::EnvReader;
+use crate::error::{AppError, AppResult};
+use crate::id::Id;
+use crate::models::{Product, ShopifyWebhook};
+use crate::repository::WebhookRepository;
+use crate::store::ProductStore;
+use crate::telemetry::redact;
+
+#[derive(Debug, Clone)]
+pub struct ShopifySettings {
+    /// Shop base URL, e.g. `https://my-shop.myshopify.com`.
+    pub shop_url: String,
+    pub access_token: String,
+    pub api_version: String,
+    pub timeout: Duration,
+}
+
+impl ShopifySettings {
+    /// `None` unless both `SHOPIFY_SHOP_URL` and `SHOPIFY_ACCESS_TOKEN` are set.
+    pub fn read(env: &mut EnvReader) -> Option<Self> {
+        let shop_url = env.var("SHOPIFY_SHOP_URL", String::new());
+        let access_token = env.var("SHOPIFY_ACCESS_TOKEN", String::new());
+        let api_version = env.var("SHOPIFY_API_VERSION", "2024-07".to_string());
+        let timeout = Duration::from_secs(env.var("SHOPIFY_TIMEOUT_SECS", 10));
+
+        match (shop_url.is_empty(), access_token.is_empty()) {
+            (true, true) => None,
+            (false, false) => Some(Self {
+                shop_url: shop_url.trim_end_matches('/').to_string(),
+                access_token,
+                api_version,
+                timeout,
+            }),
+            _ => {
+                env.check(false, "SHOPIFY_SHOP_URL and SHOPIFY_ACCESS_TOKEN must be set together");
+                None
+            }
+        }
+    }
+}
+
+/// Reference to the Shopify product a sync created or updated.
+#[derive(Debug, Serialize)]
+pub struct ShopifySync {
+    pub product_id: Id,
+    pub shopify_product_id: i64,
+}
+
+#[derive(Deserialize)]
+struct ShopifyProductEnvelope {
+    product: ShopifyProduct,
+}
+
+#[derive(Deserialize)]
+struct ShopifyProduct {
+    id: i64,
+}
+
+/// Outbound client for the Shopify Admin REST API.
+#[derive(Clone)]
+pub struct ShopifyClient {
+    client: reqwest::Client,
+    settings: ShopifySettings,
+}
+
+impl ShopifyClient {
+    pub fn new(settings: ShopifySettings) -> AppResult<Self> {
+        let client = reqwest::Client::builder()
+            .timeout(settings.timeout)
+            .build()
+            .map_err(|e| AppError::Internal(e.into()))?;
+        Ok(Self { client, settings })
+    }
+
+    /// Pushes product `id` and remembers the Shopify product it became, so
+    /// syncing it again updates that one instead of creating a duplicate.
+    pub async fn sync(&self, products: &dyn ProductStore, id: Id) -> AppResult<ShopifySync> {
+        let product = products
+            .find_by_id(id)
+            .await?
+            .ok_or_else(|| AppError::NotFound("product".to_string()))?;
+        let sync = self.push_product(&product).await?;
+        if product.shopify_product_id != Some(sync.shopify_product_id) {
+            products.link_shopify_product(id, sync.shopify_product_id).await?;
+        }
+        Ok(sync)
+    }
+
+    /// Creates the product in Shopify with a single variant carrying the
+    /// local price and inventory, or updates the Shopify product it was
+    /// synced to before.
+    pub async fn push_product(&self, product: &Product) -> AppResult<ShopifySync> {
+        let base = format!("{}/admin/api/{}", self.settings.shop_url, self.settings.api_version);
+        let mut body = json!({
+            "product": {
+                "title": product.name,
+                "body_html": product.description,
+                "variants": [{
+                    "price": cents_to_decimal(product.price),
+                    "inventory_quantity": product.inventory,
+                }],
+            }
+        });
+        let (method, url) = match product.shopify_product_id {
+            Some(shopify_id) => {
+                body["product"]["id"] = json!(shopify_id);
+                (reqwest::Method::PUT, format!("{}/products/{}.json", base, shopify_id))
+            }
+            None => (reqwest::Method::POST, format!("{}/products.json", base)),
+        };
+
+        let response = self
+            .client
+            .request(method, url)
+            .header("X-Shopify-Access-Token", &self.settings.access_token)
+            .json(&body)
+            .send()
+            .await
+            .map_err(map_transport_error)?;
+
+        let status = response.status();
+        if !status.is_success() {
+            let detail = response.text().await.unwrap_or_default();
+            return Err(match status.as_u16() {
+                // Shopify rejected the product itself, e.g. an invalid title
+                422 => AppError::Validation(format!("Shopify rejected the product: {}", detail)),
+                _ => AppError::Upstream(format!("Shopify answered {}: {}", status, detail)),
+            });
+        }
+
+        let created: ShopifyProductEnvelope = response.json().await.map_err(map_transport_error)?;
+        Ok(ShopifySync {
+            product_id: product.id,
+            shopify_product_id: created.product.id,
+        })
+    }
+}
+
+fn map_transport_error(err: reqwest::Error) -> AppError {
+    if err.is_timeout() {
+        AppError::Timeout
+    } else {
+        AppError::Upstream(err.to_string())
+    }
+}
+
+/// Formats integer cents as the decimal string Shopify expects, without
+/// going through floating point: `1999` becomes `"19.99"`, `-5` `"-0.05"`.
+pub fn cents_to_decimal(cents: i64) -> String {
+    let sign = if cents < 0 { "-" } else { "" };
+    let cents = cents.unsigned_abs();
+    format!("{}{}.{:02}", sign, cents / 100, cents % 100)
+}
+
+/// Checks `X-Shopify-Hmac-Sha256`: the base64 HMAC-SHA256 of the raw body
+/// keyed with the app's webhook secret. The comparison is constant time.
+pub fn verify_shopify_hmac(secret: &str, body: &[u8], signature: Option<&str>) -> AppResult<()> {
+    let invalid = || AppError::Authentication("invalid webhook signature".to_string());
+
+    let signature = base64::engine::general_purpose::STANDARD
+        .decode(signature.ok_or_else(invalid)?)
+        .map_err(|_| invalid())?;
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes()).map_err(|e| AppError::Internal(e.into()))?;
+    mac.update(body);
+    mac.verify_slice(&signature).map_err(|_| invalid())
+}
+
+/// The Shopify headers a webhook delivery is identified by.
+pub struct ShopifyWebhookHeaders<'a> {
+    pub hmac: Option<&'a str>,
+    pub webhook_id: Option<&'a str>,
+    pub topic: Option<&'a str>,
+}
+
+/// Stored deliveries waiting for the worker; further ones are refused with a
+/// 503 so Shopify redelivers them later.
+const WEBHOOK_QUEUE_CAPACITY: usize = 256;
+const PROCESS_ATTEMPTS: u32 = 5;
+const PROCESS_RETRY_BASE_DELAY: Duration = Duration::from_millis(500);
+
+/// Verifies, stores and processes inbound Shopify webhooks. Processing
+/// happens on one worker task fed by a bounded queue.
+#[derive(Clone)]
+pub struct ShopifyWebhookReceiver {
+    webhooks: WebhookRepository,
+    secret: Option<String>,
+    queue: mpsc::Sender<ShopifyWebhook>,
+}
+
+impl ShopifyWebhookReceiver {
+    /// Without a secret every delivery is rejected, since none can be verified.
+    /// Starts the worker, which first picks up deliveries a previous run
+    /// stored but never finished.
+    pub fn new(webhooks: WebhookRepository, secret: Option<String>) -> Self {
+        let (queue, pending) = mpsc::channel(WEBHOOK_QUEUE_CAPACITY);
+        tokio::spawn(run_worker(webhooks.clone(), pending));
+        Self { webhooks, secret, queue }
+    }
+
+    /// Stores a verified delivery and queues it for the worker; returns once
+    /// it is persisted so Shopify gets its 200 quickly. Redeliveries of a
+    /// stored webhook id are acknowledged without being stored again. A full
+    /// queue answers `Overloaded` before anything is stored.
+    pub async fn receive(&self, headers: ShopifyWebhookHeaders<'_>, body: &[u8]) -> AppResult<()> {
+        let secret = self
+            .secret
+            .as_deref()
+            .ok_or_else(|| AppError::Authentication("webhook verification is not configured".to_string()))?;
+        verify_shopify_hmac(secret, body, headers.hmac)?;
+
+        let webhook_id = headers
+            .webhook_id
+            .ok_or_else(|| AppError::Validation("missing X-Shopify-Webhook-Id header".to_string()))?;
+        let topic = headers.topic.unwrap_or("unknown");
+        let payload: serde_json::Value = serde_json::from_slice(body)
+            .map_err(|_| AppError::Validation("webhook body is not valid JSON".to_string()))?;
+        // Only ids at info level; the payload may hold customer data
+        tracing::info!(webhook_id, topic, "received Shopify webhook");
+        tracing::debug!(webhook_id, payload = %redact(&payload), "Shopify webhook payload");
+
+        // Reserved up front: refusing a delivery that was already stored
+        // would make Shopify's retry look like a duplicate
+        let slot = self.queue.try_reserve().map_err(|_| {
+            tracing::warn!(webhook_id, "Shopify webhook queue is full");
+            AppError::Overloaded
+        })?;
+        let Some(webhook) = self.webhooks.insert_if_new(webhook_id, topic, &payload).await? else {
+            tracing::info!(webhook_id, "ignoring duplicate Shopify webhook");
+            return Ok(());
+        };
+        slot.send(webhook);
+        Ok(())
+    }
+
+    pub async fn list(&self, processed: Option<bool>) -> AppResult<Vec<ShopifyWebhook>> {
+        self.webhooks.list(processed).await
+    }
+}
+
+async fn run_worker(webhooks: WebhookRepository, mut pending: mpsc::Receiver<ShopifyWebhook>) {
+    match webhooks.list(Some(false)).await {
+        Ok(unfinished) => {
+            if !unfinished.is_empty() {
+                tracing::info!(count = unfinished.len(), "resuming unprocessed Shopify webhooks");
+            }
+            for webhook in unfinished {
+                process_with_retries(&webhooks, &webhook).await;
+            }
+        }
+        Err(e) => tracing::warn!(error = %e, "could not load unprocessed Shopify webhooks"),
+    }
+    while let Some(webhook) = pending.recv().await {
+        process_with_retries(&webhooks, &webhook).await;
+    }
+}
+
+/// Retries with exponential backoff. A delivery that never succeeds stays
+/// unprocessed, listed under `?processed=false`, and is retried on restart.
+async fn process_with_retries(webhooks: &WebhookRepository, webhook: &ShopifyWebhook) {
+    for attempt in 0..PROCESS_ATTEMPTS {
+        if attempt > 0 {
+            tokio::time::sleep(PROCESS_RETRY_BASE_DELAY * 2u32.pow(attempt - 1)).await;
+        }
+        match process_webhook(webhooks, webhook).await {
+            Ok(()) => return,
+            Err(e) => tracing::warn!(id = %webhook.id, attempt, error = %e, "failed to process Shopify webhook"),
+        }
+    }
+    tracing::error!(id = %webhook.id, "giving up on Shopify webhook after {} attempts", PROCESS_ATTEMPTS);
+}
+
+/// No topic needs handling yet; deliveries are recorded and marked done.
+/// Topic handlers belong inside the transaction, so a delivery is only
+/// marked processed if its effects commit. Failed ones stay pending and show
+/// up under `?processed=false`.
+async fn process_webhook(webhooks: &WebhookRepository, webhook: &ShopifyWebhook) -> AppResult<()> {
+    tracing::info!(id = %webhook.id, topic = %webhook.webhook_type, "processing Shopify webhook");
+    let id = webhook.id;
+    let repository = webhooks.clone();
+    webhooks
+        .with_transaction(move |tx| Box::pin(async move { repository.mark_processed(&mut **tx, id).await }))
+        .await
+}
\ No newline at end of file
@@ -0,0 +1,136 @@
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// How often the drain checks whether the last request has finished.
+const DRAIN_POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// How often the drain reports the requests still running.
+const DRAIN_LOG_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Requests currently being handled, counted by a middleware in each server
+/// so shutdown can report how the drain is going.
+#[derive(Clone, Default)]
+pub struct InFlightRequests {
+    count: Arc<AtomicUsize>,
+}
+
+/// Keeps its request counted as in flight until dropped.
+pub struct InFlightGuard {
+    count: Arc<AtomicUsize>,
+}
+
+impl Drop for InFlightGuard {
+    fn drop(&mut self) {
+        self.count.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+impl InFlightRequests {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn track(&self) -> InFlightGuard {
+        self.count.fetch_add(1, Ordering::SeqCst);
+        InFlightGuard { count: self.count.clone() }
+    }
+
+    pub fn count(&self) -> usize {
+        self.count.load(Ordering::SeqCst)
+    }
+
+    /// Waits for the requests in flight to finish, logging how many are left
+    /// every second. Gives up after `timeout`, warning how many requests
+    /// are being abandoned.
+    pub async fn drain(&self, timeout: Duration) {
+        let started = Instant::now();
+        let mut last_report: Option<Instant> = None;
+        loop {
+            let in_flight = self.count();
+            if in_flight == 0 {
+                tracing::info!(
+                    elapsed_ms = started.elapsed().as_secs_f64() * 1000.0,
+                    "in-flight requests drained"
+                );
+                return;
+            }
+            if started.elapsed() >= timeout {
+                tracing::warn!(
+                    abandoned = in_flight,
+                    timeout_secs = timeout.as_secs_f64(),
+                    "shutdown timeout elapsed with requests still in flight"
+                );
+                return;
+            }
+            if last_report.is_none_or(|at| at.elapsed() >= DRAIN_LOG_INTERVAL) {
+                tracing::info!(in_flight, "draining in-flight requests");
+                last_report = Some(Instant::now());
+            }
+            tokio::time::sleep(DRAIN_POLL_INTERVAL).await;
+        }
+    }
+}
+
+/// Raised once a shutdown signal arrives. The health checks read it to
+/// answer 503, so load balancers stop routing here while requests drain.
+#[derive(Clone, Default)]
+pub struct ShuttingDown {
+    flag: Arc<AtomicBool>,
+}
+
+impl ShuttingDown {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn set(&self) {
+        self.flag.store(true, Ordering::SeqCst);
+    }
+
+    pub fn is_set(&self) -> bool {
+        self.flag.load(Ordering::SeqCst)
+    }
+}
+
+/// Waits for `shutdown_signal`, raises `shutting_down`, then keeps serving
+/// for `delay` so the failing health checks are seen before the listener
+/// closes.
+pub async fn begin_shutdown(shutting_down: &ShuttingDown, delay: Duration) {
+    shutdown_signal().await;
+    shutting_down.set();
+    if !delay.is_zero() {
+        tracing::info!(delay_secs = delay.as_secs_f64(), "reporting unhealthy before closing the listener");
+        tokio::time::sleep(delay).await;
+    }
+}
+
+/// Resolves on Ctrl+C or, on Unix, SIGTERM.
+pub async fn shutdown_signal() {
+    let ctrl_c = async {
+        if let Err(e) = tokio::signal::ctrl_c().await {
+            tracing::error!(error = %e, "failed to listen for Ctrl+C");
+            std::future::pending::<()>().await;
+        }
+    };
+    #[cfg(unix)]
+    let terminate = async {
+        match tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate()) {
+            Ok(mut signal) => {
+                signal.recv().await;
+            }
+            Err(e) => {
+                tracing::error!(error = %e, "failed to listen for SIGTERM");
+                std::future::pending::<()>().await;
+            }
+        }
+    };
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        _ = ctrl_c => {}
+        _ = terminate => {}
+    }
+    tracing::info!("shutdown signal received, draining requests");
+}
\ No newline at end of file
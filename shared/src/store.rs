@@ -0,0 +1,414 @@
+use std::collections::{HashMap, HashSet};
+use std::sync::Mutex;
+
+use async_trait::async_trait;
+use chrono::Utc;
+use futures::stream::{self, BoxStream, StreamExt};
+
+use crate::error::{AppError, AppResult};
+use crate::id::Id;
+use crate::models::{Category, CreateCategory, CreateProduct, Product, ProductFilter, ProductStats, UpdateProduct};
+use crate::repository::{check_bulk_size, check_if_match, duplicate_category, ProductChange, ProductRepository};
+
+/// Product persistence as the handlers and resolvers see it. Production uses
+/// the Postgres `ProductRepository`; `InMemoryProductStore` runs the same
+/// handlers without a database.
+#[async_trait]
+pub trait ProductStore: Send + Sync {
+    /// Every product, oldest first.
+    async fn list(&self) -> AppResult<Vec<Product>>;
+
+    /// One limit/offset page of the products `filter` matches, in its order.
+    async fn list_page(&self, filter: &ProductFilter, limit: i64, offset: i64) -> AppResult<Vec<Product>>;
+
+    /// How many products `filter` matches.
+    async fn count(&self, filter: &ProductFilter) -> AppResult<i64>;
+
+    /// Every product in `list` order, yielded as they are read rather than
+    /// collected first.
+    fn stream_all(&self) -> BoxStream<'static, AppResult<Product>>;
+
+    async fn find_by_id(&self, id: Id) -> AppResult<Option<Product>>;
+
+    async fn create(&self, input: CreateProduct) -> AppResult<Product>;
+
+    /// Creates all products or none of them, returned in input order.
+    async fn create_many(&self, inputs: Vec<CreateProduct>) -> AppResult<Vec<Product>>;
+
+    /// `None` when the product doesn't exist.
+    async fn update(&self, id: Id, input: UpdateProduct) -> AppResult<Option<ProductChange>>;
+
+    /// `update`, but atomically checked against `if_match`, an `If-Match`
+    /// header value: `PreconditionFailed` when it doesn't match the
+    /// product's current ETag.
+    async fn update_if_match(
+        &self,
+        id: Id,
+        input: UpdateProduct,
+        if_match: &str,
+    ) -> AppResult<Option<ProductChange>>;
+
+    /// Takes `quantity` out of stock atomically; `Validation` when there
+    /// isn't enough, `NotFound` when the product doesn't exist. A negative
+    /// `quantity` puts units back.
+    async fn reserve_inventory(&self, id: Id, quantity: i32) -> AppResult<Product>;
+
+    /// Returns whether a product was actually removed.
+    async fn delete(&self, id: Id) -> AppResult<bool>;
+
+    /// Totals over the whole catalog, for the admin dashboard.
+    async fn stats(&self) -> AppResult<ProductStats>;
+
+    /// Remembers which Shopify product `id` was synced to; returns whether
+    /// the product exists. Leaves `updated_at`, and so the ETag, alone.
+    async fn link_shopify_product(&self, id: Id, shopify_product_id: i64) -> AppResult<bool>;
+
+    /// Every category, by name.
+    async fn list_categories(&self) -> AppResult<Vec<Category>>;
+
+    /// Category names are unique; a taken one is a `name` field error.
+    async fn create_category(&self, input: CreateCategory) -> AppResult<Category>;
+
+    /// The categories of each of `product_ids`, by name, looked up together
+    /// so the GraphQL loader can batch a whole list. Products without any
+    /// are left out of the map.
+    async fn categories_of(&self, product_ids: &[Id]) -> AppResult<HashMap<Id, Vec<Category>>>;
+
+    /// Idempotent; `NotFound` when the product or the category doesn't
+    /// exist.
+    async fn assign_category(&self, product_id: Id, category_id: Id) -> AppResult<()>;
+
+    /// Returns whether the category was assigned.
+    async fn unassign_category(&self, product_id: Id, category_id: Id) -> AppResult<bool>;
+}
+
+#[async_trait]
+impl ProductStore for ProductRepository {
+    async fn list(&self) -> AppResult<Vec<Product>> {
+        ProductRepository::list(self).await
+    }
+
+    async fn list_page(&self, filter: &ProductFilter, limit: i64, offset: i64) -> AppResult<Vec<Product>> {
+        ProductRepository::list_page(self, filter, limit, offset).await
+    }
+
+    async fn count(&self, filter: &ProductFilter) -> AppResult<i64> {
+        ProductRepository::count(self, filter).await
+    }
+
+    fn stream_all(&self) -> BoxStream<'static, AppResult<Product>> {
+        ProductRepository::stream_all(self).boxed()
+    }
+
+    async fn find_by_id(&self, id: Id) -> AppResult<Option<Product>> {
+        ProductRepository::find_by_id(self, id).await
+    }
+
+    async fn create(&self, input: CreateProduct) -> AppResult<Product> {
+        ProductRepository::create(self, input).await
+    }
+
+    async fn create_many(&self, inputs: Vec<CreateProduct>) -> AppResult<Vec<Product>> {
+        ProductRepository::create_many(self, inputs).await
+    }
+
+    async fn update(&self, id: Id, input: UpdateProduct) -> AppResult<Option<ProductChange>> {
+        ProductRepository::update(self, id, input).await
+    }
+
+    async fn update_if_match(
+        &self,
+        id: Id,
+        input: UpdateProduct,
+        if_match: &str,
+    ) -> AppResult<Option<ProductChange>> {
+        ProductRepository::update_if_match(self, id, input, if_match).await
+    }
+
+    async fn reserve_inventory(&self, id: Id, quantity: i32) -> AppResult<Product> {
+        ProductRepository::reserve_inventory(self, id, quantity).await
+    }
+
+    async fn delete(&self, id: Id) -> AppResult<bool> {
+        ProductRepository::delete(self, id).await
+    }
+
+    async fn stats(&self) -> AppResult<ProductStats> {
+        ProductRepository::stats(self).await
+    }
+
+    async fn link_shopify_product(&self, id: Id, shopify_product_id: i64) -> AppResult<bool> {
+        ProductRepository::link_shopify_product(self, id, shopify_product_id).await
+    }
+
+    async fn list_categories(&self) -> AppResult<Vec<Category>> {
+        ProductRepository::list_categories(self).await
+    }
+
+    async fn create_category(&self, input: CreateCategory) -> AppResult<Category> {
+        ProductRepository::create_category(self, input).await
+    }
+
+    async fn categories_of(&self, product_ids: &[Id]) -> AppResult<HashMap<Id, Vec<Category>>> {
+        ProductRepository::categories_of(self, product_ids).await
+    }
+
+    async fn assign_category(&self, product_id: Id, category_id: Id) -> AppResult<()> {
+        ProductRepository::assign_category(self, product_id, category_id).await
+    }
+
+    async fn unassign_category(&self, product_id: Id, category_id: Id) -> AppResult<bool> {
+        ProductRepository::unassign_category(self, product_id, category_id).await
+    }
+}
+
+/// `ProductStore` kept in a map, for running the servers' handlers in-process
+/// without Postgres. Follows the repository's ordering and bulk limits.
+#[derive(Default)]
+pub struct InMemoryProductStore {
+    products: Mutex<HashMap<Id, Product>>,
+    categories: Mutex<HashMap<Id, Category>>,
+    /// `(product_id, category_id)` pairs. When held together, the maps are
+    /// locked in field order, so this one last.
+    assignments: Mutex<HashSet<(Id, Id)>>,
+}
+
+impl InMemoryProductStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The products `filter` matches, in no particular order.
+    fn filtered(&self, filter: &ProductFilter) -> Vec<Product> {
+        let products = self.products.lock().unwrap();
+        let assignments = self.assignments.lock().unwrap();
+        products
+            .values()
+            .filter(|product| filter.matches(product))
+            .filter(|product| {
+                filter
+                    .category
+                    .is_none_or(|category_id| assignments.contains(&(product.id, category_id)))
+            })
+            .cloned()
+            .collect()
+    }
+
+    fn sorted(&self) -> Vec<Product> {
+        let mut products: Vec<Product> = self.products.lock().unwrap().values().cloned().collect();
+        products.sort_by_key(|product| (product.created_at, product.id));
+        products
+    }
+
+    fn apply_update(&self, id: Id, input: UpdateProduct, if_match: Option<&str>) -> AppResult<Option<ProductChange>> {
+        let mut products = self.products.lock().unwrap();
+        let Some(product) = products.get_mut(&id) else {
+            return Ok(None);
+        };
+        if let Some(if_match) = if_match {
+            check_if_match(if_match, product)?;
+        }
+        let previous_inventory = product.inventory;
+        if let Some(name) = input.name {
+            product.name = name;
+        }
+        if let Some(description) = input.description {
+            product.description = description;
+        }
+        if let Some(price) = input.price {
+            product.price = price;
+        }
+        if let Some(inventory) = input.inventory {
+            product.inventory = inventory;
+        }
+        if let Some(image_url) = input.image_url {
+            product.image_url = Some(image_url);
+        }
+        product.updated_at = Utc::now();
+        Ok(Some(ProductChange {
+            product: product.clone(),
+            previous_inventory,
+        }))
+    }
+}
+
+fn new_product(input: CreateProduct) -> Product {
+    let now = Utc::now();
+    Product {
+        id: Id::new(),
+        name: input.name,
+        description: input.description,
+        price: input.price,
+        inventory: input.inventory,
+        image_url: None,
+        shopify_product_id: None,
+        created_at: now,
+        updated_at: now,
+    }
+}
+
+#[async_trait]
+impl ProductStore for InMemoryProductStore {
+    async fn list(&self) -> AppResult<Vec<Product>> {
+        Ok(self.sorted())
+    }
+
+    async fn list_page(&self, filter: &ProductFilter, limit: i64, offset: i64) -> AppResult<Vec<Product>> {
+        let mut products = self.filtered(filter);
+        products.sort_by(|a, b| filter.sort.compare(a, b));
+        let page = products
+            .into_iter()
+            .skip(offset.max(0) as usize)
+            .take(limit.max(0) as usize)
+            .collect();
+        Ok(page)
+    }
+
+    async fn count(&self, filter: &ProductFilter) -> AppResult<i64> {
+        Ok(self.filtered(filter).len() as i64)
+    }
+
+    fn stream_all(&self) -> BoxStream<'static, AppResult<Product>> {
+        stream::iter(self.sorted().into_iter().map(Ok)).boxed()
+    }
+
+    async fn find_by_id(&self, id: Id) -> AppResult<Option<Product>> {
+        Ok(self.products.lock().unwrap().get(&id).cloned())
+    }
+
+    async fn create(&self, input: CreateProduct) -> AppResult<Product> {
+        let product = new_product(input);
+        self.products.lock().unwrap().insert(product.id, product.clone());
+        Ok(product)
+    }
+
+    async fn create_many(&self, inputs: Vec<CreateProduct>) -> AppResult<Vec<Product>> {
+        check_bulk_size(inputs.len())?;
+        let created: Vec<Product> = inputs.into_iter().map(new_product).collect();
+        let mut products = self.products.lock().unwrap();
+        for product in &created {
+            products.insert(product.id, product.clone());
+        }
+        Ok(created)
+    }
+
+    async fn update(&self, id: Id, input: UpdateProduct) -> AppResult<Option<ProductChange>> {
+        self.apply_update(id, input, None)
+    }
+
+    async fn update_if_match(
+        &self,
+        id: Id,
+        input: UpdateProduct,
+        if_match: &str,
+    ) -> AppResult<Option<ProductChange>> {
+        self.apply_update(id, input, Some(if_match))
+    }
+
+    async fn reserve_inventory(&self, id: Id, quantity: i32) -> AppResult<Product> {
+        let mut products = self.products.lock().unwrap();
+        let product = products
+            .get_mut(&id)
+            .ok_or_else(|| AppError::NotFound("product".to_string()))?;
+        if product.inventory < quantity {
+            return Err(AppError::Validation("insufficient inventory".to_string()));
+        }
+        product.inventory = product
+            .inventory
+            .checked_sub(quantity)
+            .ok_or_else(|| AppError::Validation("inventory is out of range".to_string()))?;
+        product.updated_at = Utc::now();
+        Ok(product.clone())
+    }
+
+    async fn delete(&self, id: Id) -> AppResult<bool> {
+        let removed = self.products.lock().unwrap().remove(&id).is_some();
+        self.assignments.lock().unwrap().retain(|(product_id, _)| *product_id != id);
+        Ok(removed)
+    }
+
+    /// Sums in 128 bits, like the repository's NUMERIC, saturating the
+    /// catalog value at `i64::MAX` the same way.
+    async fn stats(&self) -> AppResult<ProductStats> {
+        let products = self.products.lock().unwrap();
+        let total_products = products.len() as i64;
+        let total_price: i128 = products.values().map(|product| i128::from(product.price)).sum();
+        let total_value: i128 = products
+            .values()
+            .map(|product| i128::from(product.price) * i128::from(product.inventory))
+            .sum();
+        let avg_price_cents = match total_products {
+            0 => 0,
+            count => (total_price as f64 / count as f64).round() as i64,
+        };
+        Ok(ProductStats {
+            total_products,
+            total_inventory_units: products.values().map(|product| i64::from(product.inventory)).sum(),
+            total_catalog_value_cents: i64::try_from(total_value).unwrap_or(i64::MAX),
+            avg_price_cents,
+            out_of_stock_count: products.values().filter(|product| product.inventory <= 0).count() as i64,
+        })
+    }
+
+    async fn link_shopify_product(&self, id: Id, shopify_product_id: i64) -> AppResult<bool> {
+        let mut products = self.products.lock().unwrap();
+        let Some(product) = products.get_mut(&id) else {
+            return Ok(false);
+        };
+        product.shopify_product_id = Some(shopify_product_id);
+        Ok(true)
+    }
+
+    async fn list_categories(&self) -> AppResult<Vec<Category>> {
+        let mut categories: Vec<Category> = self.categories.lock().unwrap().values().cloned().collect();
+        categories.sort_by(|a, b| a.name.cmp(&b.name));
+        Ok(categories)
+    }
+
+    async fn create_category(&self, input: CreateCategory) -> AppResult<Category> {
+        let mut categories = self.categories.lock().unwrap();
+        if categories.values().any(|category| category.name == input.name) {
+            return Err(duplicate_category());
+        }
+        let category = Category {
+            id: Id::new(),
+            name: input.name,
+            created_at: Utc::now(),
+        };
+        categories.insert(category.id, category.clone());
+        Ok(category)
+    }
+
+    async fn categories_of(&self, product_ids: &[Id]) -> AppResult<HashMap<Id, Vec<Category>>> {
+        let categories = self.categories.lock().unwrap();
+        let assignments = self.assignments.lock().unwrap();
+        let mut found: HashMap<Id, Vec<Category>> = HashMap::new();
+        for (product_id, category_id) in assignments.iter() {
+            if product_ids.contains(product_id) {
+                if let Some(category) = categories.get(category_id) {
+                    found.entry(*product_id).or_default().push(category.clone());
+                }
+            }
+        }
+        for categories in found.values_mut() {
+            categories.sort_by(|a, b| a.name.cmp(&b.name));
+        }
+        Ok(found)
+    }
+
+    async fn assign_category(&self, product_id: Id, category_id: Id) -> AppResult<()> {
+        let products = self.products.lock().unwrap();
+        if !products.contains_key(&product_id) {
+            return Err(AppError::NotFound("product".to_string()));
+        }
+        if !self.categories.lock().unwrap().contains_key(&category_id) {
+            return Err(AppError::NotFound("category".to_string()));
+        }
+        self.assignments.lock().unwrap().insert((product_id, category_id));
+        Ok(())
+    }
+
+    async fn unassign_category(&self, product_id: Id, category_id: Id) -> AppResult<bool> {
+        Ok(self.assignments.lock().unwrap().remove(&(product_id, category_id)))
+    }
+}
\ No newline at end of file
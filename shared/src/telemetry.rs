@@ -0,0 +1,138 @@
+use std::future::Future;
+use std::str::FromStr;
+
+use serde_json::Value;
+use tracing::{Instrument, Span};
+use tracing_subscriber::fmt::format::FmtSpan;
+use tracing_subscriber::{fmt, layer::SubscriberExt, util::SubscriberInitExt, EnvFilter};
+
+/// Header used to correlate a request across log lines and responses.
+pub const REQUEST_ID_HEADER: &str = "x-request-id";
+
+/// Request id attached to request extensions by the logging middleware.
+#[derive(Debug, Clone)]
+pub struct RequestId(pub String);
+
+/// One GraphQL WebSocket connection, passed to subscription resolvers so
+/// their log lines can be tied to the connection and to the upgrade request
+/// that opened it. The request's span has ended by the time they run.
+#[derive(Debug, Clone)]
+pub struct SubscriptionConnection {
+    pub connection_id: String,
+    pub request_id: String,
+}
+
+impl SubscriptionConnection {
+    pub fn new(request_id: Option<&RequestId>) -> Self {
+        Self {
+            connection_id: uuid::Uuid::new_v4().to_string(),
+            request_id: request_id.map(|RequestId(id)| id.clone()).unwrap_or_default(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LogFormat {
+    Pretty,
+    Json,
+}
+
+impl LogFormat {
+    /// Reads `LOG_FORMAT`, defaulting to human-readable output. Tools that
+    /// don't load a full `Config` use this lenient variant.
+    pub fn from_env() -> Self {
+        std::env::var("LOG_FORMAT")
+            .ok()
+            .and_then(|value| value.parse().ok())
+            .unwrap_or(LogFormat::Pretty)
+    }
+}
+
+impl FromStr for LogFormat {
+    type Err = ();
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "pretty" => Ok(LogFormat::Pretty),
+            "json" => Ok(LogFormat::Json),
+            _ => Err(()),
+        }
+    }
+}
+
+/// Installs the global subscriber. In JSON mode event fields are flattened
+/// to top-level keys so log aggregators can index them directly. Closing a
+/// span logs its `time.busy` and `time.idle`, which is how request phases
+/// report their durations.
+pub fn init_tracing(default_filter: &str, format: LogFormat) {
+    let filter = EnvFilter::try_from_default_env().unwrap_or_else(|_| default_filter.into());
+    let registry = tracing_subscriber::registry().with(filter);
+    let layer = fmt::layer().with_span_events(FmtSpan::CLOSE);
+
+    match format {
+        LogFormat::Json => registry.with(layer.json().flatten_event(true)).init(),
+        LogFormat::Pretty => registry.with(layer).init(),
+    }
+}
+
+/// Span around one request, parent of its phase spans. Like them it is at
+/// DEBUG, so with the default filter none of them is recorded.
+pub fn request_span(request_id: &str, method: &str, path: &str) -> Span {
+    tracing::debug_span!("request", request_id, method, path)
+}
+
+/// Span around one phase of a request: `auth`, `password`, `db`, `execute`
+/// or `serialize`.
+pub fn phase_span(phase: &'static str) -> Span {
+    tracing::debug_span!("phase", phase)
+}
+
+/// Awaits `future` as the `phase` of the current request.
+pub async fn in_phase<F: Future>(phase: &'static str, future: F) -> F::Output {
+    future.instrument(phase_span(phase)).await
+}
+
+/// Runs `f` as the `phase` of the current request.
+pub fn in_phase_sync<T>(phase: &'static str, f: impl FnOnce() -> T) -> T {
+    phase_span(phase).in_scope(f)
+}
+
+const REDACTED: &str = "[redacted]";
+
+/// Whether a JSON key or header name carries a credential. Matches
+/// substrings so `access_token`, `password_hash` and
+/// `X-Shopify-Access-Token` are covered too.
+fn is_sensitive(name: &str) -> bool {
+    let name = name.to_ascii_lowercase();
+    ["password", "token", "secret", "hmac"].iter().any(|word| name.contains(word))
+        || name == "authorization"
+        || name == "cookie"
+}
+
+/// Copy of `value` with every sensitive field, at any depth, replaced by
+/// `"[redacted]"`. Use it before logging a request or webhook body.
+pub fn redact(value: &Value) -> Value {
+    match value {
+        Value::Object(fields) => Value::Object(
+            fields
+                .iter()
+                .map(|(key, field)| {
+                    let field = if is_sensitive(key) { Value::from(REDACTED) } else { redact(field) };
+                    (key.clone(), field)
+                })
+                .collect(),
+        ),
+        Value::Array(items) => Value::Array(items.iter().map(redact).collect()),
+        other => other.clone(),
+    }
+}
+
+/// `name: value` pairs joined for a log line, with credential headers such
+/// as `Authorization` redacted.
+pub fn redact_headers<'a>(headers: impl IntoIterator<Item = (&'a str, &'a str)>) -> String {
+    headers
+        .into_iter()
+        .map(|(name, value)| format!("{}: {}", name, if is_sensitive(name) { REDACTED } else { value }))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
\ No newline at end of file
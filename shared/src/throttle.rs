@@ -0,0 +1,178 @@
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use crate::error::{AppError, AppResult};
+
+/// Beyond this many tracked keys, room is made for a new one so a
+/// credential-stuffing run can't grow the map without bound.
+const MAX_TRACKED_KEYS: usize = 10_000;
+
+/// When the map is full, pairs that are not locked out and haven't failed
+/// for this long are the first to be forgotten.
+const IDLE_WINDOW: Duration = Duration::from_secs(15 * 60);
+
+struct Attempts {
+    failures: u32,
+    lockouts: u32,
+    locked_until: Option<Instant>,
+    last_failure: Instant,
+}
+
+impl Attempts {
+    fn new(now: Instant) -> Self {
+        Self { failures: 0, lockouts: 0, locked_until: None, last_failure: now }
+    }
+
+    fn is_locked(&self, now: Instant) -> bool {
+        self.locked_until.is_some_and(|until| until > now)
+    }
+}
+
+/// Tracks failed logins per email + client IP. After `max_failures`
+/// consecutive failures the pair is locked out, each lockout lasting twice
+/// as long as the previous one. A successful login clears the history.
+pub struct LoginThrottle {
+    attempts: Mutex<HashMap<(String, IpAddr), Attempts>>,
+    max_failures: u32,
+    base_lockout: Duration,
+    max_tracked_keys: usize,
+}
+
+impl LoginThrottle {
+    pub fn new(max_failures: u32, base_lockout: Duration) -> Self {
+        Self {
+            attempts: Mutex::new(HashMap::new()),
+            max_failures,
+            base_lockout,
+            max_tracked_keys: MAX_TRACKED_KEYS,
+        }
+    }
+
+    /// Fails with `AppError::RateLimited` while the pair is locked out.
+    pub fn check(&self, email: &str, ip: IpAddr) -> AppResult<()> {
+        let attempts = self.attempts.lock().unwrap();
+        let locked_until = attempts
+            .get(&(email.to_string(), ip))
+            .and_then(|entry| entry.locked_until);
+
+        match locked_until {
+            Some(until) if until > Instant::now() => Err(AppError::RateLimited {
+                retry_after: until - Instant::now(),
+                limit: u64::from(self.max_failures),
+            }),
+            _ => Ok(()),
+        }
+    }
+
+    pub fn record_failure(&self, email: &str, ip: IpAddr) {
+        let mut attempts = self.attempts.lock().unwrap();
+        let now = Instant::now();
+        let key = (email.to_string(), ip);
+
+        if attempts.len() >= self.max_tracked_keys && !attempts.contains_key(&key) {
+            self.make_room(&mut attempts, now);
+        }
+
+        let entry = attempts.entry(key).or_insert_with(|| Attempts::new(now));
+        entry.failures += 1;
+        entry.last_failure = now;
+
+        if entry.failures >= self.max_failures {
+            let lockout = self.base_lockout * 2u32.saturating_pow(entry.lockouts);
+            entry.locked_until = Some(now + lockout);
+            entry.lockouts += 1;
+            entry.failures = 0;
+        }
+    }
+
+    pub fn record_success(&self, email: &str, ip: IpAddr) {
+        self.attempts.lock().unwrap().remove(&(email.to_string(), ip));
+    }
+
+    /// Forgets idle pairs. If every pair is still active, only the one with
+    /// the fewest failures that failed longest ago goes, and locked pairs
+    /// only once nothing else is left, so flooding the map with fresh junk
+    /// emails can't reset a pair that is close to a lockout.
+    fn make_room(&self, attempts: &mut HashMap<(String, IpAddr), Attempts>, now: Instant) {
+        attempts.retain(|_, entry| entry.is_locked(now) || now.duration_since(entry.last_failure) < IDLE_WINDOW);
+        if attempts.len() < self.max_tracked_keys {
+            return;
+        }
+        let evicted = attempts
+            .iter()
+            .min_by_key(|(_, entry)| (entry.is_locked(now), entry.failures, entry.last_failure))
+            .map(|(key, _)| key.clone());
+        if let Some(key) = evicted {
+            attempts.remove(&key);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::Ipv4Addr;
+
+    const IP: IpAddr = IpAddr::V4(Ipv4Addr::LOCALHOST);
+
+    fn throttle(max_tracked_keys: usize) -> LoginThrottle {
+        LoginThrottle { max_tracked_keys, ..LoginThrottle::new(5, Duration::from_secs(30)) }
+    }
+
+    #[test]
+    fn locks_out_after_max_failures() {
+        let throttle = throttle(MAX_TRACKED_KEYS);
+        for _ in 0..4 {
+            throttle.record_failure("ada@example.com", IP);
+        }
+        assert!(throttle.check("ada@example.com", IP).is_ok());
+        throttle.record_failure("ada@example.com", IP);
+        assert!(matches!(throttle.check("ada@example.com", IP), Err(AppError::RateLimited { .. })));
+    }
+
+    #[test]
+    fn flooding_junk_emails_keeps_pending_failures() {
+        let throttle = throttle(100);
+        for _ in 0..4 {
+            throttle.record_failure("ada@example.com", IP);
+        }
+        for n in 0..1000 {
+            throttle.record_failure(&format!("junk{}@example.com", n), IP);
+        }
+        assert_eq!(throttle.attempts.lock().unwrap().len(), 100);
+        throttle.record_failure("ada@example.com", IP);
+        assert!(matches!(throttle.check("ada@example.com", IP), Err(AppError::RateLimited { .. })));
+    }
+
+    #[test]
+    fn flooding_keeps_locked_pairs() {
+        let throttle = throttle(100);
+        for _ in 0..5 {
+            throttle.record_failure("ada@example.com", IP);
+        }
+        for n in 0..1000 {
+            for _ in 0..2 {
+                throttle.record_failure(&format!("junk{}@example.com", n), IP);
+            }
+        }
+        assert!(throttle.check("ada@example.com", IP).is_err());
+    }
+
+    #[test]
+    fn idle_pairs_are_forgotten_first() {
+        let throttle = throttle(2);
+        throttle.record_failure("idle@example.com", IP);
+        throttle.attempts.lock().unwrap().values_mut().for_each(|entry| {
+            entry.last_failure = Instant::now() - IDLE_WINDOW - Duration::from_secs(1);
+            entry.failures = 4;
+        });
+        throttle.record_failure("ada@example.com", IP);
+        throttle.record_failure("ada@example.com", IP);
+        throttle.record_failure("new@example.com", IP);
+        let attempts = throttle.attempts.lock().unwrap();
+        assert!(!attempts.contains_key(&("idle@example.com".to_string(), IP)));
+        assert_eq!(attempts[&("ada@example.com".to_string(), IP)].failures, 2);
+    }
+}
\ No newline at end of file
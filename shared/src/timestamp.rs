@@ -0,0 +1,90 @@
+use chrono::{DateTime, SecondsFormat, Utc};
+use serde::{Deserialize, Deserializer, Serializer};
+
+/// RFC 3339 with exactly three fractional digits and a `Z`, e.g.
+/// `2025-08-01T12:00:00.000Z`. chrono's default prints as many digits as
+/// the value needs, so the width varied from row to row. Fields opt in with
+/// `#[serde(with = "crate::timestamp")]`.
+pub fn format(at: &DateTime<Utc>) -> String {
+    at.to_rfc3339_opts(SecondsFormat::Millis, true)
+}
+
+pub fn serialize<S: Serializer>(at: &DateTime<Utc>, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_str(&format(at))
+}
+
+/// Accepts any RFC 3339 timestamp, whatever its precision or offset.
+pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<DateTime<Utc>, D::Error> {
+    DateTime::deserialize(deserializer)
+}
+
+/// The same format for an optional timestamp; `None` is `null`.
+pub mod option {
+    use chrono::{DateTime, Utc};
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(at: &Option<DateTime<Utc>>, serializer: S) -> Result<S::Ok, S::Error> {
+        match at {
+            Some(at) => super::serialize(at, serializer),
+            None => serializer.serialize_none(),
+        }
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<DateTime<Utc>>, D::Error> {
+        Option::deserialize(deserializer)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+    use serde::{Deserialize, Serialize};
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Row {
+        #[serde(with = "crate::timestamp")]
+        at: DateTime<Utc>,
+        #[serde(with = "crate::timestamp::option")]
+        seen: Option<DateTime<Utc>>,
+    }
+
+    fn at(nanos: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2025, 8, 1, 12, 0, 0).unwrap() + chrono::Duration::nanoseconds(i64::from(nanos))
+    }
+
+    #[test]
+    fn formats_exactly_three_fractional_digits() {
+        assert_eq!(format(&at(0)), "2025-08-01T12:00:00.000Z");
+        assert_eq!(format(&at(5_000_000)), "2025-08-01T12:00:00.005Z");
+        assert_eq!(format(&at(120_000_000)), "2025-08-01T12:00:00.120Z");
+    }
+
+    #[test]
+    fn sub_millisecond_nanos_are_truncated() {
+        assert_eq!(format(&at(123_456_789)), "2025-08-01T12:00:00.123Z");
+        assert_eq!(format(&at(999_999)), "2025-08-01T12:00:00.000Z");
+        assert_eq!(format(&at(999_999_999)), "2025-08-01T12:00:00.999Z");
+    }
+
+    #[test]
+    fn serializes_fields_and_optional_fields() {
+        let row = Row { at: at(42_000_000), seen: None };
+        assert_eq!(
+            serde_json::to_string(&row).unwrap(),
+            r#"{"at":"2025-08-01T12:00:00.042Z","seen":null}"#
+        );
+        let row = Row { at: at(0), seen: Some(at(1_500_000)) };
+        assert_eq!(
+            serde_json::to_string(&row).unwrap(),
+            r#"{"at":"2025-08-01T12:00:00.000Z","seen":"2025-08-01T12:00:00.001Z"}"#
+        );
+    }
+
+    #[test]
+    fn deserializes_any_precision_and_offset() {
+        let json = r#"{"at":"2025-08-01T14:00:00.123456789+02:00","seen":"2025-08-01T12:00:00Z"}"#;
+        let row: Row = serde_json::from_str(json).unwrap();
+        assert_eq!(row, Row { at: at(123_456_789), seen: Some(at(0)) });
+    }
+}
\ No newline at end of file
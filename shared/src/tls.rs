@@ -0,0 +1,52 @@
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use anyhow::Context;
+use rustls::pki_types::pem::PemObject;
+use rustls::pki_types::{CertificateDer, PrivateKeyDer};
+
+use crate::config::EnvReader;
+use crate::error::AppResult;
+
+/// PEM files HTTPS is served with.
+#[derive(Debug, Clone)]
+pub struct TlsSettings {
+    /// Certificate chain, leaf first.
+    pub cert_path: PathBuf,
+    pub key_path: PathBuf,
+}
+
+impl TlsSettings {
+    /// `None` unless both `TLS_CERT` and `TLS_KEY` are set.
+    pub fn read(env: &mut EnvReader) -> Option<Self> {
+        let cert_path = env.var("TLS_CERT", String::new());
+        let key_path = env.var("TLS_KEY", String::new());
+
+        match (cert_path.is_empty(), key_path.is_empty()) {
+            (true, true) => None,
+            (false, false) => Some(Self {
+                cert_path: cert_path.into(),
+                key_path: key_path.into(),
+            }),
+            _ => {
+                env.check(false, "TLS_CERT and TLS_KEY must be set together");
+                None
+            }
+        }
+    }
+
+    /// Reads both files into a rustls config using the `ring` provider. No
+    /// ALPN protocols are set; each server adds the ones it speaks.
+    pub fn server_config(&self) -> AppResult<rustls::ServerConfig> {
+        let certs = CertificateDer::pem_file_iter(&self.cert_path)
+            .and_then(|certs| certs.collect::<Result<Vec<_>, _>>())
+            .with_context(|| format!("failed to read TLS_CERT {}", self.cert_path.display()))?;
+        let key = PrivateKeyDer::from_pem_file(&self.key_path)
+            .with_context(|| format!("failed to read TLS_KEY {}", self.key_path.display()))?;
+        let config = rustls::ServerConfig::builder_with_provider(Arc::new(rustls::crypto::ring::default_provider()))
+            .with_safe_default_protocol_versions()
+            .and_then(|builder| builder.with_no_client_auth().with_single_cert(certs, key))
+            .context("invalid TLS certificate or key")?;
+        Ok(config)
+    }
+}
\ No newline at end of file
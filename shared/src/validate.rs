@@ -0,0 +1,8 @@
+use crate::error::AppResult;
+
+/// Input checks shared by the REST handlers and the GraphQL resolvers, so
+/// both accept exactly the same requests. Implemented next to each input
+/// type; every bad field is reported at once as `ValidationFields`.
+pub trait Validate {
+    fn validate(&self) -> AppResult<()>;
+}
\ No newline at end of file